@@ -1,3 +1,4 @@
+mod daemon;
 mod handler;
 mod utils;
 mod widgets;
@@ -27,6 +28,9 @@ pub struct Cmds {
     #[arg(long, require_equals = true)]
     /// specify the ClashTUI config directory
     pub config_dir: Option<std::path::PathBuf>,
+    #[arg(long, global = true)]
+    /// use a named controller from `controllers` in config.yaml
+    pub controller: Option<String>,
     #[arg(long, short, action=clap::ArgAction::Count)]
     /// increase log level, default is Warning
     pub verbose: u8,
@@ -81,6 +85,29 @@ pub(crate) enum ArgCommand {
         #[command(subcommand)]
         command: ServiceCommand,
     },
+    /// rule-provider related
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
+    /// query the core's DNS
+    Dns {
+        #[command(subcommand)]
+        command: DnsCommand,
+    },
+    /// list or export connections
+    Conn {
+        #[command(subcommand)]
+        command: ConnCommand,
+    },
+    /// capture the core's logs
+    Logs {
+        #[command(subcommand)]
+        command: LogsCommand,
+    },
+    /// keep profiles and providers updated and watch the core until stopped,
+    /// logging to clashtui.log
+    Daemon,
     /// set proxy mode,
     /// leave empty to get current mode
     Mode {
@@ -164,6 +191,10 @@ enum ProfileCommand {
         /// update profile with proxyprovider removed
         #[arg(long)]
         without_proxyprovider: bool,
+        /// apply downloads without showing what they change,
+        /// needed when stdin is not a terminal, e.g. in cron
+        #[arg(long)]
+        auto_confirm: bool,
         /// filter by profile type
         #[arg(long, value_enum)]
         r#type: Option<ProfileTypeFilter>,
@@ -174,6 +205,30 @@ enum ProfileCommand {
         #[arg(short, long)]
         name: Option<String>,
     },
+    /// set how often a URL profile is updated in the background by the TUI
+    Interval {
+        /// the profile name
+        #[arg(short, long)]
+        name: String,
+        /// hours between updates, 0 to disable,
+        /// auto to follow the provider's profile-update-interval
+        hours: String,
+    },
+    /// list the earlier versions kept of a profile, newest first
+    History {
+        /// the profile name
+        #[arg(short, long)]
+        name: String,
+    },
+    /// put an earlier version of a profile back
+    Rollback {
+        /// the profile name
+        #[arg(short, long)]
+        name: String,
+        /// the version to restore, 1 being the newest, see `profile history`
+        #[arg(long, default_value_t = 1)]
+        to: usize,
+    },
     /// list all profile
     List {
         /// without domain hint
@@ -185,6 +240,89 @@ enum ProfileCommand {
     },
 }
 
+#[derive(clap::Subcommand)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum RulesCommand {
+    /// update the given rule provider or all
+    Update {
+        /// the rule provider name, all providers if omitted
+        #[arg(short, long)]
+        name: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum DnsCommand {
+    /// resolve a domain like the core does, marking fake-ip answers
+    Query {
+        /// the domain name
+        name: String,
+        /// record type, e.g. A, AAAA, CNAME, MX, TXT
+        #[arg(short, long, default_value = "A")]
+        r#type: String,
+    },
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum OutputFormat {
+    /// aligned columns for reading
+    Table,
+    /// one JSON object per line
+    Jsonl,
+    /// comma separated values with a header
+    Csv,
+}
+
+impl OutputFormat {
+    fn export_format(self) -> Option<crate::functions::export::Format> {
+        use crate::functions::export::Format;
+        match self {
+            OutputFormat::Table => None,
+            OutputFormat::Jsonl => Some(Format::Jsonl),
+            OutputFormat::Csv => Some(Format::Csv),
+        }
+    }
+}
+
+#[derive(clap::Subcommand)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum ConnCommand {
+    /// list open connections
+    List {
+        /// output format, jsonl and csv include every field
+        #[arg(short, long, value_enum, default_value = "table")]
+        output: OutputFormat,
+        /// only connections whose host, process, rule or chain contains this
+        #[arg(short, long)]
+        filter: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+#[cfg_attr(debug_assertions, derive(Debug))]
+enum LogsCommand {
+    /// print what the core logs over a while
+    Dump {
+        /// output format
+        #[arg(short, long, value_enum, default_value = "table")]
+        output: OutputFormat,
+        /// log level, e.g. debug, info, warning, error; the core's level if omitted
+        #[arg(short, long)]
+        level: Option<String>,
+        /// seconds to capture
+        #[arg(short, long, default_value_t = 10)]
+        duration: u64,
+    },
+    /// append what the core logs to the log archive until interrupted
+    Record {
+        /// log level, e.g. debug, info, warning, error; the core's level if omitted
+        #[arg(short, long)]
+        level: Option<String>,
+    },
+}
+
 #[derive(clap::Subcommand)]
 #[cfg_attr(debug_assertions, derive(Debug))]
 enum ServiceCommand {
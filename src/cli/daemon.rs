@@ -0,0 +1,201 @@
+//! `clashtui daemon`: the background work of the TUI, without the TUI
+//!
+//! Meant to run as a service on machines nobody opens the TUI on. Every job
+//! loops on its own interval from [Daemon] and reports to `clashtui.log` only.
+
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+
+use crate::config::{AutoSelect, CONFIG, Daemon};
+use crate::functions::autoselect::{self, MIN_INTERVAL};
+use crate::functions::command;
+use crate::functions::file::profile::{AutoUpdate, AutoUpdater};
+use crate::functions::latency;
+use crate::functions::restful::capability::{self, Capability};
+use crate::functions::restful::client::client;
+use crate::functions::restful::{control, providers, rules};
+
+pub(super) fn run() -> Result<()> {
+    let settings: Daemon = CONFIG.cfg_file.daemon.clone();
+    log::info!("clashtui daemon started");
+    tokio::runtime::Runtime::new()?.block_on(async move {
+        match tokio::task::spawn_blocking(|| capability::probe(&*client())).await? {
+            Ok(caps) => capability::set_current(caps),
+            Err(e) => log::warn!("Controller unreachable, capabilities are assumed: {e}"),
+        }
+
+        tokio::spawn(update_profiles());
+        if settings.liveness_interval > 0 {
+            tokio::spawn(check_liveness(
+                settings.liveness_interval,
+                settings.restart_core,
+            ));
+        }
+        every(
+            settings.provider_update_interval,
+            "Provider update",
+            update_providers,
+        );
+        every(settings.health_check_interval, "Health check", health_check);
+        for policy in CONFIG.cfg_file.auto_select.iter().cloned() {
+            tokio::spawn(auto_select(policy));
+        }
+
+        std::future::pending::<()>().await;
+        Ok(())
+    })
+}
+
+/// Run `job` every `secs` seconds, starting now; `0` disables it
+fn every(secs: u64, what: &'static str, job: fn() -> Result<String>) {
+    if secs == 0 {
+        log::info!("{what} disabled");
+        return;
+    }
+    tokio::spawn(async move {
+        loop {
+            match tokio::task::spawn_blocking(job).await.unwrap() {
+                Ok(summary) => log::info!("{what}: {summary}"),
+                Err(e) => log::warn!("{what}: {e}"),
+            }
+            tokio::time::sleep(Duration::from_secs(secs)).await;
+        }
+    });
+}
+
+async fn update_profiles() {
+    let mut updater = AutoUpdater::new(CONFIG.cfg_file.auto_apply_updates);
+    loop {
+        // Profiles are local files
+        if CONFIG.ensure_local().is_ok() {
+            for (name, result) in updater.update_due().await {
+                match result {
+                    AutoUpdate::Updated(r) => {
+                        log::info!("Profile {name} updated ({} resources)", r.net_updates.len())
+                    }
+                    AutoUpdate::Held(diff) => log::warn!(
+                        "Scheduled update of profile {name} held, run `clashtui profile update --name {name}` to review:\n{diff}"
+                    ),
+                    AutoUpdate::Failed(e) => {
+                        log::warn!("Scheduled update of profile {name} failed: {e}")
+                    }
+                }
+            }
+        }
+        tokio::time::sleep(AutoUpdater::CHECK_EVERY).await;
+    }
+}
+
+/// Log when the core goes down or comes back, not on every check
+async fn check_liveness(secs: u64, restart: bool) {
+    let mut healthy = true;
+    loop {
+        let result = tokio::task::spawn_blocking(move || check_core(restart))
+            .await
+            .unwrap();
+        match result {
+            Ok(()) if !healthy => {
+                log::info!("Core is back");
+                healthy = true;
+            }
+            Err(e) if healthy => {
+                log::warn!("Core check failed: {e}");
+                healthy = false;
+            }
+            _ => {}
+        }
+        tokio::time::sleep(Duration::from_secs(secs)).await;
+    }
+}
+
+fn check_core(restart: bool) -> Result<()> {
+    // Without a service name or with a remote controller there is no service to ask
+    if CONFIG.ensure_local().is_ok() && command::is_core_service_running() == Some(false) {
+        if !restart {
+            bail!("the core service is not running");
+        }
+        log::warn!("The core service is not running, starting it");
+        command::start_core_service(None, CONFIG.core_type())
+            .map_err(|e| anyhow!("failed to start the core service: {e}"))?;
+    }
+    control::check_connectivity().map_err(|e| anyhow!("no connectivity through the core: {e}"))
+}
+
+/// Providers the core downloads itself; `File` and `Inline` ones have nothing to fetch
+fn is_remote(vehicle_type: &str) -> bool {
+    vehicle_type == "HTTP"
+}
+
+fn update_providers() -> Result<String> {
+    let mut updated = 0;
+    let mut failed = Vec::new();
+    if capability::supports(Capability::ProxyProviders) {
+        for (name, provider) in providers::fetch_proxy_providers()? {
+            if !is_remote(&provider.vehicle_type) {
+                continue;
+            }
+            match providers::update_proxy_provider(&name) {
+                Ok(()) => updated += 1,
+                Err(e) => failed.push(format!("{name}: {e}")),
+            }
+        }
+    }
+    if capability::supports(Capability::RuleProviders) {
+        for (name, provider) in rules::fetch_rule_providers()? {
+            if !is_remote(&provider.vehicle_type) {
+                continue;
+            }
+            match rules::update_rule_provider(&name) {
+                Ok(()) => updated += 1,
+                Err(e) => failed.push(format!("{name}: {e}")),
+            }
+        }
+    }
+    if !failed.is_empty() {
+        bail!("{updated} updated, failed: {}", failed.join(", "));
+    }
+    Ok(format!("{updated} updated"))
+}
+
+fn health_check() -> Result<String> {
+    if !capability::supports(Capability::ProxyProviders) {
+        return Ok("the core has no proxy providers".to_owned());
+    }
+    let mut checked = 0;
+    let mut failed = Vec::new();
+    for (name, provider) in providers::fetch_proxy_providers()? {
+        if provider.is_compatible() {
+            continue;
+        }
+        match providers::healthcheck_proxy_provider(&name) {
+            Ok(()) => checked += 1,
+            Err(e) => failed.push(format!("{name}: {e}")),
+        }
+    }
+    if !failed.is_empty() {
+        bail!("{checked} checked, failed: {}", failed.join(", "));
+    }
+    Ok(format!("{checked} providers checked"))
+}
+
+/// The `auto_select` policies, as the TUI runs them
+async fn auto_select(policy: AutoSelect) {
+    let interval = Duration::from_secs(policy.interval.max(MIN_INTERVAL));
+    loop {
+        let p = policy.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let outcome = autoselect::run_once(&p)?;
+            latency::record(outcome.samples);
+            Ok::<_, anyhow::Error>(outcome.switch)
+        })
+        .await
+        .unwrap();
+        match result {
+            Ok(Some(switch)) => log::info!("{switch}"),
+            Ok(None) => {}
+            Err(e) => log::warn!("auto-select {}: {e}", policy.group),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -2,6 +2,7 @@ use anyhow::Result;
 use anyhow::bail;
 
 use super::*;
+use crate::functions::update;
 
 pub fn handle_cli(cmd: Cmds) -> Result<()> {
     let Some(command) = cmd.command else {
@@ -12,6 +13,11 @@ pub fn handle_cli(cmd: Cmds) -> Result<()> {
         ArgCommand::Profile { command } => handle_profile(command),
         #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
         ArgCommand::Service { command } => handle_service(command),
+        ArgCommand::Rules { command } => handle_rules(command),
+        ArgCommand::Dns { command } => handle_dns(command),
+        ArgCommand::Conn { command } => handle_conn(command),
+        ArgCommand::Logs { command } => handle_logs(command),
+        ArgCommand::Daemon => daemon::run(),
         ArgCommand::Mode { mode } => handle_mode(mode),
         ArgCommand::Update { ci, target } => handle_update(ci, target),
     }
@@ -20,12 +26,16 @@ pub fn handle_cli(cmd: Cmds) -> Result<()> {
 // ── Profile ──────────────────────────────────────────────────────────
 
 fn handle_profile(command: ProfileCommand) -> Result<()> {
+    if !matches!(command, ProfileCommand::List { .. }) {
+        crate::config::CONFIG.ensure_local()?;
+    }
     match command {
         ProfileCommand::Update {
             all,
             name,
             with_proxy,
             without_proxyprovider,
+            auto_confirm,
             r#type: type_filter,
         } => {
             let profiles: Vec<crate::config::database::Profile> = if all {
@@ -65,13 +75,14 @@ fn handle_profile(command: ProfileCommand) -> Result<()> {
             for pf in &profiles {
                 println!("Updating profile: {}", pf.name);
                 if without_proxyprovider {
-                    let mut pm = crate::config::CONFIG.data.lock().unwrap();
+                    let mut pm = crate::config::CONFIG.data_mut();
                     pm.set_no_pp(&pf.name, true);
                     pm.to_file()?;
                 }
                 let pf = pf.clone();
+                let review = async |diff| review_update(&diff, auto_confirm);
                 match rt.block_on(crate::functions::file::profile::update_profile(
-                    pf, with_proxy,
+                    pf, with_proxy, review,
                 )) {
                     Ok(result) => {
                         println!(
@@ -103,6 +114,40 @@ fn handle_profile(command: ProfileCommand) -> Result<()> {
             }
             Ok(())
         }
+        ProfileCommand::Interval { name, hours } => {
+            let hours = crate::functions::file::profile::parse_update_interval(&hours)?;
+            crate::functions::file::profile::db::set_update_interval(&name, hours)?;
+            match hours {
+                Some(0) => println!("Background update disabled for {name}"),
+                Some(h) => println!("{name} is updated every {h} hours"),
+                None => println!("{name} follows the provider's update interval"),
+            }
+            Ok(())
+        }
+        ProfileCommand::History { name } => {
+            use crate::functions::file::history;
+            let versions = history::versions(&history::profile_dir(&name))?;
+            if versions.is_empty() {
+                println!("No earlier versions of {name} are kept");
+            }
+            for (i, v) in versions.iter().enumerate() {
+                println!("{:>3}  {}  {}  {} bytes", i + 1, v.stamp, v.hash, v.size);
+            }
+            Ok(())
+        }
+        ProfileCommand::Rollback { name, to } => {
+            let Some(pf) = crate::functions::file::profile::db::get(&name) else {
+                eprintln!("Profile not found in database: {name}");
+                std::process::exit(1);
+            };
+            let rt = tokio::runtime::Runtime::new()?;
+            let version = rt.block_on(crate::functions::file::profile::rollback(pf, to))?;
+            println!(
+                "{name} rolled back to {} ({}, {} bytes)",
+                version.stamp, version.hash, version.size
+            );
+            Ok(())
+        }
         ProfileCommand::List {
             name_only,
             r#type: type_filter,
@@ -140,6 +185,24 @@ fn handle_profile(command: ProfileCommand) -> Result<()> {
     }
 }
 
+/// Print what a download changes and ask whether to apply it
+fn review_update(diff: &crate::functions::file::profile::diff::ProfileDiff, auto: bool) -> bool {
+    use std::io::IsTerminal;
+    if auto {
+        println!("{diff}");
+        return true;
+    }
+    if !std::io::stdin().is_terminal() {
+        eprintln!("{diff}\nNot applied, pass --auto-confirm to update without a terminal");
+        return false;
+    }
+    Confirm::default()
+        .append_prompt(diff)
+        .append_prompt("Apply the update?")
+        .interact()
+        .unwrap_or(false)
+}
+
 // ── Service ──────────────────────────────────────────────────────────
 
 #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
@@ -151,12 +214,14 @@ fn handle_service(command: ServiceCommand) -> Result<()> {
                     .map_err(|e| anyhow::anyhow!("Soft restart failed: {e}"))?;
                 println!("Core restarted (soft).");
             } else {
+                crate::config::CONFIG.ensure_local()?;
                 let output = crate::functions::command::restart_service(None)?;
                 println!("{output}");
             }
             Ok(())
         }
         ServiceCommand::Stop => {
+            crate::config::CONFIG.ensure_local()?;
             let output = crate::functions::command::stop_service(None)?;
             println!("{output}");
             Ok(())
@@ -164,6 +229,165 @@ fn handle_service(command: ServiceCommand) -> Result<()> {
     }
 }
 
+// ── DNS ──────────────────────────────────────────────────────────────
+
+fn handle_dns(command: DnsCommand) -> Result<()> {
+    use crate::functions::restful::dns;
+
+    match command {
+        DnsCommand::Query { name, r#type } => {
+            let resp =
+                dns::query(&name, &r#type).map_err(|e| anyhow::anyhow!("DNS query failed: {e}"))?;
+            for line in resp.build(&dns::FakeIpRange::current()) {
+                println!("{line}");
+            }
+            Ok(())
+        }
+    }
+}
+
+// ── Connections / Logs ───────────────────────────────────────────────
+
+fn handle_conn(command: ConnCommand) -> Result<()> {
+    use crate::functions::restful::connection;
+
+    match command {
+        ConnCommand::List { output, filter } => {
+            let conns = connection::get_connections()
+                .map_err(|e| anyhow::anyhow!("Failed to fetch connections: {e}"))?
+                .connections
+                .unwrap_or_default();
+            let conns: Vec<_> = conns
+                .iter()
+                .filter(|c| {
+                    filter.as_deref().is_none_or(|pat| {
+                        c.metadata.host.contains(pat)
+                            || c.metadata.process.contains(pat)
+                            || c.rule.as_deref().unwrap_or_default().contains(pat)
+                            || c.chains.iter().any(|n| n.contains(pat))
+                    })
+                })
+                .collect();
+            let Some(format) = output.export_format() else {
+                for c in conns {
+                    let host = if c.metadata.host.is_empty() {
+                        c.metadata.destination_ip.as_deref().unwrap_or_default()
+                    } else {
+                        &c.metadata.host
+                    };
+                    println!(
+                        "{:<40} {:<16} {:<24} {:<32} {:>10} {:>10}",
+                        format!("{host}:{}", c.metadata.destination_port),
+                        c.metadata.process,
+                        c.rule.as_deref().unwrap_or("-"),
+                        c.chains.first().map(String::as_str).unwrap_or("-"),
+                        c.download,
+                        c.upload
+                    );
+                }
+                return Ok(());
+            };
+            crate::functions::export::write_conns(&mut std::io::stdout().lock(), format, conns)?;
+            Ok(())
+        }
+    }
+}
+
+fn handle_logs(command: LogsCommand) -> Result<()> {
+    use crate::functions::restful::api_log;
+
+    match command {
+        LogsCommand::Dump {
+            output,
+            level,
+            duration,
+        } => {
+            let entries = api_log::collect_logs(level.as_deref(), duration)
+                .map_err(|e| anyhow::anyhow!("Failed to read logs: {e}"))?;
+            let Some(format) = output.export_format() else {
+                for e in &entries {
+                    println!("{} {} {}", e.time, e.type_, e.payload);
+                }
+                return Ok(());
+            };
+            crate::functions::export::write_logs(&mut std::io::stdout().lock(), format, &entries)?;
+            Ok(())
+        }
+        LogsCommand::Record { level } => {
+            use crate::functions::log_archive::ArchiveWriter;
+            use std::time::{Duration, Instant};
+
+            let settings = crate::config::CONFIG
+                .cfg_file
+                .log_archive
+                .clone()
+                .unwrap_or_default();
+            let mut writer = ArchiveWriter::new(crate::config::log_archive_dir(), &settings);
+            println!("Recording core logs to {}", writer.dir().display());
+            loop {
+                let started = Instant::now();
+                let mut write_error = None;
+                let result = api_log::stream_logs(level.as_deref(), 3600, &mut |e| {
+                    if write_error.is_none()
+                        && let Err(err) = writer.append(&e)
+                    {
+                        write_error = Some(err);
+                    }
+                });
+                if let Some(e) = write_error {
+                    bail!("Failed to write {}: {e}", writer.dir().display());
+                }
+                if let Err(e) = &result {
+                    eprintln!("Log stream: {e}, reconnecting");
+                }
+                // The core went away; do not hammer it
+                if result.is_err() || started.elapsed() < Duration::from_secs(5) {
+                    std::thread::sleep(Duration::from_secs(5));
+                }
+            }
+        }
+    }
+}
+
+// ── Rules ────────────────────────────────────────────────────────────
+
+fn handle_rules(command: RulesCommand) -> Result<()> {
+    use crate::functions::restful::rules;
+
+    match command {
+        RulesCommand::Update { name } => {
+            let names: Vec<String> = match name {
+                Some(name) => vec![name],
+                None => rules::fetch_rule_providers()
+                    .map_err(|e| anyhow::anyhow!("Failed to fetch rule providers: {e}"))?
+                    .into_keys()
+                    .collect(),
+            };
+            if names.is_empty() {
+                println!("No rule providers.");
+                return Ok(());
+            }
+
+            let mut failed = 0;
+            for name in &names {
+                println!("Updating rule provider: {name}");
+                if let Err(e) = rules::update_rule_provider(name) {
+                    eprintln!("  Error: {e}");
+                    failed += 1;
+                }
+            }
+            if failed > 0 {
+                bail!(
+                    "{failed} of {} rule providers failed to update",
+                    names.len()
+                );
+            }
+            println!("Done.");
+            Ok(())
+        }
+    }
+}
+
 // ── Mode ─────────────────────────────────────────────────────────────
 
 fn handle_mode(mode: Option<ModeCommand>) -> Result<()> {
@@ -200,8 +424,7 @@ fn handle_update(ci: bool, target: Target) -> Result<()> {
 
 fn update_clashtui(ci: bool) -> Result<()> {
     let current = env!("CARGO_PKG_VERSION");
-    let repo = "JohanChane/clashtui";
-    let release = fetch_latest_release(repo, ci)?;
+    let release = update::fetch_latest_release(&update::release_api(), update::CLASHTUI_REPO, ci)?;
     let latest = release.tag_name.trim_start_matches('v');
 
     if latest == current {
@@ -211,104 +434,41 @@ fn update_clashtui(ci: bool) -> Result<()> {
 
     println!("New version available: v{latest} (current: v{current})");
 
-    let asset = find_linux_asset(&release.assets)?;
+    let asset = update::find_linux_asset(&release.assets)?;
     println!("Downloading {}...", asset.name);
     download_and_replace(&asset.browser_download_url)?;
     println!("Updated to v{latest}.");
     Ok(())
 }
 
+/// Ask the core to upgrade itself, download the release from GitHub if it cannot
 fn update_mihomo(ci: bool) -> Result<()> {
-    let current = crate::functions::restful::control::version()
-        .map_err(|e| anyhow::anyhow!("Failed to fetch core version: {e}"))?;
-    let current = current.trim().trim_matches('"');
+    use crate::functions::restful::control::{self, Upgrade};
 
-    let repo = "MetaCubeX/mihomo";
-    let release = fetch_latest_release(repo, ci)?;
-    let latest = release.tag_name.trim_start_matches('v');
+    let current =
+        control::version().map_err(|e| anyhow::anyhow!("Failed to fetch core version: {e}"))?;
+    let current = update::core_version(&current);
 
-    if latest == current.trim_start_matches('v') {
-        println!("Already up to date (v{latest}).");
-        return Ok(());
-    }
-
-    println!("New version available: v{latest} (current: {current})");
-
-    let mihomo_path = {
-        let path = &crate::config::CONFIG.cfg_file.mihomo.core.config_path;
-        if path.is_empty() {
-            std::env::current_dir()?.join("mihomo")
-        } else {
-            std::path::PathBuf::from(path)
-                .parent()
-                .unwrap_or(std::path::Path::new("."))
-                .join("mihomo")
+    println!("Upgrading mihomo {current} through the controller...");
+    match control::upgrade(Upgrade::Core { alpha: ci }) {
+        Ok(_) => {
+            println!("Upgraded, mihomo restarts itself.");
+            return Ok(());
         }
-    };
-
-    let asset = find_linux_asset(&release.assets)?;
-    println!("Downloading {}...", asset.name);
-    download_to_path(&asset.browser_download_url, &mihomo_path)?;
-    println!("Updated mihomo to v{latest}.");
-    Ok(())
-}
-
-// ── GitHub helpers ───────────────────────────────────────────────────
-
-#[derive(serde::Deserialize)]
-struct GhRelease {
-    tag_name: String,
-    assets: Vec<GhAsset>,
-}
-
-#[derive(serde::Deserialize)]
-struct GhAsset {
-    name: String,
-    browser_download_url: String,
-}
-
-fn fetch_latest_release(repo: &str, ci: bool) -> Result<GhRelease> {
-    let url = if ci {
-        format!("https://api.github.com/repos/{repo}/releases?per_page=1")
-    } else {
-        format!("https://api.github.com/repos/{repo}/releases/latest")
-    };
-
-    let mut releases: Vec<GhRelease> = if ci {
-        minreq::get(url)
-            .with_header("User-Agent", "clashtui")
-            .with_timeout(10)
-            .send()
-            .map_err(|e| anyhow::anyhow!("Failed to fetch releases: {e}"))?
-            .json()
-            .map_err(|e| anyhow::anyhow!("Failed to parse releases: {e}"))?
-    } else {
-        vec![
-            minreq::get(url)
-                .with_header("User-Agent", "clashtui")
-                .with_timeout(10)
-                .send()
-                .map_err(|e| anyhow::anyhow!("Failed to fetch latest release: {e}"))?
-                .json()
-                .map_err(|e| anyhow::anyhow!("Failed to parse release: {e}"))?,
-        ]
-    };
-
-    if releases.is_empty() {
-        bail!("No releases found");
+        Err(e) => println!("Controller upgrade failed: {e}"),
     }
-    Ok(releases.remove(0))
-}
 
-fn find_linux_asset(assets: &[GhAsset]) -> Result<&GhAsset> {
-    assets
-        .iter()
-        .find(|a| {
-            let n = a.name.to_lowercase();
-            n.contains("linux") && !n.contains("musl") && !n.contains("aarch")
-        })
-        .or_else(|| assets.first())
-        .ok_or_else(|| anyhow::anyhow!("No suitable asset found"))
+    crate::config::CONFIG.ensure_local()?;
+    println!("Falling back to the GitHub release.");
+    let msg = update::mihomo_from_release(
+        &update::release_api(),
+        ci,
+        &current,
+        &update::mihomo_path()?,
+        |s| println!("{s}"),
+    )?;
+    println!("{msg}");
+    Ok(())
 }
 
 fn download_and_replace(url: &str) -> Result<()> {
@@ -321,31 +481,9 @@ fn download_and_replace(url: &str) -> Result<()> {
     }
     new_path.set_extension(new_ext);
 
-    download_to_path(url, &new_path)?;
+    update::download_to_path(url, &new_path)?;
 
     self_replace::self_replace(&new_path)?;
     let _ = std::fs::remove_file(&new_path);
     Ok(())
 }
-
-fn download_to_path(url: &str, dest: &std::path::Path) -> Result<()> {
-    let response = minreq::get(url)
-        .with_header("User-Agent", "clashtui")
-        .with_timeout(300)
-        .send()
-        .map_err(|e| anyhow::anyhow!("Download failed: {e}"))?;
-
-    if let Some(parent) = dest.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-    std::fs::write(dest, response.as_bytes())?;
-
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let mut perms = std::fs::metadata(dest)?.permissions();
-        perms.set_mode(0o755);
-        std::fs::set_permissions(dest, perms)?;
-    }
-    Ok(())
-}
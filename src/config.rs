@@ -9,21 +9,25 @@
 //! * `Folder` sing-box/templates/
 //! * `Folder` sing-box/proxy-providers/
 
-use anyhow::{Context, Result, ensure};
+use anyhow::{Context, Result, bail, ensure};
 use core::*;
 use database::*;
 use std::{
     path::PathBuf,
-    sync::atomic::{AtomicBool, Ordering},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     sync::{Mutex, OnceLock},
 };
 use util::*;
 
 mod core;
-pub use core::{CoreType, ServiceController};
+pub use core::{
+    AutoSelect, ControllerEndpoint, ControllerTls, CoreType, Daemon, LogArchive, SelectPolicy,
+    ServiceController,
+};
 #[macro_use]
 mod util;
 pub mod database;
+pub use util::write_atomic;
 
 /// Load using [init]
 pub const CONFIG: Wrapper = Wrapper;
@@ -42,6 +46,16 @@ pub fn is_core_mismatch() -> bool {
     CORE_MISMATCH.load(Ordering::Acquire)
 }
 
+/// `0` is the local core, `i + 1` is `cfg_file.controllers[i]`
+static ACTIVE_CONTROLLER: AtomicUsize = AtomicUsize::new(0);
+static CONTROLLER_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Bumped whenever another controller is selected, long-lived connections
+/// compare it to know when to reconnect
+pub fn controller_generation() -> u64 {
+    CONTROLLER_GENERATION.load(Ordering::Acquire)
+}
+
 static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
 static CONFIG_ROOT: OnceLock<PathBuf> = OnceLock::new();
 static _CONFIG: OnceLock<Config> = OnceLock::new();
@@ -62,7 +76,11 @@ impl std::ops::Deref for Wrapper {
 /// Do Not use it directly, use [CONFIG] instead
 pub struct Config {
     pub cfg_file: ConfigFile,
-    pub data: Mutex<ProfileManager>,
+    /// Borrowed through [Config::data] and [Config::data_mut]
+    data: Mutex<Data>,
+    /// Locked while [Config::data_mut] is borrowed, so that clashtui processes
+    /// sharing the config dir take turns changing clashtui.db
+    data_lock: Option<std::fs::File>,
     pub external_controller: String,
     pub proxy_addr: String,
     pub secret: Option<String>,
@@ -112,7 +130,10 @@ impl Config {
                 }
             }
         }
-        let data: Mutex<ProfileManager> = data.into();
+        let data = Mutex::new(Data::new(data, data_path()));
+        let data_lock = std::fs::File::create(DATA_DIR.get().unwrap().join(defs::LOCK_FILE))
+            .inspect_err(|e| log::warn!("clashtui.db is used without a lock: {e}"))
+            .ok();
         if !cfg_file.mihomo.core.config_path.is_empty() {
             cfg_file.mihomo.core.config_path =
                 std::path::absolute(std::path::PathBuf::from(&cfg_file.mihomo.core.config_path))
@@ -173,10 +194,17 @@ impl Config {
             };
             (url, secret)
         };
+        check_controllers(&cfg_file.controllers)?;
+        for endpoint in &mut cfg_file.controllers {
+            endpoint.address = endpoint.url();
+        }
+        let external_controller =
+            basic_info.get_external_controller(&cfg_file.mihomo.core.config_dir);
         Ok(Self {
             cfg_file,
             data,
-            external_controller: basic_info.get_external_controller(),
+            data_lock,
+            external_controller,
             proxy_addr: basic_info
                 .get_proxy_addr()
                 .context("Failed to determine proxy port")?,
@@ -186,24 +214,260 @@ impl Config {
             singbox_secret,
         })
     }
+    /// Core type of the active controller, see [Config::active_controller]
     pub fn core_type(&self) -> CoreType {
-        self.data.lock().unwrap().core_type
+        match self.active_controller() {
+            Some(endpoint) => endpoint.core_type,
+            None => self.data().core_type,
+        }
+    }
+    /// The profile data for reading.
+    ///
+    /// Changes other processes made to clashtui.db are picked up at most
+    /// [Data::RECHECK_AFTER] late, without taking the lock.
+    pub fn data(&self) -> DataRef<'_> {
+        let mut data = self.data.lock().unwrap();
+        if data.checked_at.elapsed() >= Data::RECHECK_AFTER {
+            data.reload_if_changed();
+        }
+        DataRef { data }
+    }
+    /// The profile data for changing, reloaded first if another process changed
+    /// clashtui.db.
+    ///
+    /// Other clashtui processes wait for the returned guard to drop, so a change
+    /// made through it and saved with [ProfileManager::to_file] is not lost to
+    /// a concurrent one. Only clashtui.db is covered, profile files and their
+    /// history are written without the lock.
+    pub fn data_mut(&self) -> DataGuard<'_> {
+        let mut data = self.data.lock().unwrap();
+        let lock = self.data_lock.as_ref().filter(|file| {
+            file.lock()
+                .inspect_err(|e| log::warn!("Failed to lock clashtui.db: {e}"))
+                .is_ok()
+        });
+        data.reload_if_changed();
+        DataGuard { data, lock }
     }
     pub fn save(&self) -> Result<()> {
-        self.data.lock().unwrap().to_file()
+        self.data_mut().to_file()
     }
     pub fn controller_for_core(&self) -> &str {
-        match self.data.lock().unwrap().core_type {
+        if let Some(endpoint) = self.active_controller() {
+            return &endpoint.address;
+        }
+        match self.data().core_type {
             CoreType::Mihomo => &self.external_controller,
             CoreType::Singbox => &self.singbox_external_controller,
         }
     }
     pub fn secret_for_core(&self) -> Option<&str> {
-        match self.data.lock().unwrap().core_type {
+        if let Some(endpoint) = self.active_controller() {
+            return endpoint.secret.as_deref().filter(|s| !s.is_empty());
+        }
+        match self.data().core_type {
             CoreType::Mihomo => self.secret.as_deref(),
             CoreType::Singbox => self.singbox_secret.as_deref(),
         }
     }
+    /// Trust for an `https://` controller
+    pub fn controller_tls(&self) -> &ControllerTls {
+        self.active_controller()
+            .and_then(|endpoint| endpoint.tls.as_ref())
+            .unwrap_or(&self.cfg_file.controller_tls)
+    }
+
+    /// The selected entry of `controllers`, None for the local core
+    pub fn active_controller(&self) -> Option<&ControllerEndpoint> {
+        ACTIVE_CONTROLLER
+            .load(Ordering::Acquire)
+            .checked_sub(1)
+            .and_then(|i| self.cfg_file.controllers.get(i))
+    }
+    /// Service and file operations only make sense for the local core
+    pub fn is_remote(&self) -> bool {
+        self.active_controller().is_some()
+    }
+    /// Err naming the active controller if it is remote
+    pub fn ensure_local(&self) -> Result<()> {
+        match self.active_controller() {
+            Some(endpoint) => bail!(
+                "`{}` is a remote controller, service and file operations are disabled",
+                endpoint.name
+            ),
+            None => Ok(()),
+        }
+    }
+    /// Point every API call at the controller named `name`, or at the
+    /// local core with None
+    pub fn use_controller(&self, name: Option<&str>) -> Result<()> {
+        let slot = match name {
+            Some(name) => find_controller(&self.cfg_file.controllers, name)? + 1,
+            None => 0,
+        };
+        self.set_active_slot(slot);
+        Ok(())
+    }
+    /// Select the controller after the active one, wrapping to the local core
+    pub fn cycle_controller(&self) -> Option<&ControllerEndpoint> {
+        let current = ACTIVE_CONTROLLER.load(Ordering::Acquire);
+        self.set_active_slot(next_slot(current, self.cfg_file.controllers.len()));
+        self.active_controller()
+    }
+    fn set_active_slot(&self, slot: usize) {
+        if ACTIVE_CONTROLLER.swap(slot, Ordering::AcqRel) != slot {
+            set_core_mismatch(false);
+            CONTROLLER_GENERATION.fetch_add(1, Ordering::AcqRel);
+        }
+    }
+}
+
+/// clashtui.db in the config dir
+fn data_path() -> PathBuf {
+    DATA_DIR.get().unwrap().join(defs::DATA_FILE)
+}
+
+/// Tells whether a file was replaced since it was last seen
+#[derive(PartialEq)]
+struct FileStamp {
+    modified: std::time::SystemTime,
+    len: u64,
+}
+
+impl FileStamp {
+    fn of(path: &std::path::Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: meta.modified().ok()?,
+            len: meta.len(),
+        })
+    }
+}
+
+struct Data {
+    pm: ProfileManager,
+    path: PathBuf,
+    /// `path` as of the last load or save by this process
+    seen: Option<FileStamp>,
+    checked_at: std::time::Instant,
+}
+
+impl Data {
+    /// How stale [Config::data] may be
+    const RECHECK_AFTER: std::time::Duration = std::time::Duration::from_secs(1);
+
+    fn new(pm: ProfileManager, path: PathBuf) -> Self {
+        Self {
+            pm,
+            seen: FileStamp::of(&path),
+            path,
+            checked_at: std::time::Instant::now(),
+        }
+    }
+
+    fn reload_if_changed(&mut self) {
+        self.checked_at = std::time::Instant::now();
+        let current = FileStamp::of(&self.path);
+        if current.is_none() || current == self.seen {
+            return;
+        }
+        let loaded = std::fs::File::open(&self.path)
+            .map_err(anyhow::Error::from)
+            .and_then(|fp| Ok(serde_yml::from_reader::<_, ProfileManager>(fp)?));
+        match loaded {
+            Ok(pm) => {
+                log::info!("clashtui.db was changed by another process, reloaded");
+                self.pm = pm;
+            }
+            Err(e) => log::warn!("clashtui.db was changed but cannot be reloaded: {e:#}"),
+        }
+        self.seen = current;
+    }
+}
+
+/// Read access to the [ProfileManager], see [Config::data]
+pub struct DataRef<'a> {
+    data: std::sync::MutexGuard<'a, Data>,
+}
+
+impl std::ops::Deref for DataRef<'_> {
+    type Target = ProfileManager;
+
+    fn deref(&self) -> &ProfileManager {
+        &self.data.pm
+    }
+}
+
+/// Write access to the [ProfileManager], see [Config::data_mut]
+pub struct DataGuard<'a> {
+    data: std::sync::MutexGuard<'a, Data>,
+    lock: Option<&'a std::fs::File>,
+}
+
+impl std::ops::Deref for DataGuard<'_> {
+    type Target = ProfileManager;
+
+    fn deref(&self) -> &ProfileManager {
+        &self.data.pm
+    }
+}
+
+impl std::ops::DerefMut for DataGuard<'_> {
+    fn deref_mut(&mut self) -> &mut ProfileManager {
+        &mut self.data.pm
+    }
+}
+
+impl Drop for DataGuard<'_> {
+    fn drop(&mut self) {
+        // Whatever is on disk now was written by this process
+        self.data.seen = FileStamp::of(&self.data.path);
+        if let Some(file) = self.lock {
+            let _ = file.unlock();
+        }
+    }
+}
+
+/// Names must be unique and neither name nor address may be empty
+fn check_controllers(controllers: &[ControllerEndpoint]) -> Result<()> {
+    for (i, endpoint) in controllers.iter().enumerate() {
+        ensure!(
+            !endpoint.name.is_empty(),
+            "controllers[{i}]: name must not be empty"
+        );
+        ensure!(
+            !endpoint.address.is_empty(),
+            "controller `{}`: address must not be empty",
+            endpoint.name
+        );
+        ensure!(
+            !controllers[..i].iter().any(|e| e.name == endpoint.name),
+            "controller `{}` is defined twice",
+            endpoint.name
+        );
+    }
+    Ok(())
+}
+
+fn find_controller(controllers: &[ControllerEndpoint], name: &str) -> Result<usize> {
+    match controllers.iter().position(|e| e.name == name) {
+        Some(i) => Ok(i),
+        None if controllers.is_empty() => {
+            bail!("Unknown controller `{name}`, no `controllers` in config.yaml")
+        }
+        None => bail!(
+            "Unknown controller `{name}`, expected one of: {}",
+            controllers
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn next_slot(current: usize, count: usize) -> usize {
+    (current + 1) % (count + 1)
 }
 
 pub fn init(base_path: Option<PathBuf>) -> Result<()> {
@@ -373,6 +637,29 @@ pub const DEFAULT_SINGBOX_BASIC_CONFIG: &str = r#"{
 pub fn keymap_path() -> PathBuf {
     DATA_DIR.get().unwrap().join(defs::KEYMAP_FILE)
 }
+pub fn latency_history_path() -> PathBuf {
+    DATA_DIR.get().unwrap().join(defs::LATENCY_FILE)
+}
+pub fn export_dir() -> PathBuf {
+    match CONFIG.cfg_file.export_dir.as_deref() {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => DATA_DIR.get().unwrap().join(defs::EXPORT_DIR),
+    }
+}
+pub fn profile_history_dir() -> PathBuf {
+    DATA_DIR.get().unwrap().join(defs::PROFILE_HISTORY_DIR)
+}
+pub fn log_archive_dir() -> PathBuf {
+    let dir = CONFIG
+        .cfg_file
+        .log_archive
+        .as_ref()
+        .and_then(|a| a.dir.as_deref());
+    match dir {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => DATA_DIR.get().unwrap().join(defs::LOG_ARCHIVE_DIR),
+    }
+}
 
 load_save!(BasicInfo, defs::CORE_OVERRIDE_FILE, no_save, "mihomo");
 load_save!(ConfigFile, defs::CONFIG_FILE);
@@ -387,6 +674,33 @@ mod tests {
         assert!(!is_core_mismatch());
     }
 
+    #[test]
+    fn data_picks_up_changes_from_another_writer() {
+        let dir = std::env::temp_dir().join(format!("clashtui-data-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(defs::DATA_FILE);
+        let write = |pm: &ProfileManager| {
+            write_atomic(&path, serde_yml::to_string(pm).unwrap()).unwrap();
+        };
+        write(&ProfileManager::default());
+        let mut data = Data::new(ProfileManager::default(), path.clone());
+
+        // Nothing changed on disk, what is in memory stays
+        data.pm.core_type = CoreType::Singbox;
+        data.reload_if_changed();
+        assert_eq!(data.pm.core_type, CoreType::Singbox);
+
+        // Another process saves a new current profile
+        let mut other = ProfileManager::default();
+        other.insert("work", ProfileType::File);
+        other.set_current(other.get("work").unwrap());
+        write(&other);
+        data.reload_if_changed();
+        assert_eq!(data.pm, other);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn core_mismatch_flag_toggle() {
         set_core_mismatch(true);
@@ -395,6 +709,44 @@ mod tests {
         assert!(!is_core_mismatch());
     }
 
+    fn endpoints(names: &[&str]) -> Vec<ControllerEndpoint> {
+        names
+            .iter()
+            .map(|name| ControllerEndpoint {
+                name: (*name).to_owned(),
+                address: "192.168.1.1:9090".to_owned(),
+                secret: None,
+                core_type: CoreType::Mihomo,
+                tls: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn find_controller_by_name() {
+        let list = endpoints(&["router", "nas"]);
+        assert_eq!(find_controller(&list, "nas").unwrap(), 1);
+        let err = find_controller(&list, "laptop").unwrap_err().to_string();
+        assert!(err.contains("router, nas"), "{err}");
+        let err = find_controller(&[], "router").unwrap_err().to_string();
+        assert!(err.contains("no `controllers`"), "{err}");
+    }
+
+    #[test]
+    fn check_controllers_rejects_duplicates_and_empty_names() {
+        assert!(check_controllers(&endpoints(&["router", "nas"])).is_ok());
+        assert!(check_controllers(&endpoints(&["router", "router"])).is_err());
+        assert!(check_controllers(&endpoints(&[""])).is_err());
+    }
+
+    #[test]
+    fn cycling_wraps_to_local() {
+        assert_eq!(next_slot(0, 2), 1);
+        assert_eq!(next_slot(1, 2), 2);
+        assert_eq!(next_slot(2, 2), 0);
+        assert_eq!(next_slot(0, 0), 0);
+    }
+
     #[test]
     fn core_data_dir_returns_correct_subdir_per_core_type() {
         let tmp = std::env::temp_dir().join(format!("clashtui-test-{}", fastrand::u32(..)));
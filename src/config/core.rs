@@ -67,6 +67,22 @@ pub struct ConfigFile {
     pub mihomo: MihomoSection,
     pub singbox: SingboxSection,
     pub timeout: Option<u64>,
+    pub controller_tls: ControllerTls,
+    pub controllers: Vec<ControllerEndpoint>,
+    /// Base of the GitHub releases API, for mirrors or a local stand-in
+    pub release_api: Option<String>,
+    pub auto_select: Vec<AutoSelect>,
+    /// Where connection and log exports go, `exports` in the config dir by default
+    pub export_dir: Option<String>,
+    /// Keep the core log stream on disk, off if absent
+    pub log_archive: Option<LogArchive>,
+    /// Jobs of `clashtui daemon`
+    pub daemon: Daemon,
+    /// Earlier versions kept per profile and of the core config, `0` keeps none
+    pub profile_history: usize,
+    /// Let scheduled profile updates apply changes to proxies, groups, providers
+    /// or rules; otherwise they are held until updated by hand
+    pub auto_apply_updates: bool,
     pub extra: Extra,
 }
 impl Default for ConfigFile {
@@ -106,6 +122,15 @@ impl Default for ConfigFile {
                     },
                 },
                 timeout: Default::default(),
+                controller_tls: Default::default(),
+                controllers: Default::default(),
+                release_api: Default::default(),
+                auto_select: Default::default(),
+                export_dir: Default::default(),
+                log_archive: Default::default(),
+                daemon: Default::default(),
+                profile_history: 10,
+                auto_apply_updates: false,
                 extra: Default::default(),
             }
         }
@@ -136,11 +161,147 @@ impl Default for ConfigFile {
                 },
             },
             timeout: Default::default(),
+            controller_tls: Default::default(),
+            controllers: Default::default(),
+            release_api: Default::default(),
+            auto_select: Default::default(),
+            export_dir: Default::default(),
+            log_archive: Default::default(),
+            daemon: Default::default(),
+            profile_history: 10,
+            auto_apply_updates: false,
             extra: Default::default(),
         }
     }
 }
 
+/// Trust settings for an `https://` external controller.
+///
+/// Without either field the controller certificate must chain to a public root.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ControllerTls {
+    /// PEM file with the CA that signed the controller certificate
+    pub ca_file: Option<String>,
+    /// SHA-256 of the controller certificate (DER) in hex, `:` separators allowed.
+    /// A matching certificate is accepted even if it is self-signed.
+    pub pin_sha256: Option<String>,
+}
+
+/// A named controller of a core clashtui does not manage, e.g. on a router
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControllerEndpoint {
+    pub name: String,
+    /// `host:port`, `http(s)://host:port` or `unix:///path/to/sock`
+    pub address: String,
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub core_type: CoreType,
+    /// Trust for an `https://` address, `controller_tls` if absent
+    pub tls: Option<ControllerTls>,
+}
+
+impl ControllerEndpoint {
+    /// `address` with a scheme and without the trailing slash
+    pub fn url(&self) -> String {
+        let address = self.address.trim_end_matches('/');
+        if address.contains("://") {
+            address.to_owned()
+        } else {
+            format!("http://{address}")
+        }
+    }
+}
+
+/// A `Selector` group clashtui tests and switches by itself
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoSelect {
+    pub group: String,
+    #[serde(default)]
+    pub policy: SelectPolicy,
+    /// Seconds between two tests of the group
+    #[serde(default = "AutoSelect::default_interval")]
+    pub interval: u64,
+    /// `lowest-latency` keeps the current node unless another one is faster by more ms
+    #[serde(default)]
+    pub tolerance: u64,
+    /// Test URL, the group's own `testUrl` or the default if absent
+    pub url: Option<String>,
+}
+
+impl AutoSelect {
+    fn default_interval() -> u64 {
+        300
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SelectPolicy {
+    /// The fastest node
+    #[default]
+    LowestLatency,
+    /// The first node that passes, in the group's order
+    Failover,
+    /// Keep the current node until it fails, then the fastest
+    Sticky,
+}
+
+impl std::fmt::Display for SelectPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SelectPolicy::LowestLatency => write!(f, "lowest-latency"),
+            SelectPolicy::Failover => write!(f, "failover"),
+            SelectPolicy::Sticky => write!(f, "sticky"),
+        }
+    }
+}
+
+/// Rotation of the on-disk core log, see [crate::functions::log_archive]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogArchive {
+    /// `core-logs` in the config dir if absent
+    pub dir: Option<String>,
+    /// Start a new file once the current one reaches this size, in MiB
+    pub max_size_mb: u64,
+    /// Delete rotated files last written this many days ago
+    pub max_age_days: u64,
+}
+impl Default for LogArchive {
+    fn default() -> Self {
+        Self {
+            dir: None,
+            max_size_mb: 10,
+            max_age_days: 7,
+        }
+    }
+}
+
+/// What `clashtui daemon` does besides updating profiles, intervals in seconds, `0` disables
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Daemon {
+    /// Check that the core service runs and proxies traffic
+    pub liveness_interval: u64,
+    /// Start the core service again when it is found stopped
+    pub restart_core: bool,
+    /// Re-download the HTTP proxy and rule providers
+    pub provider_update_interval: u64,
+    /// Health-check the nodes of every proxy provider
+    pub health_check_interval: u64,
+}
+impl Default for Daemon {
+    fn default() -> Self {
+        Self {
+            liveness_interval: 60,
+            restart_core: false,
+            provider_update_interval: 24 * 3600,
+            health_check_interval: 0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct Extra {
@@ -220,7 +381,11 @@ impl ServiceController {
 #[serde(rename_all = "kebab-case")]
 /// Get necessary info
 pub struct BasicInfo {
+    #[serde(default)]
     external_controller: String,
+    /// mihomo resolves a relative path against its home dir
+    external_controller_unix: Option<String>,
+    external_controller_tls: Option<String>,
     mixed_port: Option<u32>,
     port: Option<u32>,
     socks_port: Option<u32>,
@@ -232,15 +397,40 @@ impl BasicInfo {
     const LOCALHOST: &str = "127.0.0.1";
     pub const DEFAULT: &str = "external-controller: 127.0.0.1:9090\nmixed-port: 7890";
 
-    pub fn get_external_controller(&self) -> String {
-        let str = match self.external_controller.strip_prefix("http://") {
-            Some(str) => str,
-            None => self.external_controller.as_str(),
+    /// `unix:///path/to/sock` if `external-controller-unix` is set,
+    /// `https://host:port` if `external-controller-tls` is set,
+    /// `http://host:port` otherwise
+    pub fn get_external_controller(&self, home_dir: &str) -> String {
+        if let Some(sock) = self
+            .external_controller_unix
+            .as_deref()
+            .filter(|s| !s.is_empty())
+        {
+            return format!(
+                "unix://{}",
+                std::path::Path::new(home_dir).join(sock).display()
+            );
+        }
+        let (scheme, addr) = match self
+            .external_controller_tls
+            .as_deref()
+            .filter(|s| !s.is_empty())
+        {
+            Some(tls) => ("https", tls.strip_prefix("https://").unwrap_or(tls)),
+            None => match self.external_controller.strip_prefix("https://") {
+                Some(str) => ("https", str),
+                None => (
+                    "http",
+                    self.external_controller
+                        .strip_prefix("http://")
+                        .unwrap_or(&self.external_controller),
+                ),
+            },
         };
-        if let Some(after) = str.strip_prefix("0.0.0.0") {
-            format!("http://{}{}", Self::LOCALHOST, after)
+        if let Some(after) = addr.strip_prefix("0.0.0.0") {
+            format!("{scheme}://{}{}", Self::LOCALHOST, after)
         } else {
-            format!("http://{}", str)
+            format!("{scheme}://{addr}")
         }
     }
     pub fn get_proxy_addr(&self) -> Option<String> {
@@ -388,6 +578,140 @@ open_dir_cmd: ""
         assert_eq!(deser.open_dir_cmd.as_deref(), Some(r#"explorer "%s""#));
     }
 
+    #[test]
+    fn external_controller_tcp() {
+        let info: BasicInfo =
+            serde_yml::from_str("external-controller: 0.0.0.0:9090\nmixed-port: 7890").unwrap();
+        assert_eq!(
+            info.get_external_controller("/opt/mihomo"),
+            "http://127.0.0.1:9090"
+        );
+    }
+
+    #[test]
+    fn external_controller_tls_preferred_over_plain() {
+        let info: BasicInfo = serde_yml::from_str(
+            "external-controller: 127.0.0.1:9090\nexternal-controller-tls: 0.0.0.0:9443",
+        )
+        .unwrap();
+        assert_eq!(
+            info.get_external_controller("/opt/mihomo"),
+            "https://127.0.0.1:9443"
+        );
+    }
+
+    #[test]
+    fn controller_tls_defaults_to_empty() {
+        let cfg: ConfigFile = serde_yml::from_str("timeout: 3").unwrap();
+        assert!(cfg.controller_tls.ca_file.is_none());
+        assert!(cfg.controller_tls.pin_sha256.is_none());
+        let cfg: ConfigFile =
+            serde_yml::from_str("controller_tls:\n  pin_sha256: 'ab:cd'").unwrap();
+        assert_eq!(cfg.controller_tls.pin_sha256.as_deref(), Some("ab:cd"));
+    }
+
+    #[test]
+    fn controllers_deserialize() {
+        let cfg: ConfigFile = serde_yml::from_str("timeout: 3").unwrap();
+        assert!(cfg.controllers.is_empty());
+        let cfg: ConfigFile = serde_yml::from_str(
+            r#"controllers:
+  - name: router
+    address: 192.168.1.1:9090/
+    secret: abc
+  - name: nas
+    address: https://nas.lan:9443
+    core_type: singbox
+    tls:
+      pin_sha256: 'ab:cd'
+"#,
+        )
+        .unwrap();
+        let [router, nas] = cfg.controllers.as_slice() else {
+            panic!("expected two controllers");
+        };
+        assert_eq!(router.url(), "http://192.168.1.1:9090");
+        assert_eq!(router.secret.as_deref(), Some("abc"));
+        assert_eq!(router.core_type, CoreType::Mihomo);
+        assert!(router.tls.is_none());
+        assert_eq!(nas.url(), "https://nas.lan:9443");
+        assert_eq!(nas.core_type, CoreType::Singbox);
+        assert_eq!(
+            nas.tls.as_ref().unwrap().pin_sha256.as_deref(),
+            Some("ab:cd")
+        );
+    }
+
+    #[test]
+    fn auto_select_deserialize() {
+        let cfg: ConfigFile = serde_yml::from_str(
+            r#"auto_select:
+  - group: Proxy
+  - group: Streaming
+    policy: failover
+    interval: 60
+    url: https://cp.cloudflare.com
+"#,
+        )
+        .unwrap();
+        let [proxy, streaming] = cfg.auto_select.as_slice() else {
+            panic!("expected two groups");
+        };
+        assert_eq!(proxy.policy, SelectPolicy::LowestLatency);
+        assert_eq!(proxy.interval, 300);
+        assert_eq!(proxy.tolerance, 0);
+        assert_eq!(streaming.policy, SelectPolicy::Failover);
+        assert_eq!(streaming.interval, 60);
+        assert_eq!(streaming.url.as_deref(), Some("https://cp.cloudflare.com"));
+    }
+
+    #[test]
+    fn log_archive_deserialize() {
+        let cfg: ConfigFile = serde_yml::from_str("timeout: 5").unwrap();
+        assert!(cfg.log_archive.is_none());
+        let cfg: ConfigFile = serde_yml::from_str("log_archive:\n  max_age_days: 30\n").unwrap();
+        let archive = cfg.log_archive.unwrap();
+        assert_eq!(archive.max_age_days, 30);
+        assert_eq!(archive.max_size_mb, 10);
+        assert!(archive.dir.is_none());
+    }
+
+    #[test]
+    fn daemon_deserialize() {
+        let cfg: ConfigFile = serde_yml::from_str("timeout: 5").unwrap();
+        assert_eq!(cfg.daemon.liveness_interval, 60);
+        assert_eq!(cfg.daemon.health_check_interval, 0);
+        assert!(!cfg.auto_apply_updates);
+        let cfg: ConfigFile = serde_yml::from_str(
+            "daemon:
+  restart_core: true
+  health_check_interval: 600
+",
+        )
+        .unwrap();
+        assert!(cfg.daemon.restart_core);
+        assert_eq!(cfg.daemon.health_check_interval, 600);
+        assert_eq!(cfg.daemon.provider_update_interval, 24 * 3600);
+    }
+
+    #[test]
+    fn external_controller_unix_wins_and_resolves_relative_path() {
+        let info: BasicInfo =
+            serde_yml::from_str("external-controller-unix: mihomo.sock\nmixed-port: 7890").unwrap();
+        assert_eq!(
+            info.get_external_controller("/opt/mihomo"),
+            "unix:///opt/mihomo/mihomo.sock"
+        );
+        let info: BasicInfo = serde_yml::from_str(
+            "external-controller: 127.0.0.1:9090\nexternal-controller-unix: /run/mihomo.sock",
+        )
+        .unwrap();
+        assert_eq!(
+            info.get_external_controller("/opt/mihomo"),
+            "unix:///run/mihomo.sock"
+        );
+    }
+
     #[test]
     fn service_controller_bin_name() {
         assert_eq!(ServiceController::Launchd.bin_name(), "launchctl");
@@ -8,6 +8,7 @@ pub struct Profile {
     pub dtype: ProfileType,
     pub no_pp: bool,
     pub update_with_proxy: bool,
+    pub schedule: UpdateSchedule,
 }
 
 impl Default for Profile {
@@ -17,16 +18,48 @@ impl Default for Profile {
             dtype: ProfileType::File,
             no_pp: false,
             update_with_proxy: false,
+            schedule: UpdateSchedule::default(),
         }
     }
 }
 
+/// When a URL profile is refreshed in the background, intervals in hours
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UpdateSchedule {
+    /// Set by the user, `0` disables the background update
+    pub interval: Option<u64>,
+    /// `profile-update-interval` header of the last download
+    pub provider_interval: Option<u64>,
+    /// Unix time of the last successful update
+    pub updated_at: Option<u64>,
+}
+
+impl UpdateSchedule {
+    /// Longest interval accepted from the user or a provider: a year
+    pub const MAX_INTERVAL: u64 = 24 * 365;
+
+    /// The interval in effect, `None` if the profile is not updated in the background
+    pub fn interval(&self) -> Option<u64> {
+        self.interval
+            .or(self.provider_interval)
+            .filter(|hours| *hours > 0)
+    }
+
+    pub fn is_due(&self, now: u64) -> bool {
+        self.interval().is_some_and(|hours| {
+            self.updated_at
+                .is_none_or(|at| now >= at.saturating_add(hours.saturating_mul(3600)))
+        })
+    }
+}
+
 #[cfg_attr(test, derive(PartialEq))]
 #[derive(Clone, Debug, Default)]
 pub struct ProfileData {
     pub dtype: ProfileType,
     pub no_pp: bool,
     pub update_with_proxy: bool,
+    pub schedule: UpdateSchedule,
 }
 
 impl ProfileData {
@@ -35,6 +68,17 @@ impl ProfileData {
             dtype,
             no_pp: false,
             update_with_proxy: false,
+            schedule: UpdateSchedule::default(),
+        }
+    }
+
+    fn into_profile(self, name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            dtype: self.dtype,
+            no_pp: self.no_pp,
+            update_with_proxy: self.update_with_proxy,
+            schedule: self.schedule,
         }
     }
 }
@@ -146,13 +190,25 @@ impl serde::Serialize for ProfileData {
         serializer: S,
     ) -> std::result::Result<S::Ok, S::Error> {
         use serde::ser::SerializeMap;
-        let fields = 2 + self.update_with_proxy as usize;
+        let schedule = [
+            ("update_interval", self.schedule.interval),
+            ("provider_interval", self.schedule.provider_interval),
+            ("updated_at", self.schedule.updated_at),
+        ];
+        let fields = 2
+            + self.update_with_proxy as usize
+            + schedule.iter().filter(|(_, v)| v.is_some()).count();
         let mut map = serializer.serialize_map(Some(fields))?;
         map.serialize_entry("dtype", &self.dtype)?;
         map.serialize_entry("no_pp", &self.no_pp)?;
         if self.update_with_proxy {
             map.serialize_entry("update_with_proxy", &self.update_with_proxy)?;
         }
+        for (key, value) in schedule {
+            if let Some(value) = value {
+                map.serialize_entry(key, &value)?;
+            }
+        }
         map.end()
     }
 }
@@ -178,18 +234,23 @@ impl<'de> serde::Deserialize<'de> for ProfileData {
                 .get(&serde_yml::Value::String("update_with_proxy".into()))
                 .and_then(|v| v.as_bool())
                 .unwrap_or(false);
+            let get_u64 = |key: &str| {
+                map.get(serde_yml::Value::String(key.into()))
+                    .and_then(|v| v.as_u64())
+            };
             Ok(ProfileData {
                 dtype,
                 no_pp,
                 update_with_proxy,
+                schedule: UpdateSchedule {
+                    interval: get_u64("update_interval"),
+                    provider_interval: get_u64("provider_interval"),
+                    updated_at: get_u64("updated_at"),
+                },
             })
         } else {
             let dtype = serde_yml::from_value(value).map_err(serde::de::Error::custom)?;
-            Ok(ProfileData {
-                dtype,
-                no_pp: false,
-                update_with_proxy: false,
-            })
+            Ok(ProfileData::new(dtype))
         }
     }
 }
@@ -235,12 +296,7 @@ impl ProfileManager {
         };
         db.profiles
             .insert(name.as_ref().into(), ProfileData::new(dtype))
-            .map(|data| Profile {
-                name: name.as_ref().to_string(),
-                dtype: data.dtype,
-                no_pp: data.no_pp,
-                update_with_proxy: data.update_with_proxy,
-            })
+            .map(|data| data.into_profile(name.as_ref()))
     }
     pub fn get<S: AsRef<str>>(&self, name: S) -> Option<Profile> {
         let name = name.as_ref();
@@ -249,12 +305,7 @@ impl ProfileManager {
             .get(name)
             .cloned()
             .or_else(|| self.singbox.profiles.get(name).cloned())
-            .map(|data| Profile {
-                name: name.to_string(),
-                dtype: data.dtype,
-                no_pp: data.no_pp,
-                update_with_proxy: data.update_with_proxy,
-            })
+            .map(|data| data.into_profile(name))
     }
     /// return all profile names from both sections
     pub fn all(&self) -> Vec<String> {
@@ -271,21 +322,18 @@ impl ProfileManager {
     }
     pub fn remove<S: AsRef<str>>(&mut self, name: S) -> Option<Profile> {
         let name = name.as_ref();
-        let from_mihomo = self.mihomo.profiles.remove(name).map(|data| Profile {
-            name: name.to_string(),
-            dtype: data.dtype,
-            no_pp: data.no_pp,
-            update_with_proxy: data.update_with_proxy,
-        });
+        let from_mihomo = self
+            .mihomo
+            .profiles
+            .remove(name)
+            .map(|data| data.into_profile(name));
         if from_mihomo.is_some() {
             return from_mihomo;
         }
-        self.singbox.profiles.remove(name).map(|data| Profile {
-            name: name.to_string(),
-            dtype: data.dtype,
-            no_pp: data.no_pp,
-            update_with_proxy: data.update_with_proxy,
-        })
+        self.singbox
+            .profiles
+            .remove(name)
+            .map(|data| data.into_profile(name))
     }
     pub fn get_current(&self) -> Option<Profile> {
         match self.core_type {
@@ -326,6 +374,49 @@ impl ProfileManager {
             data.update_with_proxy = val;
         }
     }
+    fn data_mut(&mut self, name: &str) -> Option<&mut ProfileData> {
+        match self.mihomo.profiles.get_mut(name) {
+            Some(data) => Some(data),
+            None => self.singbox.profiles.get_mut(name),
+        }
+    }
+    /// `None` follows the provider's `profile-update-interval`, `Some(0)` disables
+    pub fn set_update_interval<S: AsRef<str>>(&mut self, name: S, hours: Option<u64>) {
+        if let Some(data) = self.data_mut(name.as_ref()) {
+            data.schedule.interval = hours;
+        }
+    }
+    /// Note a successful update at `now` and the interval the provider asked for
+    pub fn record_update<S: AsRef<str>>(
+        &mut self,
+        name: S,
+        now: u64,
+        provider_interval: Option<u64>,
+    ) {
+        if let Some(data) = self.data_mut(name.as_ref()) {
+            data.schedule.updated_at = Some(now);
+            if provider_interval.is_some() {
+                data.schedule.provider_interval = provider_interval;
+            }
+        }
+    }
+    /// URL profiles of the active core whose update interval has passed at `now`
+    pub fn due_for_update(&self, now: u64) -> Vec<Profile> {
+        let db = match self.core_type {
+            crate::config::CoreType::Mihomo => &self.mihomo,
+            crate::config::CoreType::Singbox => &self.singbox,
+        };
+        let mut due: Vec<Profile> = db
+            .profiles
+            .iter()
+            .filter(|(_, data)| {
+                matches!(data.dtype, ProfileType::Url(_)) && data.schedule.is_due(now)
+            })
+            .map(|(name, data)| data.clone().into_profile(name))
+            .collect();
+        due.sort_by(|a, b| a.name.cmp(&b.name));
+        due
+    }
 }
 
 #[cfg(test)]
@@ -485,6 +576,62 @@ singbox:
         assert!(!db.get("pf1").unwrap().update_with_proxy);
     }
 
+    #[test]
+    fn update_schedule_roundtrip_and_old_format() {
+        let yaml = r#"core_type: mihomo
+mihomo:
+  profiles:
+    pf1: {dtype: !Url "https://example.com", no_pp: false}
+singbox:
+  profiles: {}
+"#;
+        let mut db: ProfileManager = serde_yml::from_str(yaml).unwrap();
+        assert_eq!(db.get("pf1").unwrap().schedule, UpdateSchedule::default());
+
+        db.record_update("pf1", 1_000, Some(24));
+        db.set_update_interval("pf1", Some(6));
+        let serialized = serde_yml::to_string(&db).unwrap();
+        assert!(serialized.contains("update_interval: 6"));
+        let deser: ProfileManager = serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(db, deser);
+        let schedule = deser.get("pf1").unwrap().schedule;
+        assert_eq!(schedule.provider_interval, Some(24));
+        assert_eq!(schedule.updated_at, Some(1_000));
+    }
+
+    #[test]
+    fn due_for_update_follows_interval() {
+        let mut db = ProfileManager::default();
+        db.insert("file", ProfileType::File);
+        db.insert("never", ProfileType::Url("https://a.example".into()));
+        db.insert("header", ProfileType::Url("https://b.example".into()));
+        db.insert("user", ProfileType::Url("https://c.example".into()));
+        db.set_update_interval("file", Some(1));
+
+        let hour = 3600;
+        // The provider asks for 12h, the user overrides another profile to 2h
+        db.record_update("header", 0, Some(12));
+        db.record_update("user", 0, Some(12));
+        db.set_update_interval("user", Some(2));
+        let names = |db: &ProfileManager, now| {
+            db.due_for_update(now)
+                .into_iter()
+                .map(|pf| pf.name)
+                .collect::<Vec<_>>()
+        };
+        assert!(names(&db, hour).is_empty());
+        assert_eq!(names(&db, 2 * hour), ["user"]);
+        assert_eq!(names(&db, 12 * hour), ["header", "user"]);
+
+        // 0 turns the header's interval off
+        db.set_update_interval("header", Some(0));
+        assert_eq!(names(&db, 12 * hour), ["user"]);
+
+        // An absurd interval from an older clashtui.db is never due, not a panic
+        db.set_update_interval("user", Some(u64::MAX));
+        assert!(names(&db, u64::MAX - 1).is_empty());
+    }
+
     #[test]
     fn update_with_proxy_roundtrip() {
         let mut db = ProfileManager::default();
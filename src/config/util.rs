@@ -3,6 +3,7 @@ use anyhow::{Context, Result, bail};
 pub mod defs {
     pub const CONFIG_FILE: &str = "config.yaml";
     pub const DATA_FILE: &str = "clashtui.db";
+    pub const LOCK_FILE: &str = "clashtui.lock";
     pub const CORE_OVERRIDE_FILE: &str = "core_override_config.yaml";
     pub const CORE_OVERRIDE_SINGBOX_FILE: &str = "core_override_config.json";
     pub const LOG_FILE: &str = "clashtui.log";
@@ -12,10 +13,58 @@ pub mod defs {
     pub const PROFILE_JSONS_DIR: &str = "profiles";
     pub const TEMPLATE_DIR: &str = "templates";
     pub const KEYMAP_FILE: &str = "keymap.yaml";
+    pub const LATENCY_FILE: &str = "latency.jsonl";
+    pub const EXPORT_DIR: &str = "exports";
+    pub const LOG_ARCHIVE_DIR: &str = "core-logs";
+    pub const PROFILE_HISTORY_DIR: &str = "profile-history";
     pub const PROVIDER_CACHE_DIR: &str = "providers";
     pub const PROXY_PROVIDERS_DIR: &str = "proxy-providers";
 }
 
+/// Replace `path` with `contents` through a temporary file and a rename
+///
+/// A crash or a full disk leaves either the old or the new file, never a
+/// truncated one. A symlinked `path` has its target replaced.
+pub fn write_atomic(path: &std::path::Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(path.file_name().unwrap_or_default());
+    tmp_name.push(format!(
+        ".{}-{}.tmp",
+        std::process::id(),
+        SEQ.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp = path.with_file_name(tmp_name);
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&tmp)?;
+        if let Ok(meta) = std::fs::metadata(&path) {
+            file.set_permissions(meta.permissions())?;
+        }
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, &path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+        return result;
+    }
+    // The rename itself only survives a crash once the directory is synced
+    #[cfg(unix)]
+    {
+        let parent = path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(std::path::Path::new("."));
+        std::fs::File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
 pub(super) fn load_home_dir() -> Result<std::path::PathBuf> {
     use std::{env, path};
     let data_dir = env::current_exe()
@@ -68,6 +117,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn write_atomic_replaces_and_keeps_symlinks() {
+        let dir = std::env::temp_dir().join(format!("clashtui-atomic-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("clashtui.db");
+
+        write_atomic(&file, "current_profile: a\n").unwrap();
+        write_atomic(&file, "current_profile: b\n").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "current_profile: b\n"
+        );
+
+        #[cfg(unix)]
+        {
+            let link = dir.join("link.db");
+            std::os::unix::fs::symlink(&file, &link).unwrap();
+            write_atomic(&link, "current_profile: c\n").unwrap();
+            assert!(link.is_symlink());
+            assert_eq!(
+                std::fs::read_to_string(&file).unwrap(),
+                "current_profile: c\n"
+            );
+        }
+        // No temporary files are left behind
+        let names: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert!(names.iter().all(|n| !n.to_string_lossy().ends_with(".tmp")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
     #[test]
     fn cfg_macos_consistent() {
         // cfg!(target_os = "macos") should be true when compiled with --target *-apple-darwin
@@ -82,10 +165,10 @@ macro_rules! load_save {
         impl $id {
             pub fn to_file(&self) -> Result<()> {
                 let path = DATA_DIR.get().unwrap().join($name);
-                let fp = std::fs::File::create(&path)
-                    .with_context(|| format!("Failed to create {}", path.display()))?;
-                Ok(serde_yml::to_writer(fp, &self)
-                    .with_context(|| format!("Failed to write {}", path.display()))?)
+                let content = serde_yml::to_string(&self)
+                    .with_context(|| format!("Failed to serialize {}", path.display()))?;
+                write_atomic(&path, content)
+                    .with_context(|| format!("Failed to write {}", path.display()))
             }
         }
         load_save!($id, $name, no_save);
@@ -1,3 +1,9 @@
+pub mod autoselect;
 pub mod command;
+pub mod export;
 pub mod file;
+pub mod latency;
+pub mod log_archive;
+pub mod logline;
 pub mod restful;
+pub mod update;
@@ -0,0 +1,245 @@
+//! Client-side node selection for `Selector` groups, see [AutoSelect]
+
+use anyhow::{Result, anyhow, bail};
+use std::collections::HashMap;
+
+use crate::config::{AutoSelect, SelectPolicy};
+use crate::functions::latency::{self, Sample};
+use crate::functions::restful::client::client;
+use crate::functions::restful::proxies;
+
+/// Shortest interval between two tests of a group, in seconds
+pub const MIN_INTERVAL: u64 = 10;
+
+/// Outbound types a policy never switches to
+const BUILTIN_TYPES: [&str; 7] = [
+    "direct",
+    "reject",
+    "rejectdrop",
+    "pass",
+    "compatible",
+    "block",
+    "dns",
+];
+
+/// A switch made by a policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Switch {
+    pub group: String,
+    pub from: Option<String>,
+    pub to: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for Switch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "auto-select {}: {} -> {} ({})",
+            self.group,
+            self.from.as_deref().unwrap_or("-"),
+            self.to,
+            self.reason
+        )
+    }
+}
+
+/// Result of one test round of a group
+pub struct Outcome {
+    /// Delay test results, for [latency::record]
+    pub samples: Vec<Sample>,
+    pub switch: Option<Switch>,
+}
+
+fn fmt_delay(d: Option<u64>) -> String {
+    d.map_or("FAIL".to_owned(), |d| format!("{d}ms"))
+}
+
+/// The node `policy` wants instead of `current` and why, `None` to stay
+///
+/// `candidates` are in the group's order, `delays` only holds nodes that passed.
+pub fn choose(
+    policy: &AutoSelect,
+    candidates: &[String],
+    current: Option<&str>,
+    delays: &HashMap<String, u64>,
+) -> Option<(String, String)> {
+    let delay_of = |n: &str| delays.get(n).copied().filter(|&d| d > 0);
+    let current_delay = current.and_then(delay_of);
+    let fastest = || {
+        candidates
+            .iter()
+            .filter_map(|n| delay_of(n).map(|d| (n, d)))
+            .min_by_key(|&(_, d)| d)
+    };
+    let (to, reason) = match policy.policy {
+        SelectPolicy::LowestLatency => {
+            let (best, d) = fastest()?;
+            if current_delay.is_some_and(|cd| d + policy.tolerance >= cd) {
+                return None;
+            }
+            (best, format!("{d}ms, was {}", fmt_delay(current_delay)))
+        }
+        SelectPolicy::Failover => {
+            let first = candidates.iter().find(|n| delay_of(n).is_some())?;
+            let reason = match current_delay {
+                Some(_) => "higher priority node is back".to_owned(),
+                None => "current node failed".to_owned(),
+            };
+            (first, reason)
+        }
+        SelectPolicy::Sticky => {
+            if current_delay.is_some() {
+                return None;
+            }
+            let (best, d) = fastest()?;
+            (best, format!("current node failed, {d}ms"))
+        }
+    };
+    (current != Some(to.as_str())).then(|| (to.clone(), format!("{}: {reason}", policy.policy)))
+}
+
+/// Test the group of `policy` once and switch it if the policy says so
+pub fn run_once(policy: &AutoSelect) -> Result<Outcome> {
+    let all = proxies::fetch_proxies()?.proxies;
+    let group = all
+        .get(&policy.group)
+        .ok_or_else(|| anyhow!("group `{}` not found", policy.group))?;
+    if !group.proxy_type.eq_ignore_ascii_case("selector") {
+        bail!(
+            "`{}` is a {} group, only Selector groups can be switched",
+            policy.group,
+            group.proxy_type
+        );
+    }
+    let members = group.all.clone().unwrap_or_default();
+    let candidates: Vec<String> = members
+        .iter()
+        .filter(|m| {
+            all.get(m.as_str()).is_none_or(|p| {
+                !BUILTIN_TYPES.contains(&p.proxy_type.to_ascii_lowercase().as_str())
+            })
+        })
+        .cloned()
+        .collect();
+    let url = policy.url.as_deref().or(group.test_url.as_deref());
+    let delays = proxies::test_group_delay(&policy.group, url, client().timeout() * 1000)?;
+    let samples = latency::group_samples(
+        &policy.group,
+        &candidates,
+        url.unwrap_or(proxies::DEFAULT_TEST_URL),
+        &delays,
+    );
+
+    let current = group.now.as_deref();
+    let switch = match choose(policy, &candidates, current, &delays) {
+        Some((to, reason)) => {
+            proxies::select_proxy(&policy.group, &to)?;
+            Some(Switch {
+                group: policy.group.clone(),
+                from: current.map(str::to_owned),
+                to,
+                reason,
+            })
+        }
+        None => None,
+    };
+    Ok(Outcome { samples, switch })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(policy: SelectPolicy, tolerance: u64) -> AutoSelect {
+        AutoSelect {
+            group: "G".to_owned(),
+            policy,
+            interval: 60,
+            tolerance,
+            url: None,
+        }
+    }
+
+    fn nodes() -> Vec<String> {
+        ["a", "b", "c"].map(str::to_owned).to_vec()
+    }
+
+    fn delays(pairs: &[(&str, u64)]) -> HashMap<String, u64> {
+        pairs.iter().map(|&(n, d)| (n.to_owned(), d)).collect()
+    }
+
+    #[test]
+    fn lowest_latency_respects_tolerance() {
+        let d = delays(&[("a", 200), ("b", 150), ("c", 90)]);
+        let p = policy(SelectPolicy::LowestLatency, 0);
+        assert_eq!(
+            choose(&p, &nodes(), Some("a"), &d),
+            Some(("c".to_owned(), "lowest-latency: 90ms, was 200ms".to_owned()))
+        );
+        assert_eq!(choose(&p, &nodes(), Some("c"), &d), None);
+        let p = policy(SelectPolicy::LowestLatency, 150);
+        assert_eq!(choose(&p, &nodes(), Some("a"), &d), None);
+        // a failed current node is always replaced
+        let d = delays(&[("b", 150), ("c", 140)]);
+        assert_eq!(choose(&p, &nodes(), Some("a"), &d).unwrap().0, "c");
+    }
+
+    #[test]
+    fn failover_prefers_group_order() {
+        let p = policy(SelectPolicy::Failover, 0);
+        let d = delays(&[("b", 300), ("c", 50)]);
+        assert_eq!(
+            choose(&p, &nodes(), Some("a"), &d),
+            Some(("b".to_owned(), "failover: current node failed".to_owned()))
+        );
+        let d = delays(&[("a", 500), ("b", 300)]);
+        assert_eq!(choose(&p, &nodes(), Some("b"), &d).unwrap().0, "a");
+        assert_eq!(choose(&p, &nodes(), Some("a"), &d), None);
+    }
+
+    #[test]
+    fn sticky_only_moves_on_failure() {
+        let p = policy(SelectPolicy::Sticky, 0);
+        let d = delays(&[("a", 900), ("b", 50)]);
+        assert_eq!(choose(&p, &nodes(), Some("a"), &d), None);
+        let d = delays(&[("b", 80), ("c", 50)]);
+        assert_eq!(
+            choose(&p, &nodes(), Some("a"), &d),
+            Some((
+                "c".to_owned(),
+                "sticky: current node failed, 50ms".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn nothing_alive_keeps_current() {
+        for p in [
+            SelectPolicy::LowestLatency,
+            SelectPolicy::Failover,
+            SelectPolicy::Sticky,
+        ] {
+            assert_eq!(
+                choose(&policy(p, 0), &nodes(), Some("a"), &HashMap::new()),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn run_once_switches_recorded_group() {
+        crate::functions::restful::client::use_fixtures();
+        let mut p = policy(SelectPolicy::LowestLatency, 0);
+        p.group = "Entry".to_owned();
+        let outcome = run_once(&p).unwrap();
+        let switch = outcome.switch.unwrap();
+        assert_eq!(switch.from.as_deref(), Some("看视频和下载不要选这个"));
+        assert_eq!(switch.to, "vmess-ipdktc33");
+        assert_eq!(outcome.samples.len(), 5);
+
+        p.group = "At-pvd0".to_owned();
+        let e = run_once(&p).err().unwrap();
+        assert!(e.to_string().contains("only Selector groups"));
+    }
+}
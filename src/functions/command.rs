@@ -27,7 +27,7 @@ pub async fn resolve_sudo_password(needs_sudo: bool) -> Result<Option<String>> {
         if !sudo_needs_password() {
             return Ok(None);
         }
-        match crate::tui::prompt_sudo_password().await {
+        match crate::tui::prompt_sudo_password().await? {
             Some(pw) if pw.is_empty() => Ok(None),
             Some(pw) => Ok(Some(pw)),
             None => Err(anyhow::anyhow!("cancelled")),
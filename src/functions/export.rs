@@ -0,0 +1,275 @@
+//! Connections and log entries as JSONL or CSV, to attach to bug reports
+
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::functions::restful::api_log::{self, LogEntry};
+use crate::functions::restful::connection::Conn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line, connections as the controller sends them
+    Jsonl,
+    Csv,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+/// Every field of `Conn` and its `ConnMetaData`, named like the controller does
+const CONN_COLUMNS: [&str; 18] = [
+    "id",
+    "start",
+    "network",
+    "type",
+    "host",
+    "process",
+    "processPath",
+    "sourceIP",
+    "sourcePort",
+    "destinationIP",
+    "destinationPort",
+    "remoteDestination",
+    "sniffHost",
+    "upload",
+    "download",
+    "rule",
+    "rulePayload",
+    "chains",
+];
+
+const LOG_COLUMNS: [&str; 3] = ["time", "type", "payload"];
+
+fn conn_row(c: &Conn) -> [String; 18] {
+    let m = &c.metadata;
+    [
+        c.id.clone(),
+        c.start.clone(),
+        m.network.clone(),
+        m.ctype.clone(),
+        m.host.clone(),
+        m.process.clone(),
+        m.process_path.clone(),
+        m.source_ip.clone(),
+        m.source_port.clone(),
+        m.destination_ip.clone().unwrap_or_default(),
+        m.destination_port.clone(),
+        m.remote_destination.clone(),
+        m.sniff_host.clone().unwrap_or_default(),
+        c.upload.to_string(),
+        c.download.to_string(),
+        c.rule.clone().unwrap_or_default(),
+        c.rule_payload.clone().unwrap_or_default(),
+        // From the group to the node, as the Connections tab shows it
+        c.chains
+            .iter()
+            .rev()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" > "),
+    ]
+}
+
+/// Quote a field if it holds a separator, quote or line break (RFC 4180)
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+fn write_csv_row<S: AsRef<str>>(out: &mut dyn Write, row: &[S]) -> io::Result<()> {
+    let line: Vec<String> = row.iter().map(|f| csv_field(f.as_ref())).collect();
+    writeln!(out, "{}", line.join(","))
+}
+
+pub fn write_conns<'a>(
+    out: &mut dyn Write,
+    format: Format,
+    conns: impl IntoIterator<Item = &'a Conn>,
+) -> io::Result<()> {
+    match format {
+        Format::Jsonl => {
+            for c in conns {
+                serde_json::to_writer(&mut *out, c)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        Format::Csv => {
+            write_csv_row(out, &CONN_COLUMNS)?;
+            for c in conns {
+                write_csv_row(out, &conn_row(c))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn write_logs<'a>(
+    out: &mut dyn Write,
+    format: Format,
+    entries: impl IntoIterator<Item = &'a LogEntry>,
+) -> io::Result<()> {
+    match format {
+        Format::Jsonl => {
+            for e in entries {
+                serde_json::to_writer(&mut *out, e)?;
+                out.write_all(b"\n")?;
+            }
+        }
+        Format::Csv => {
+            write_csv_row(out, &LOG_COLUMNS)?;
+            for e in entries {
+                write_csv_row(out, &[&e.time, &e.type_, &e.payload])?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Create `<dir>/<kind>-<yymmdd-HHMMSS>.<ext>` and fill it with `write`,
+/// `-N` is appended to the stamp if that file exists
+pub fn save(
+    dir: &Path,
+    kind: &str,
+    format: Format,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<PathBuf> {
+    let stamp = api_log::file_stamp();
+    let ext = format.extension();
+    std::fs::create_dir_all(dir)?;
+    let mut path = dir.join(format!("{kind}-{stamp}.{ext}"));
+    let mut n = 1;
+    let file = loop {
+        match std::fs::File::create_new(&path) {
+            Ok(file) => break file,
+            // Two exports within a second
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                path = dir.join(format!("{kind}-{stamp}-{n}.{ext}"));
+                n += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    let mut out = BufWriter::new(file);
+    write(&mut out)?;
+    out.flush()?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::restful::connection::ConnInfo;
+
+    fn recorded() -> Vec<Conn> {
+        let info: ConnInfo =
+            serde_json::from_str(include_str!("restful/tests/fixtures/connections.json")).unwrap();
+        info.connections.unwrap()
+    }
+
+    #[test]
+    fn save_twice_in_a_second_keeps_both() {
+        let dir = std::env::temp_dir().join(format!("clashtui-export-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let first = save(&dir, "logs", Format::Csv, |out| out.write_all(b"first")).unwrap();
+        let second = save(&dir, "logs", Format::Csv, |out| out.write_all(b"second")).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(std::fs::read(&first).unwrap(), b"first");
+        assert_eq!(std::fs::read(&second).unwrap(), b"second");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn csv_quotes_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn conns_as_csv_and_jsonl() {
+        let conns = recorded();
+        let mut csv = Vec::new();
+        write_conns(&mut csv, Format::Csv, &conns).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), conns.len() + 1);
+        assert_eq!(lines[0].split(',').count(), CONN_COLUMNS.len());
+        assert!(lines[1].contains(",firefox,/usr/lib/firefox/firefox,"));
+        assert!(lines[1].ends_with(",DomainSuffix,google.com,Entry > Sl-pvd0 > vmess-ipdktc33"));
+
+        let mut jsonl = Vec::new();
+        write_conns(&mut jsonl, Format::Jsonl, &conns).unwrap();
+        let back: Vec<Conn> = String::from_utf8(jsonl)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(back.len(), conns.len());
+        assert_eq!(back[2].metadata.process_path, "/usr/bin/git");
+        assert_eq!(back[0].rule_payload.as_deref(), Some("google.com"));
+    }
+
+    #[test]
+    fn logs_keep_their_time() {
+        let entries = [LogEntry {
+            time: "26-10-18 09:12:03".to_owned(),
+            type_: "warning".to_owned(),
+            payload: "dial DIRECT, error".to_owned(),
+            fields: None,
+        }];
+        let mut csv = Vec::new();
+        write_logs(&mut csv, Format::Csv, &entries).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "time,type,payload\n26-10-18 09:12:03,warning,\"dial DIRECT, error\"\n"
+        );
+        let mut jsonl = Vec::new();
+        write_logs(&mut jsonl, Format::Jsonl, &entries).unwrap();
+        assert_eq!(
+            String::from_utf8(jsonl).unwrap(),
+            "{\"time\":\"26-10-18 09:12:03\",\"type\":\"warning\",\"payload\":\"dial DIRECT, error\"}\n"
+        );
+
+        // Parsed fields come along in JSONL
+        let entries = [LogEntry::new(
+            "info".to_owned(),
+            "[TCP] 127.0.0.1:51234 --> ads.example.com:443 match DomainSuffix(example.com) using REJECT"
+                .to_owned(),
+        )];
+        let mut jsonl = Vec::new();
+        write_logs(&mut jsonl, Format::Jsonl, &entries).unwrap();
+        let jsonl = String::from_utf8(jsonl).unwrap();
+        assert!(jsonl.contains(",\"network\":\"TCP\",\"source\":\"127.0.0.1:51234\","));
+        assert!(jsonl.contains(",\"proxy\":\"REJECT\"}"));
+    }
+
+    #[test]
+    fn save_creates_dir_and_file() {
+        let dir = std::env::temp_dir().join(format!("clashtui-export-{}", std::process::id()));
+        let path = save(&dir, "logs", Format::Csv, |out| {
+            write_logs(out, Format::Csv, [])
+        })
+        .unwrap();
+        assert!(path.extension().is_some_and(|e| e == "csv"));
+        assert!(
+            path.file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("logs-")
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "time,type,payload\n"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
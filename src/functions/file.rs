@@ -1,9 +1,13 @@
 macro_rules! pm {
     () => {
-        crate::config::CONFIG.data.lock().unwrap()
+        crate::config::CONFIG.data()
+    };
+    (mut) => {
+        crate::config::CONFIG.data_mut()
     };
 }
 
+pub mod history;
 pub mod net_resource;
 pub mod profile;
 pub mod template;
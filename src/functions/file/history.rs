@@ -0,0 +1,217 @@
+//! Earlier versions of profiles and of the core config
+//!
+//! Before a profile or the core config is overwritten, its content is copied
+//! to `profile-history/profiles/<name>/` or `profile-history/core/` as
+//! `<yymmdd-HHMMSS>-<hash>.<ext>`, unless the newest copy has the same hash.
+//! Only the newest `profile_history` copies of each are kept.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::CONFIG;
+use crate::functions::restful::api_log;
+
+/// Length of the content hash in file names
+const HASH_LEN: usize = 12;
+
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub path: PathBuf,
+    /// `yymmdd-HHMMSS` the copy was made
+    pub stamp: String,
+    /// Start of the content's MD5
+    pub hash: String,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+pub fn profile_dir(name: &str) -> PathBuf {
+    crate::config::profile_history_dir()
+        .join("profiles")
+        .join(name)
+}
+
+pub fn core_dir() -> PathBuf {
+    crate::config::profile_history_dir().join("core")
+}
+
+fn hash(content: &[u8]) -> String {
+    let mut hash = format!("{:x}", md5::compute(content));
+    hash.truncate(HASH_LEN);
+    hash
+}
+
+/// Saved versions in `dir`, newest first
+pub fn versions(dir: &Path) -> io::Result<Vec<Version>> {
+    let mut versions = Vec::new();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(versions),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let Some((stamp, hash)) = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .and_then(|s| s.rsplit_once('-'))
+        else {
+            continue;
+        };
+        let meta = entry.metadata()?;
+        versions.push(Version {
+            stamp: stamp.to_owned(),
+            hash: hash.to_owned(),
+            size: meta.len(),
+            modified: meta.modified()?,
+            path,
+        });
+    }
+    versions.sort_by_key(|v| {
+        (
+            std::cmp::Reverse(v.modified),
+            std::cmp::Reverse(v.stamp.clone()),
+        )
+    });
+    Ok(versions)
+}
+
+/// Copy `file` into `dir` and drop all but the newest `keep` copies.
+///
+/// Nothing is saved if `file` is missing or empty, or the same as the newest copy.
+pub fn save(file: &Path, dir: &Path, keep: usize) -> io::Result<Option<Version>> {
+    if keep == 0 {
+        return Ok(None);
+    }
+    let content = match std::fs::read(file) {
+        Ok(content) if !content.is_empty() => content,
+        Ok(_) => return Ok(None),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let hash = hash(&content);
+    let existing = versions(dir)?;
+    if existing.first().is_some_and(|v| v.hash == hash) {
+        return Ok(None);
+    }
+
+    std::fs::create_dir_all(dir)?;
+    let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("txt");
+    let stamp = api_log::file_stamp();
+    let path = dir.join(format!("{stamp}-{hash}.{ext}"));
+    crate::config::write_atomic(&path, &content)?;
+    for old in existing.iter().skip(keep.saturating_sub(1)) {
+        // A copy of the same content made within the same second was just replaced
+        if old.path != path {
+            match std::fs::remove_file(&old.path) {
+                // Another clashtui process pruned it first
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                r => r?,
+            }
+        }
+    }
+    let meta = std::fs::metadata(&path)?;
+    Ok(Some(Version {
+        path,
+        stamp,
+        hash,
+        size: meta.len(),
+        modified: meta.modified()?,
+    }))
+}
+
+/// Put the `n`th newest copy in `dir` back in place of `file`, `n` starting at 1.
+///
+/// The content being replaced is saved first, so a rollback can be undone by
+/// rolling back again.
+pub fn restore(file: &Path, dir: &Path, n: usize, keep: usize) -> anyhow::Result<Version> {
+    let version = versions(dir)?
+        .into_iter()
+        .nth(n.saturating_sub(1))
+        .ok_or_else(|| anyhow::anyhow!("No version {n} in {}", dir.display()))?;
+    let content = std::fs::read(&version.path)?;
+    save(file, dir, keep.max(1))?;
+    crate::config::write_atomic(file, &content)?;
+    Ok(version)
+}
+
+/// Save a copy of profile `name` before it is overwritten; failures are only logged
+pub fn keep_profile(name: &str, file: &Path) {
+    if let Err(e) = save(file, &profile_dir(name), CONFIG.cfg_file.profile_history) {
+        log::warn!("Failed to keep a copy of profile {name}: {e}");
+    }
+}
+
+/// Save a copy of the core config before it is overwritten; failures are only logged
+pub fn keep_core_config(file: &Path) {
+    if let Err(e) = save(file, &core_dir(), CONFIG.cfg_file.profile_history) {
+        log::warn!("Failed to keep a copy of {}: {e}", file.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("clashtui-history-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn save_skips_duplicates_and_prunes() {
+        let dir = temp_dir("save");
+        let file = dir.join("pf.yaml");
+        let history = dir.join("history");
+
+        assert!(save(&file, &history, 2).unwrap().is_none());
+        std::fs::write(&file, "proxies: [a]").unwrap();
+        assert!(save(&file, &history, 2).unwrap().is_some());
+        assert!(save(&file, &history, 2).unwrap().is_none());
+        for content in ["proxies: [b]", "proxies: [c]"] {
+            std::fs::write(&file, content).unwrap();
+            save(&file, &history, 2).unwrap();
+        }
+        let versions = versions(&history).unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            std::fs::read_to_string(&versions[0].path).unwrap(),
+            "proxies: [c]"
+        );
+        assert_eq!(versions[0].hash, hash(b"proxies: [c]"));
+        assert!(versions[0].path.extension().is_some_and(|e| e == "yaml"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn restore_keeps_the_replaced_content() {
+        let dir = temp_dir("restore");
+        let file = dir.join("pf.json");
+        let history = dir.join("history");
+        std::fs::write(&file, r#"{"outbounds":[{"tag":"good"}]}"#).unwrap();
+        save(&file, &history, 10).unwrap();
+        // The provider serves an empty node list
+        std::fs::write(&file, r#"{"outbounds":[]}"#).unwrap();
+
+        let restored = restore(&file, &history, 1, 10).unwrap();
+        assert_eq!(restored.hash, hash(br#"{"outbounds":[{"tag":"good"}]}"#));
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            r#"{"outbounds":[{"tag":"good"}]}"#
+        );
+        // Rolling back again brings the replaced content back
+        restore(&file, &history, 1, 10).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            r#"{"outbounds":[]}"#
+        );
+
+        assert!(restore(&file, &history, 9, 10).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
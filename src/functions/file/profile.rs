@@ -1,14 +1,16 @@
+pub mod diff;
 mod profile;
 
 use super::PROFILE_JSONS_PATH;
 use super::PROFILE_YAMLS_PATH;
 use crate::config::database::{Profile, ProfileType};
+use diff::ProfileDiff;
 
 pub mod db {
     use super::*;
 
     pub fn create(name: impl AsRef<str>, url: impl AsRef<str>) -> anyhow::Result<Profile> {
-        let mut pm = pm!();
+        let mut pm = pm!(mut);
         pm.insert(&name, ProfileType::Url(url.as_ref().to_owned()));
         pm.to_file()?;
         Ok(pm.get(name).unwrap())
@@ -24,7 +26,7 @@ pub mod db {
                 }
             }
         }
-        let mut pm = pm!();
+        let mut pm = pm!(mut);
         pm.remove(pf.name);
         pm.to_file()
     }
@@ -42,20 +44,51 @@ pub mod db {
         pm!().get_current().unwrap_or_default()
     }
     pub fn set_current(pf: Profile) -> anyhow::Result<()> {
-        let mut pm = pm!();
+        let mut pm = pm!(mut);
         pm.set_current(pf);
         pm.to_file()
     }
     pub fn toggle_no_pp(name: impl AsRef<str>) -> anyhow::Result<bool> {
-        let mut pm = pm!();
+        let mut pm = pm!(mut);
         let current = pm.get(name.as_ref()).map(|pf| pf.no_pp).unwrap_or(false);
         let new = !current;
         pm.set_no_pp(name.as_ref(), new);
         pm.to_file()?;
         Ok(new)
     }
+    /// `None` follows the provider's `profile-update-interval`, `Some(0)` disables
+    pub fn set_update_interval(name: impl AsRef<str>, hours: Option<u64>) -> anyhow::Result<()> {
+        let mut pm = pm!(mut);
+        anyhow::ensure!(
+            pm.get(name.as_ref()).is_some(),
+            "Profile not found: {}",
+            name.as_ref()
+        );
+        pm.set_update_interval(name, hours);
+        pm.to_file()
+    }
+    pub fn record_update(
+        name: impl AsRef<str>,
+        provider_interval: Option<u64>,
+    ) -> anyhow::Result<()> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut pm = pm!(mut);
+        pm.record_update(name, now, provider_interval);
+        pm.to_file()
+    }
+    /// URL profiles whose update interval has passed
+    pub fn due_for_update() -> Vec<Profile> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        pm!().due_for_update(now)
+    }
     pub fn toggle_update_with_proxy(name: impl AsRef<str>) -> anyhow::Result<bool> {
-        let mut pm = pm!();
+        let mut pm = pm!(mut);
         let current = pm
             .get(name.as_ref())
             .map(|pf| pf.update_with_proxy)
@@ -67,6 +100,26 @@ pub mod db {
     }
 }
 
+/// Hours between background updates as typed by the user: `auto` or empty to
+/// follow the provider, `0` to disable
+pub fn parse_update_interval(s: &str) -> anyhow::Result<Option<u64>> {
+    use crate::config::database::UpdateSchedule;
+    let s = s.trim();
+    if s.is_empty() || s.eq_ignore_ascii_case("auto") {
+        return Ok(None);
+    }
+    let hours: u64 = s
+        .trim_end_matches('h')
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid update interval '{s}': expected hours, 0 or auto"))?;
+    anyhow::ensure!(
+        hours <= UpdateSchedule::MAX_INTERVAL,
+        "Invalid update interval '{s}': at most {} hours",
+        UpdateSchedule::MAX_INTERVAL
+    );
+    Ok(Some(hours))
+}
+
 pub fn import_profile_from_file(source_path: &str, profile_name: &str) -> anyhow::Result<Profile> {
     let source = std::path::Path::new(source_path);
     anyhow::ensure!(source.exists(), "Source file not found: {source_path}");
@@ -103,7 +156,7 @@ pub fn import_profile_from_file(source_path: &str, profile_name: &str) -> anyhow
     }
     std::fs::copy(source, &dest)?;
 
-    let mut pm = pm!();
+    let mut pm = pm!(mut);
     pm.insert(profile_name, ProfileType::File);
     pm.to_file()?;
     Ok(pm.get(profile_name).unwrap())
@@ -130,7 +183,7 @@ fn import_singbox_profile(source: &std::path::Path, profile_name: &str) -> anyho
     }
     std::fs::copy(source, &dest)?;
 
-    let mut pm = pm!();
+    let mut pm = pm!(mut);
     pm.insert(profile_name, ProfileType::Singbox);
     pm.to_file()?;
     Ok(pm.get(profile_name).unwrap())
@@ -139,9 +192,47 @@ fn import_singbox_profile(source: &std::path::Path, profile_name: &str) -> anyho
 pub struct UpdateResult {
     pub name: String,
     pub net_updates: Vec<crate::functions::file::net_resource::NetResourceUpdate>,
+    /// `profile-update-interval` the provider sent with the profile, in hours
+    pub provider_interval: Option<u64>,
+}
+
+/// The `profile-update-interval` header, in hours; fractions are rounded up
+/// and anything past [UpdateSchedule::MAX_INTERVAL] is capped
+///
+/// [UpdateSchedule::MAX_INTERVAL]: crate::config::database::UpdateSchedule::MAX_INTERVAL
+fn provider_interval(headers: &std::collections::HashMap<String, String>) -> Option<u64> {
+    use crate::config::database::UpdateSchedule;
+    let hours: f64 = headers
+        .get("profile-update-interval")?
+        .trim()
+        .parse()
+        .ok()?;
+    (hours.is_finite() && hours > 0.0)
+        .then(|| (hours.ceil() as u64).min(UpdateSchedule::MAX_INTERVAL))
 }
 
-pub async fn update_profile(profile: Profile, with_proxy: bool) -> anyhow::Result<UpdateResult> {
+/// Ask `review` whether to apply `diff`, unless nothing that it covers changed
+async fn confirm_diff(
+    name: &str,
+    diff: ProfileDiff,
+    review: impl AsyncFnOnce(ProfileDiff) -> bool,
+) -> anyhow::Result<()> {
+    if !diff.is_empty() && !review(diff).await {
+        anyhow::bail!("Update of {name} aborted, the profile is unchanged");
+    }
+    Ok(())
+}
+
+/// Update `profile`, downloading it first for URL profiles.
+///
+/// When a download changes proxies, groups, providers or the rule count of an
+/// existing profile, `review` gets the [ProfileDiff] and the download is only
+/// written if it returns true.
+pub async fn update_profile(
+    profile: Profile,
+    with_proxy: bool,
+    review: impl AsyncFnOnce(ProfileDiff) -> bool,
+) -> anyhow::Result<UpdateResult> {
     use super::template::fetch_net_resource_statuses;
 
     let result = if matches!(profile.dtype, ProfileType::Template { .. }) {
@@ -149,18 +240,28 @@ pub async fn update_profile(profile: Profile, with_proxy: bool) -> anyhow::Resul
     } else if matches!(profile.dtype, ProfileType::Singbox)
         || crate::config::CONFIG.core_type() == crate::config::CoreType::Singbox
     {
-        update_singbox_profile(profile.clone(), with_proxy).await
+        update_singbox_profile(profile.clone(), with_proxy, review).await
     } else {
         let path = PROFILE_YAMLS_PATH.join(format!("{}.yaml", &profile.name));
+        let mut interval = None;
 
         if let ProfileType::Url(ref url) = profile.dtype {
             let mut response = crate::functions::restful::download::profile(url, with_proxy)?;
+            interval = provider_interval(&response.headers);
             let content: serde_yml::Mapping = serde_yml::from_reader(&mut response)
                 .map_err(|e| anyhow::anyhow!("Failed to parse downloaded profile YAML: {e}"))?;
+            let current: Option<serde_yml::Mapping> = std::fs::File::open(&path)
+                .ok()
+                .and_then(|f| serde_yml::from_reader(f).ok());
+            if let Some(current) = current {
+                let diff = ProfileDiff::mihomo(&current, &content);
+                confirm_diff(&profile.name, diff, review).await?;
+            }
             if let Some(parent) = path.parent() {
                 std::fs::create_dir_all(parent)?;
             }
-            serde_yml::to_writer(std::fs::File::create(&path)?, &content)?;
+            super::history::keep_profile(&profile.name, &path);
+            crate::config::write_atomic(&path, serde_yml::to_string(&content)?)?;
         }
 
         anyhow::ensure!(
@@ -176,38 +277,136 @@ pub async fn update_profile(profile: Profile, with_proxy: bool) -> anyhow::Resul
         };
 
         let net_updates = fetch_net_resource_statuses(&content, with_proxy).await;
-        serde_yml::to_writer(std::fs::File::create(&path)?, &content)?;
+        crate::config::write_atomic(&path, serde_yml::to_string(&content)?)?;
         Ok(UpdateResult {
             name: profile.name.clone(),
             net_updates,
+            provider_interval: interval,
         })
     };
 
-    if result.is_ok() {
+    if let Ok(r) = &result {
+        if let Err(e) = db::record_update(&profile.name, r.provider_interval) {
+            log::warn!("Failed to record update of {}: {e}", profile.name);
+        }
         let cur = db::get_current();
         if cur.name == profile.name {
-            let _ = select(profile).await;
+            select(profile)
+                .await
+                .map_err(|e| anyhow::anyhow!("Updated, but applying it failed: {e}"))?;
         }
     }
 
     result
 }
 
+/// What a background update of one profile came to
+pub enum AutoUpdate {
+    Updated(UpdateResult),
+    /// The download changes the profile and nobody was there to confirm it,
+    /// the profile is left as it was
+    Held(ProfileDiff),
+    Failed(anyhow::Error),
+}
+
+/// Runs the background updates [db::due_for_update] asks for
+pub struct AutoUpdater {
+    /// Apply downloads that change the profile instead of holding them
+    auto_apply: bool,
+    /// A failed profile is not retried before [Self::RETRY_AFTER], however overdue it is
+    failed: std::collections::HashMap<String, std::time::Instant>,
+    /// Profiles whose download was held, skipped until updated some other way
+    held: std::collections::HashSet<String>,
+}
+
+impl AutoUpdater {
+    /// How often callers should look for due profiles
+    pub const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(60);
+    const RETRY_AFTER: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+    pub fn new(auto_apply: bool) -> Self {
+        Self {
+            auto_apply,
+            failed: Default::default(),
+            held: Default::default(),
+        }
+    }
+
+    /// Update every due profile, with the outcome per profile name
+    pub async fn update_due(&mut self) -> Vec<(String, AutoUpdate)> {
+        let due = db::due_for_update();
+        // A held profile that was updated by hand is no longer due
+        self.held
+            .retain(|name| due.iter().any(|pf| &pf.name == name));
+
+        let mut results = Vec::new();
+        for pf in due {
+            if self.held.contains(&pf.name)
+                || self
+                    .failed
+                    .get(&pf.name)
+                    .is_some_and(|at| at.elapsed() < Self::RETRY_AFTER)
+            {
+                continue;
+            }
+            let name = pf.name.clone();
+            let with_proxy = pf.update_with_proxy;
+            let auto_apply = self.auto_apply;
+            let mut held = None;
+            let result = update_profile(pf, with_proxy, async |diff| {
+                if auto_apply {
+                    log::info!("Profile {name} changes:\n{diff}");
+                } else {
+                    held = Some(diff);
+                }
+                auto_apply
+            })
+            .await;
+            let outcome = match (result, held) {
+                (Ok(r), _) => {
+                    self.failed.remove(&name);
+                    AutoUpdate::Updated(r)
+                }
+                (Err(_), Some(diff)) => {
+                    self.held.insert(name.clone());
+                    AutoUpdate::Held(diff)
+                }
+                (Err(e), None) => {
+                    self.failed.insert(name.clone(), std::time::Instant::now());
+                    AutoUpdate::Failed(e)
+                }
+            };
+            results.push((name, outcome));
+        }
+        results
+    }
+}
+
 async fn update_singbox_profile(
     profile: Profile,
     with_proxy: bool,
+    review: impl AsyncFnOnce(ProfileDiff) -> bool,
 ) -> anyhow::Result<UpdateResult> {
     let path = PROFILE_JSONS_PATH.join(format!("{}.json", &profile.name));
+    let mut interval = None;
 
     if let ProfileType::Url(ref url) = profile.dtype {
         let mut response = crate::functions::restful::download::profile(url, with_proxy)?;
+        interval = provider_interval(&response.headers);
         let content: serde_json::Value = serde_json::from_reader(&mut response)
             .map_err(|e| anyhow::anyhow!("Failed to parse downloaded profile JSON: {e}"))?;
+        let current: Option<serde_json::Value> = std::fs::File::open(&path)
+            .ok()
+            .and_then(|f| serde_json::from_reader(f).ok());
+        if let Some(current) = current {
+            let diff = ProfileDiff::singbox(&current, &content);
+            confirm_diff(&profile.name, diff, review).await?;
+        }
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let file = std::fs::File::create(&path)?;
-        serde_json::to_writer_pretty(file, &content)?;
+        super::history::keep_profile(&profile.name, &path);
+        crate::config::write_atomic(&path, serde_json::to_vec_pretty(&content)?)?;
     }
 
     anyhow::ensure!(
@@ -235,6 +434,7 @@ async fn update_singbox_profile(
     Ok(UpdateResult {
         name: profile.name,
         net_updates,
+        provider_interval: interval,
     })
 }
 
@@ -279,7 +479,7 @@ async fn update_template_profile(
                                     return (name, url, path, false, Some(e.to_string()));
                                 }
                             }
-                            match std::fs::write(&path, &buf) {
+                            match crate::config::write_atomic(&path, &buf) {
                                 Ok(()) => (name, url, path, true, None),
                                 Err(e) => (name, url, path, false, Some(e.to_string())),
                             }
@@ -368,7 +568,7 @@ async fn update_template_profile(
                                 return (name, url, path, false, Some(e.to_string()));
                             }
                         }
-                        match std::fs::write(&path, &buf) {
+                        match crate::config::write_atomic(&path, &buf) {
                             Ok(()) => (name, url, path, true, None),
                             Err(e) => (name, url, path, false, Some(e.to_string())),
                         }
@@ -427,6 +627,7 @@ async fn update_template_profile(
     Ok(UpdateResult {
         name: profile.name,
         net_updates: statuses,
+        provider_interval: None,
     })
 }
 
@@ -472,6 +673,7 @@ pub async fn select(profile: Profile) -> anyhow::Result<()> {
     let out_path = std::path::absolute(std::path::PathBuf::from(&cfg.config_path))
         .map_err(|e| anyhow::anyhow!("Failed to resolve config path: {e}"))?;
     lprofile.path = out_path.clone();
+    super::history::keep_core_config(&out_path);
     lprofile.sync_to_disk()?;
     db::set_current(profile)?;
     crate::functions::restful::config::reload(&out_path.display().to_string())
@@ -479,6 +681,22 @@ pub async fn select(profile: Profile) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Put the `to`th newest saved version of `profile` back, `to` starting at 1.
+///
+/// The current profile is selected again so the core runs the restored version.
+pub async fn rollback(profile: Profile, to: usize) -> anyhow::Result<super::history::Version> {
+    let version = super::history::restore(
+        &profile.local_path(),
+        &super::history::profile_dir(&profile.name),
+        to,
+        crate::config::CONFIG.cfg_file.profile_history,
+    )?;
+    if db::get_current().name == profile.name {
+        select(profile).await?;
+    }
+    Ok(version)
+}
+
 fn rewrite_provider_paths(_content: Option<&mut serde_yml::Mapping>) {
     // Paths are kept as-is (relative to mihomo's -d working directory).
     // Mihomo resolves relative proxy-provider/rule-provider paths against
@@ -549,7 +767,8 @@ async fn select_singbox(profile: Profile) -> anyhow::Result<()> {
 
     let merged_content = serde_json::to_string_pretty(&config)
         .map_err(|e| anyhow::anyhow!("Failed to serialize merged config: {e}"))?;
-    std::fs::write(&out_path, merged_content)
+    super::history::keep_core_config(&out_path);
+    crate::config::write_atomic(&out_path, merged_content)
         .map_err(|e| anyhow::anyhow!("Failed to write config to {}: {e}", out_path.display()))?;
 
     db::set_current(profile)?;
@@ -612,6 +831,32 @@ mod tests {
         base
     }
 
+    #[test]
+    fn parse_update_interval_accepts_hours_and_auto() {
+        assert_eq!(parse_update_interval("6").unwrap(), Some(6));
+        assert_eq!(parse_update_interval("12h").unwrap(), Some(12));
+        assert_eq!(parse_update_interval("0").unwrap(), Some(0));
+        assert_eq!(parse_update_interval("auto").unwrap(), None);
+        assert_eq!(parse_update_interval(" ").unwrap(), None);
+        assert!(parse_update_interval("-1").is_err());
+        assert_eq!(parse_update_interval("8760").unwrap(), Some(8760));
+        assert!(parse_update_interval("8761").is_err());
+        assert!(parse_update_interval("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn provider_interval_header() {
+        let headers = |v: &str| {
+            std::collections::HashMap::from([("profile-update-interval".to_owned(), v.to_owned())])
+        };
+        assert_eq!(provider_interval(&headers("24")), Some(24));
+        assert_eq!(provider_interval(&headers(" 1.5 ")), Some(2));
+        assert_eq!(provider_interval(&headers("0")), None);
+        assert_eq!(provider_interval(&headers("1e300")), Some(24 * 365));
+        assert_eq!(provider_interval(&headers("daily")), None);
+        assert_eq!(provider_interval(&Default::default()), None);
+    }
+
     #[test]
     fn scalar_overwrite() {
         let result = merge(r#"{"port": 7890}"#, r#"{"port": 20122}"#);
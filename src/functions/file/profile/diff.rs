@@ -0,0 +1,293 @@
+//! What a profile update changes, by name rather than by line
+//!
+//! Proxies, groups and providers are matched by name (`tag` for sing-box), so
+//! a provider reordering its nodes is no change, while a node that keeps its
+//! name but gets a new server is.
+
+use std::fmt;
+
+/// Names that appear, disappear or get a different definition
+#[derive(Debug, Default, PartialEq)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    fn between<V: PartialEq>(old: Vec<(String, V)>, new: Vec<(String, V)>) -> Self {
+        let mut changes = Self::default();
+        for (name, value) in &new {
+            match old.iter().find(|(n, _)| n == name) {
+                None => changes.added.push(name.clone()),
+                Some((_, v)) if v != value => changes.changed.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.removed = old
+            .into_iter()
+            .filter(|(name, _)| !new.iter().any(|(n, _)| n == name))
+            .map(|(name, _)| name)
+            .collect();
+        changes
+    }
+
+    fn extend(&mut self, other: Self) {
+        self.added.extend(other.added);
+        self.removed.extend(other.removed);
+        self.changed.extend(other.changed);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ProfileDiff {
+    pub proxies: Changes,
+    pub groups: Changes,
+    /// Proxy and rule providers, or sing-box rule sets
+    pub providers: Changes,
+    /// Number of rules before and after
+    pub rules: (usize, usize),
+}
+
+impl ProfileDiff {
+    pub fn mihomo(old: &serde_yml::Mapping, new: &serde_yml::Mapping) -> Self {
+        fn named_list(map: &serde_yml::Mapping, key: &str) -> Vec<(String, serde_yml::Value)> {
+            map.get(key)
+                .and_then(|v| v.as_sequence())
+                .into_iter()
+                .flatten()
+                .filter_map(|item| {
+                    let name = item.get("name")?.as_str()?;
+                    Some((name.to_owned(), item.clone()))
+                })
+                .collect()
+        }
+        fn named_map(map: &serde_yml::Mapping, key: &str) -> Vec<(String, serde_yml::Value)> {
+            map.get(key)
+                .and_then(|v| v.as_mapping())
+                .into_iter()
+                .flatten()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_owned(), v.clone())))
+                .collect()
+        }
+        fn rule_count(map: &serde_yml::Mapping) -> usize {
+            map.get("rules")
+                .and_then(|v| v.as_sequence())
+                .map_or(0, |rules| rules.len())
+        }
+
+        let mut providers = Changes::between(
+            named_map(old, "proxy-providers"),
+            named_map(new, "proxy-providers"),
+        );
+        providers.extend(Changes::between(
+            named_map(old, "rule-providers"),
+            named_map(new, "rule-providers"),
+        ));
+        Self {
+            proxies: Changes::between(named_list(old, "proxies"), named_list(new, "proxies")),
+            groups: Changes::between(
+                named_list(old, "proxy-groups"),
+                named_list(new, "proxy-groups"),
+            ),
+            providers,
+            rules: (rule_count(old), rule_count(new)),
+        }
+    }
+
+    pub fn singbox(old: &serde_json::Value, new: &serde_json::Value) -> Self {
+        /// Outbound types that pick among other outbounds
+        const GROUP_TYPES: &[&str] = &["selector", "urltest"];
+
+        fn tagged(list: Option<&serde_json::Value>) -> Named {
+            list.and_then(|v| v.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|item| {
+                    let tag = item.get("tag")?.as_str()?;
+                    Some((tag.to_owned(), item.clone()))
+                })
+                .collect()
+        }
+        fn is_group(outbound: &serde_json::Value) -> bool {
+            outbound
+                .get("type")
+                .and_then(|t| t.as_str())
+                .is_some_and(|t| GROUP_TYPES.contains(&t))
+        }
+        type Named = Vec<(String, serde_json::Value)>;
+
+        /// Proxies and groups of `config`
+        fn split(config: &serde_json::Value) -> (Named, Named) {
+            let mut proxies = tagged(config.get("outbounds"));
+            let groups = proxies.extract_if(.., |(_, o)| is_group(o)).collect();
+            proxies.extend(tagged(config.get("endpoints")));
+            (proxies, groups)
+        }
+        fn rule_count(config: &serde_json::Value) -> usize {
+            config
+                .pointer("/route/rules")
+                .and_then(|v| v.as_array())
+                .map_or(0, |rules| rules.len())
+        }
+
+        let (old_proxies, old_groups) = split(old);
+        let (new_proxies, new_groups) = split(new);
+        Self {
+            proxies: Changes::between(old_proxies, new_proxies),
+            groups: Changes::between(old_groups, new_groups),
+            providers: Changes::between(
+                tagged(old.pointer("/route/rule_set")),
+                tagged(new.pointer("/route/rule_set")),
+            ),
+            rules: (rule_count(old), rule_count(new)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proxies.is_empty()
+            && self.groups.is_empty()
+            && self.providers.is_empty()
+            && self.rules.0 == self.rules.1
+    }
+}
+
+/// Names listed per line before the rest is only counted
+const MAX_NAMES: usize = 8;
+
+fn write_names(f: &mut fmt::Formatter, sign: char, names: &[String]) -> fmt::Result {
+    if names.is_empty() {
+        return Ok(());
+    }
+    write!(f, "  {sign} ")?;
+    let shown = names.len().min(MAX_NAMES);
+    write!(f, "{}", names[..shown].join(", "))?;
+    if names.len() > shown {
+        write!(f, " and {} more", names.len() - shown)?;
+    }
+    writeln!(f)
+}
+
+impl fmt::Display for ProfileDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (label, changes) in [
+            ("Proxies", &self.proxies),
+            ("Groups", &self.groups),
+            ("Providers", &self.providers),
+        ] {
+            if changes.is_empty() {
+                writeln!(f, "{label}: unchanged")?;
+                continue;
+            }
+            writeln!(
+                f,
+                "{label}: +{} -{} ~{}",
+                changes.added.len(),
+                changes.removed.len(),
+                changes.changed.len()
+            )?;
+            write_names(f, '+', &changes.added)?;
+            write_names(f, '-', &changes.removed)?;
+            write_names(f, '~', &changes.changed)?;
+        }
+        let (before, after) = self.rules;
+        write!(f, "Rules: {before} -> {after}")?;
+        if before != after {
+            write!(f, " ({:+})", after as i64 - before as i64)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mihomo_diff_by_name() {
+        let old: serde_yml::Mapping = serde_yml::from_str(
+            r#"
+proxies:
+  - {name: HK, server: hk.example.com, port: 443}
+  - {name: JP paid, server: jp.example.com, port: 443}
+  - {name: SG, server: sg.example.com, port: 443}
+proxy-groups:
+  - {name: Auto, type: url-test, proxies: [HK, JP paid, SG]}
+proxy-providers:
+  sub: {type: http, url: "https://a.example.com"}
+rules: ["MATCH,Auto"]
+"#,
+        )
+        .unwrap();
+        let new: serde_yml::Mapping = serde_yml::from_str(
+            r#"
+proxies:
+  - {name: SG, server: sg.example.com, port: 443}
+  - {name: HK, server: hk2.example.com, port: 443}
+  - {name: US, server: us.example.com, port: 443}
+proxy-groups:
+  - {name: Auto, type: url-test, proxies: [HK, SG, US]}
+proxy-providers:
+  sub: {type: http, url: "https://a.example.com"}
+rule-providers:
+  ads: {type: http, behavior: domain, url: "https://ads.example.com"}
+rules: ["RULE-SET,ads,REJECT", "DOMAIN,ad.example.com,REJECT", "MATCH,Auto"]
+"#,
+        )
+        .unwrap();
+
+        let diff = ProfileDiff::mihomo(&old, &new);
+        assert_eq!(
+            diff.proxies,
+            Changes {
+                added: vec!["US".to_owned()],
+                removed: vec!["JP paid".to_owned()],
+                changed: vec!["HK".to_owned()],
+            }
+        );
+        assert_eq!(diff.groups.changed, vec!["Auto"]);
+        assert_eq!(diff.providers.added, vec!["ads"]);
+        assert_eq!(diff.rules, (1, 3));
+        assert!(!diff.is_empty());
+        assert!(ProfileDiff::mihomo(&new, &new).is_empty());
+
+        let text = diff.to_string();
+        assert!(text.contains("Proxies: +1 -1 ~1\n  + US\n  - JP paid\n  ~ HK\n"));
+        assert!(text.ends_with("Rules: 1 -> 3 (+2)"));
+    }
+
+    #[test]
+    fn singbox_diff_splits_groups_from_proxies() {
+        let old: serde_json::Value = serde_json::json!({
+            "outbounds": [
+                {"tag": "select", "type": "selector", "outbounds": ["a", "b"]},
+                {"tag": "a", "type": "vless", "server": "a.example.com"},
+                {"tag": "b", "type": "vless", "server": "b.example.com"},
+                {"tag": "direct", "type": "direct"}
+            ],
+            "route": {"rules": [{"action": "sniff"}], "rule_set": [{"tag": "geosite-cn"}]}
+        });
+        let new: serde_json::Value = serde_json::json!({
+            "outbounds": [
+                {"tag": "select", "type": "selector", "outbounds": ["a"]},
+                {"tag": "a", "type": "vless", "server": "a.example.com"},
+                {"tag": "direct", "type": "direct"}
+            ],
+            "endpoints": [{"tag": "wg", "type": "wireguard"}],
+            "route": {"rules": [{"action": "sniff"}], "rule_set": [{"tag": "geosite-cn"}]}
+        });
+
+        let diff = ProfileDiff::singbox(&old, &new);
+        assert_eq!(diff.proxies.added, vec!["wg"]);
+        assert_eq!(diff.proxies.removed, vec!["b"]);
+        assert!(diff.proxies.changed.is_empty());
+        assert_eq!(diff.groups.changed, vec!["select"]);
+        assert!(diff.providers.is_empty());
+        assert_eq!(diff.rules, (1, 1));
+        assert!(diff.to_string().ends_with("Rules: 1 -> 1"));
+    }
+}
@@ -4,16 +4,20 @@ use std::{fs::File, path::PathBuf};
 use crate::config::database::{Profile, ProfileType};
 
 impl Profile {
-    pub fn load_local_profile(self) -> anyhow::Result<LocalProfile> {
+    /// `profile_yamls/<name>.yaml`, or `profile_jsons/<name>.json` for sing-box
+    pub fn local_path(&self) -> PathBuf {
         use super::super::PROFILE_JSONS_PATH;
         use super::PROFILE_YAMLS_PATH;
-        let path = if matches!(self.dtype, ProfileType::Singbox)
+        if matches!(self.dtype, ProfileType::Singbox)
             || crate::config::CONFIG.core_type() == crate::config::CoreType::Singbox
         {
             PROFILE_JSONS_PATH.join(format!("{}.json", &self.name))
         } else {
             PROFILE_YAMLS_PATH.join(format!("{}.yaml", &self.name))
-        };
+        }
+    }
+    pub fn load_local_profile(self) -> anyhow::Result<LocalProfile> {
+        let path = self.local_path();
         let mut lpf = LocalProfile::from_pf(self, path);
         lpf.sync_from_disk()?;
         Ok(lpf)
@@ -80,9 +84,8 @@ impl LocalProfile {
     /// sync the content to disk by [`LocalProfile::path`]
     pub fn sync_to_disk(self) -> anyhow::Result<()> {
         let LocalProfile { path, content, .. } = self;
-        let fp = File::create(path)
-            .map_err(|e| anyhow::anyhow!("Failed to write clash config file: {e}"))?;
-        Ok(serde_yml::to_writer(fp, &content)?)
+        crate::config::write_atomic(&path, serde_yml::to_string(&content)?)
+            .map_err(|e| anyhow::anyhow!("Failed to write clash config file: {e}"))
     }
     pub fn from_pf(pf: Profile, path: std::path::PathBuf) -> Self {
         let Profile { name, dtype, .. } = pf;
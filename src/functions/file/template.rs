@@ -285,10 +285,7 @@ pub fn write_template_ppg(template_name: &str, groups: &ProxyProviderGroups) ->
         .ok_or_else(|| anyhow::anyhow!("clashtui key is not a mapping in: {}", path.display()))?
         .insert("proxy_provider_groups".into(), serde_yml::to_value(groups)?);
 
-    // atomic write
-    let tmp_path = path.with_extension("yaml.tmp");
-    serde_yml::to_writer(std::fs::File::create(&tmp_path)?, &value)?;
-    std::fs::rename(&tmp_path, &path)?;
+    crate::config::write_atomic(&path, serde_yml::to_string(&value)?)?;
     Ok(())
 }
 
@@ -389,11 +386,10 @@ pub fn apply_template(template_name: &str, profile_name: &str) -> anyhow::Result
     if let Some(parent) = output_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
-    // atomic write
-    let tmp_path = output_path.with_extension("yaml.tmp");
-    serde_yml::to_writer(std::fs::File::create(&tmp_path)?, &gened)?;
-    std::fs::rename(&tmp_path, &output_path)?;
-    let mut pm = pm!();
+    let content = serde_yml::to_string(&gened)?;
+    super::history::keep_profile(profile_name, &output_path);
+    crate::config::write_atomic(&output_path, content)?;
+    let mut pm = pm!(mut);
     pm.insert(
         profile_name,
         ProfileType::Template {
@@ -422,12 +418,10 @@ pub async fn apply_template_singbox(
     if let Some(parent) = output_path.parent() {
         std::fs::create_dir_all(parent)?;
     }
-    // atomic write
-    let tmp_path = output_path.with_extension("json.tmp");
-    let file = std::fs::File::create(&tmp_path)?;
-    serde_json::to_writer_pretty(file, &gened)?;
-    std::fs::rename(&tmp_path, &output_path)?;
-    let mut pm = pm!();
+    let content = serde_json::to_vec_pretty(&gened)?;
+    super::history::keep_profile(profile_name, &output_path);
+    crate::config::write_atomic(&output_path, content)?;
+    let mut pm = pm!(mut);
     pm.insert(
         profile_name,
         ProfileType::Template {
@@ -517,7 +511,7 @@ pub async fn update_profile_without_pp(
                                 if let Some(parent) = dest.parent() {
                                     let _ = std::fs::create_dir_all(parent);
                                 }
-                                let _ = std::fs::write(&dest, &buf);
+                                let _ = crate::config::write_atomic(&dest, &buf);
                             }
                         }
                         let yaml = serde_yml::from_slice::<serde_yml::Mapping>(&buf)
@@ -646,7 +640,7 @@ pub async fn update_profile_without_pp(
                             if let Some(parent) = dest.parent() {
                                 let _ = std::fs::create_dir_all(parent);
                             }
-                            let _ = std::fs::write(&dest, &buf);
+                            let _ = crate::config::write_atomic(&dest, &buf);
                         }
                         let yaml = serde_yml::from_slice::<serde_yml::Mapping>(&buf)
                             .map_err(|e| e.to_string());
@@ -751,7 +745,7 @@ pub async fn fetch_net_resource_statuses(
                             Some("Invalid YAML format".to_string()),
                         );
                     }
-                    match std::fs::write(&path, &buf) {
+                    match crate::config::write_atomic(&path, &buf) {
                         Ok(()) => (name, url, path, section, true, None),
                         Err(e) => (name, url, path, section, false, Some(e.to_string())),
                     }
@@ -930,7 +924,7 @@ pub async fn fetch_net_resource_statuses_from_resources(
                             Some("Invalid YAML format".to_string()),
                         );
                     }
-                    match std::fs::write(&path, &buf) {
+                    match crate::config::write_atomic(&path, &buf) {
                         Ok(()) => (name, url, path, section, true, None),
                         Err(e) => (name, url, path, section, false, Some(e.to_string())),
                     }
@@ -25,8 +25,8 @@ fn save_cached_proxies(url: &str, proxies: &[JsonValue]) {
     if let Some(parent) = path.parent() {
         let _ = std::fs::create_dir_all(parent);
     }
-    if let Ok(file) = std::fs::File::create(&path) {
-        let _ = serde_json::to_writer(file, proxies);
+    if let Ok(content) = serde_json::to_vec(proxies) {
+        let _ = crate::config::write_atomic(&path, content);
     }
 }
 
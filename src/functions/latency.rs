@@ -0,0 +1,377 @@
+//! Delay test results kept across core restarts
+//!
+//! The core only remembers delays since it started, so every test result is
+//! appended to `latency.jsonl` in the config dir, one compact JSON object per line.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// Samples older than this are dropped when the file is loaded
+const RETENTION_SECS: u64 = 7 * 24 * 3600;
+/// How far past [RETENTION_SECS] the oldest sample may get before [LatencyHistory::record]
+/// compacts, so a long-running process rewrites the file about once a day
+const COMPACT_SLACK_SECS: u64 = 24 * 3600;
+/// Window of [NodeStats]
+pub const STATS_WINDOW_SECS: u64 = 24 * 3600;
+/// Samples drawn in a sparkline
+const SPARK_LEN: usize = 16;
+
+static HISTORY: LazyLock<Mutex<LatencyHistory>> =
+    LazyLock::new(|| Mutex::new(LatencyHistory::load(crate::config::latency_history_path())));
+
+/// One delay test result
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sample {
+    /// Unix time in seconds
+    #[serde(rename = "t")]
+    pub time: u64,
+    #[serde(rename = "n")]
+    pub node: String,
+    /// The group tested, if the node was tested as part of one
+    #[serde(rename = "g", default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(rename = "u")]
+    pub url: String,
+    /// Milliseconds, `0` is a failure like in the core's history
+    #[serde(rename = "d")]
+    pub delay: u64,
+}
+
+impl Sample {
+    pub fn new(node: &str, group: Option<&str>, url: &str, delay: Option<u64>) -> Self {
+        Self {
+            time: now(),
+            node: node.to_owned(),
+            group: group.map(str::to_owned),
+            url: url.to_owned(),
+            delay: delay.unwrap_or(0),
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.delay > 0
+    }
+}
+
+/// Samples of a group test: members missing from `delays` failed
+pub fn group_samples(
+    group: &str,
+    members: &[String],
+    url: &str,
+    delays: &HashMap<String, u64>,
+) -> Vec<Sample> {
+    members
+        .iter()
+        .map(|m| Sample::new(m, Some(group), url, delays.get(m).copied()))
+        .collect()
+}
+
+/// Stability of a node over [STATS_WINDOW_SECS]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeStats {
+    /// The latest delays, oldest first, `0` for failures
+    pub recent: Vec<u64>,
+    pub tests: usize,
+    pub ok: usize,
+    pub p50: Option<u64>,
+    pub p95: Option<u64>,
+}
+
+impl NodeStats {
+    fn from_samples<'a>(samples: impl Iterator<Item = &'a Sample>) -> Self {
+        let samples: Vec<&Sample> = samples.collect();
+        let delays: Vec<u64> = samples.iter().map(|s| s.delay).collect();
+        let mut ok: Vec<u64> = samples
+            .iter()
+            .filter(|s| s.is_ok())
+            .map(|s| s.delay)
+            .collect();
+        ok.sort_unstable();
+        Self {
+            recent: delays[delays.len().saturating_sub(SPARK_LEN)..].to_vec(),
+            tests: delays.len(),
+            ok: ok.len(),
+            p50: percentile(&ok, 50),
+            p95: percentile(&ok, 95),
+        }
+    }
+
+    /// Share of successful tests in percent
+    pub fn success_rate(&self) -> Option<u64> {
+        (self.tests > 0).then(|| (self.ok * 100 / self.tests) as u64)
+    }
+
+    /// `recent` as block characters scaled to the slowest sample, `×` for failures
+    pub fn sparkline(&self) -> String {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let max = self.recent.iter().copied().max().unwrap_or(0).max(1);
+        self.recent
+            .iter()
+            .map(|&d| match d {
+                0 => '×',
+                d => BARS[((d * 7 / max) as usize).min(7)],
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], p: usize) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    Some(sorted[rank - 1])
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub struct LatencyHistory {
+    path: PathBuf,
+    samples: Vec<Sample>,
+}
+
+impl LatencyHistory {
+    /// Read `path`, dropping expired and unreadable lines; the file is
+    /// rewritten if any were dropped
+    pub fn load(path: PathBuf) -> Self {
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let cutoff = now().saturating_sub(RETENTION_SECS);
+        let lines = content.lines().filter(|l| !l.trim().is_empty()).count();
+        let samples: Vec<Sample> = content
+            .lines()
+            .filter_map(|l| serde_json::from_str::<Sample>(l).ok())
+            .filter(|s| s.time >= cutoff)
+            .collect();
+        let history = Self { path, samples };
+        if history.samples.len() != lines
+            && let Err(e) = history.rewrite()
+        {
+            log::warn!("Failed to compact {}: {e}", history.path.display());
+        }
+        history
+    }
+
+    fn rewrite(&self) -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        for s in &self.samples {
+            serde_json::to_writer(&mut buf, s)?;
+            buf.push(b'\n');
+        }
+        crate::config::write_atomic(&self.path, buf)
+    }
+
+    /// Append `samples` to memory and to the file
+    ///
+    /// Once the oldest sample is well past [RETENTION_SECS] the file is loaded
+    /// again, which drops expired samples and keeps what other clashtui
+    /// processes appended.
+    pub fn record(&mut self, samples: Vec<Sample>) -> std::io::Result<()> {
+        if samples.is_empty() {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut buf = Vec::new();
+        for s in &samples {
+            serde_json::to_writer(&mut buf, s)?;
+            buf.push(b'\n');
+        }
+        self.samples.extend(samples);
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(&buf)?;
+
+        let cutoff = now().saturating_sub(RETENTION_SECS);
+        // Samples are kept in the order they were recorded, the first is the oldest
+        if self
+            .samples
+            .first()
+            .is_some_and(|s| s.time.saturating_add(COMPACT_SLACK_SECS) < cutoff)
+        {
+            *self = Self::load(std::mem::take(&mut self.path));
+        }
+        Ok(())
+    }
+
+    /// [NodeStats] of every node tested in the window ending at `at`
+    pub fn stats_at(&self, at: u64) -> HashMap<String, NodeStats> {
+        let since = at.saturating_sub(STATS_WINDOW_SECS);
+        let mut by_node: HashMap<&str, Vec<&Sample>> = HashMap::new();
+        for s in self.samples.iter().filter(|s| s.time >= since) {
+            by_node.entry(&s.node).or_default().push(s);
+        }
+        by_node
+            .into_iter()
+            .map(|(node, samples)| {
+                (
+                    node.to_owned(),
+                    NodeStats::from_samples(samples.into_iter()),
+                )
+            })
+            .collect()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Persist test results, failures are only logged
+pub fn record(samples: Vec<Sample>) {
+    let mut history = HISTORY.lock().unwrap();
+    if let Err(e) = history.record(samples) {
+        log::warn!("Failed to write {}: {e}", history.path().display());
+    }
+}
+
+/// [NodeStats] of the last [STATS_WINDOW_SECS]
+pub fn stats() -> HashMap<String, NodeStats> {
+    HISTORY.lock().unwrap().stats_at(now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("clashtui-latency-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn sample(node: &str, time: u64, delay: u64) -> Sample {
+        Sample {
+            time,
+            node: node.to_owned(),
+            group: None,
+            url: "https://www.gstatic.com/generate_204".to_owned(),
+            delay,
+        }
+    }
+
+    #[test]
+    fn record_and_reload() {
+        let path = temp_file("reload");
+        let mut history = LatencyHistory::load(path.clone());
+        let t = now();
+        history
+            .record(vec![sample("hk", t, 120), sample("hk", t, 0)])
+            .unwrap();
+        history.record(vec![sample("jp", t, 80)]).unwrap();
+
+        let reloaded = LatencyHistory::load(path.clone());
+        assert_eq!(reloaded.samples, history.samples);
+        let line = std::fs::read_to_string(&path).unwrap();
+        assert!(line.starts_with(r#"{"t":"#));
+        assert!(!line.contains("\"g\""));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn load_drops_expired_and_garbage() {
+        let path = temp_file("expire");
+        let t = now();
+        let mut history = LatencyHistory::load(path.clone());
+        history
+            .record(vec![
+                sample("old", t - RETENTION_SECS - 10, 50),
+                sample("new", t, 60),
+            ])
+            .unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+
+        let reloaded = LatencyHistory::load(path.clone());
+        assert_eq!(reloaded.samples, [sample("new", t, 60)]);
+        // compacted on load
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn record_drops_expired_in_long_runs() {
+        let path = temp_file("prune");
+        let t = now();
+        let mut history = LatencyHistory::load(path.clone());
+        let stale = t - RETENTION_SECS - 10;
+        history.record(vec![sample("stale", stale, 50)]).unwrap();
+        // Just past the retention the file is only appended to
+        history.record(vec![sample("hk", t, 60)]).unwrap();
+        assert_eq!(history.samples.len(), 2);
+
+        // Another process appends meanwhile
+        LatencyHistory::load(path.clone())
+            .record(vec![sample("sg", t, 80)])
+            .unwrap();
+
+        // Samples recorded by a process running for days
+        history.samples[0].time = t - RETENTION_SECS - COMPACT_SLACK_SECS - 10;
+        history.record(vec![sample("jp", t, 70)]).unwrap();
+        assert_eq!(
+            history.samples,
+            [
+                sample("hk", t, 60),
+                sample("sg", t, 80),
+                sample("jp", t, 70)
+            ]
+        );
+        assert_eq!(LatencyHistory::load(path.clone()).samples, history.samples);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn stats_over_last_day() {
+        let t = 10 * STATS_WINDOW_SECS;
+        let mut samples = vec![sample("hk", t - STATS_WINDOW_SECS - 1, 9999)];
+        samples.extend((1..=19).map(|i| sample("hk", t - 100 + i, i * 10)));
+        samples.push(sample("hk", t, 0));
+        let history = LatencyHistory {
+            path: PathBuf::new(),
+            samples,
+        };
+        let stats = &history.stats_at(t)["hk"];
+        assert_eq!(stats.tests, 20);
+        assert_eq!(stats.ok, 19);
+        assert_eq!(stats.success_rate(), Some(95));
+        assert_eq!(stats.p50, Some(100));
+        assert_eq!(stats.p95, Some(190));
+        assert_eq!(stats.recent.len(), SPARK_LEN);
+        assert_eq!(stats.recent.last(), Some(&0));
+    }
+
+    #[test]
+    fn sparkline_scales_to_slowest() {
+        let stats = NodeStats {
+            recent: vec![10, 80, 0, 40],
+            ..Default::default()
+        };
+        assert_eq!(stats.sparkline(), "▁█×▄");
+        assert_eq!(NodeStats::default().sparkline(), "");
+    }
+
+    #[test]
+    fn group_members_without_delay_failed() {
+        let delays = HashMap::from([("a".to_owned(), 100)]);
+        let samples = group_samples("G", &["a".to_owned(), "b".to_owned()], "u", &delays);
+        assert_eq!(samples[0].delay, 100);
+        assert!(!samples[1].is_ok());
+        assert_eq!(samples[1].group.as_deref(), Some("G"));
+    }
+}
@@ -0,0 +1,263 @@
+//! The core log stream kept on disk, see [LogArchive]
+//!
+//! Entries are appended to `core.jsonl` in the archive dir, one [LogEntry] per
+//! line as `clashtui logs dump --output jsonl` prints them. Once the file
+//! reaches `max_size_mb` it is renamed to `core-<yymmdd-HHMMSS>.jsonl`, and
+//! rotated files older than `max_age_days` are deleted.
+
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::config::{CONFIG, LogArchive};
+use crate::functions::restful::api_log::{self, LogEntry};
+
+const ACTIVE_FILE: &str = "core.jsonl";
+const ROTATED_PREFIX: &str = "core-";
+const EXTENSION: &str = ".jsonl";
+
+/// `None` unless `log_archive` is set in `config.yaml`
+static ARCHIVE: LazyLock<Mutex<Option<ArchiveWriter>>> = LazyLock::new(|| {
+    Mutex::new(
+        CONFIG
+            .cfg_file
+            .log_archive
+            .as_ref()
+            .map(|settings| ArchiveWriter::new(crate::config::log_archive_dir(), settings)),
+    )
+});
+
+pub struct ArchiveWriter {
+    dir: PathBuf,
+    max_size: u64,
+    max_age: Duration,
+    file: Option<std::fs::File>,
+    /// Bytes in the active file
+    size: u64,
+}
+
+impl ArchiveWriter {
+    pub fn new(dir: PathBuf, settings: &LogArchive) -> Self {
+        Self {
+            dir,
+            max_size: settings.max_size_mb.max(1) * 1024 * 1024,
+            max_age: Duration::from_secs(settings.max_age_days * 24 * 3600),
+            file: None,
+            size: 0,
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn open(&mut self) -> io::Result<&mut std::fs::File> {
+        if self.file.is_none() {
+            std::fs::create_dir_all(&self.dir)?;
+            prune(&self.dir, self.max_age, SystemTime::now())?;
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(ACTIVE_FILE))?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        Ok(self.file.as_mut().unwrap())
+    }
+
+    /// Rename the active file, the next entry starts a new one
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        let mut to = self.dir.join(format!(
+            "{ROTATED_PREFIX}{}{EXTENSION}",
+            api_log::file_stamp()
+        ));
+        // Two rotations within a second
+        let mut n = 1;
+        while to.exists() {
+            to = self.dir.join(format!(
+                "{ROTATED_PREFIX}{}-{n}{EXTENSION}",
+                api_log::file_stamp()
+            ));
+            n += 1;
+        }
+        std::fs::rename(self.dir.join(ACTIVE_FILE), to)
+    }
+
+    pub fn append(&mut self, entry: &LogEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.open()?;
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.open()?.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Delete rotated files last written before `now - max_age`
+fn prune(dir: &Path, max_age: Duration, now: SystemTime) -> io::Result<()> {
+    let Some(cutoff) = now.checked_sub(max_age) else {
+        return Ok(());
+    };
+    for file in list(dir)? {
+        if file.name != ACTIVE_FILE && file.modified < cutoff {
+            std::fs::remove_file(&file.path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Append `entry` to the archive if one is configured, failures are only logged
+pub fn record(entry: &LogEntry) {
+    let mut archive = ARCHIVE.lock().unwrap();
+    if let Some(writer) = archive.as_mut()
+        && let Err(e) = writer.append(entry)
+    {
+        log::warn!(
+            "Failed to archive log entry in {}: {e}",
+            writer.dir().display()
+        );
+        // Retry with a fresh file next time
+        writer.file = None;
+    }
+}
+
+/// A file of the archive
+#[derive(Debug, Clone)]
+pub struct ArchiveFile {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Files of the archive in `dir`, the active one first, then newest first
+pub fn list(dir: &Path) -> io::Result<Vec<ArchiveFile>> {
+    let mut files = Vec::new();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(files),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !(name == ACTIVE_FILE || name.starts_with(ROTATED_PREFIX) && name.ends_with(EXTENSION)) {
+            continue;
+        }
+        let meta = entry.metadata()?;
+        files.push(ArchiveFile {
+            name,
+            path: entry.path(),
+            size: meta.len(),
+            modified: meta.modified()?,
+        });
+    }
+    files.sort_by_key(|f| {
+        (
+            f.name != ACTIVE_FILE,
+            std::cmp::Reverse(f.modified),
+            std::cmp::Reverse(f.name.clone()),
+        )
+    });
+    Ok(files)
+}
+
+#[derive(Deserialize)]
+struct Stored {
+    time: String,
+    #[serde(rename = "type")]
+    type_: String,
+    payload: String,
+}
+
+/// Entries of an archive file, oldest first; unreadable lines are skipped
+pub fn read(path: &Path) -> io::Result<Vec<LogEntry>> {
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .filter_map(|l| serde_json::from_str::<Stored>(l).ok())
+        .map(|s| LogEntry {
+            time: s.time,
+            ..LogEntry::new(s.type_, s.payload)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "clashtui-log-archive-{name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn entry(payload: &str) -> LogEntry {
+        LogEntry {
+            time: "26-10-18 12:30:00".to_owned(),
+            ..LogEntry::new("info".to_owned(), payload.to_owned())
+        }
+    }
+
+    #[test]
+    fn entries_round_trip() {
+        let dir = temp_dir("round-trip");
+        let mut writer = ArchiveWriter::new(dir.clone(), &LogArchive::default());
+        writer
+            .append(&entry("[TCP] 127.0.0.1:51234 --> www.google.com:443 match DomainSuffix(google.com) using Entry[vmess-ipdktc33]"))
+            .unwrap();
+        writer
+            .append(&entry("Start initial configuration"))
+            .unwrap();
+
+        let files = list(&dir).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, ACTIVE_FILE);
+        let back = read(&files[0].path).unwrap();
+        assert_eq!(back.len(), 2);
+        assert_eq!(back[0].time, "26-10-18 12:30:00");
+        let fields = back[0].fields.as_ref().unwrap();
+        assert_eq!(fields.proxy.as_deref(), Some("Entry[vmess-ipdktc33]"));
+        assert!(back[1].fields.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rotates_by_size_and_prunes_by_age() {
+        let dir = temp_dir("rotate");
+        let mut writer = ArchiveWriter::new(dir.clone(), &LogArchive::default());
+        // Rotate after every entry
+        writer.max_size = 1;
+        for i in 0..3 {
+            writer.append(&entry(&format!("line {i}"))).unwrap();
+        }
+        let files = list(&dir).unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].name, ACTIVE_FILE);
+        assert!(
+            files[1..]
+                .iter()
+                .all(|f| f.name.starts_with(ROTATED_PREFIX))
+        );
+        assert_eq!(read(&files[0].path).unwrap()[0].payload, "line 2");
+        // Newest rotated file first
+        assert_eq!(read(&files[1].path).unwrap()[0].payload, "line 1");
+
+        // A week later only the active file is left
+        let later = SystemTime::now() + Duration::from_secs(8 * 24 * 3600);
+        prune(&dir, writer.max_age, later).unwrap();
+        let files = list(&dir).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].name, ACTIVE_FILE);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,205 @@
+//! Structured fields of core log payloads
+//!
+//! mihomo logs every routed connection on one line, e.g.
+//! `[TCP] 127.0.0.1:51234(firefox) --> www.google.com:443 match DomainSuffix(google.com) using Entry[vmess]`,
+//! while sing-box spreads it over lines tagged by component, e.g.
+//! `inbound/mixed[mixed-in]: inbound connection to www.google.com:443` or
+//! `router: match[3] domain_suffix=google.com => route(proxy)`.
+//! Other payloads are left as raw text.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LogFields {
+    /// `TCP` or `UDP`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    /// The matched rule with its payload, e.g. `DomainSuffix(google.com)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    /// The proxy chain or outbound, e.g. `Entry[vmess]`, `REJECT` or `route(proxy)`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// What is left of the payload, e.g. a dial error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Fields of a known payload format, `None` for anything else
+pub fn parse(payload: &str) -> Option<LogFields> {
+    parse_mihomo(payload).or_else(|| parse_singbox(payload))
+}
+
+fn some(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_owned())
+}
+
+/// `127.0.0.1:51234(firefox)` -> address and process
+fn split_process(src: &str) -> (&str, Option<&str>) {
+    match src.strip_suffix(')').and_then(|s| s.split_once('(')) {
+        Some((addr, process)) => (addr, Some(process)),
+        None => (src, None),
+    }
+}
+
+fn parse_mihomo(payload: &str) -> Option<LogFields> {
+    let (network, rest) = payload.strip_prefix('[')?.split_once("] ")?;
+    if !matches!(network, "TCP" | "UDP") {
+        return None;
+    }
+    let mut fields = LogFields {
+        network: some(network),
+        ..Default::default()
+    };
+
+    let conn = if let Some(rest) = rest.strip_prefix("dial ") {
+        // dial Entry (match DomainSuffix/google.com) 127.0.0.1:51234 --> www.google.com:443 error: ...
+        let (proxy, rest) = rest.split_once(" (match ")?;
+        let (rule, rest) = rest.split_once(") ")?;
+        fields.proxy = some(proxy);
+        fields.rule = match rule.split_once('/') {
+            Some((kind, payload)) if !payload.is_empty() => Some(format!("{kind}({payload})")),
+            Some((kind, _)) => some(kind),
+            None => some(rule),
+        };
+        let (conn, error) = rest.split_once(" error: ").unwrap_or((rest, ""));
+        fields.message = some(error);
+        conn
+    } else {
+        // 127.0.0.1:51234 --> www.google.com:443 match Rule(payload) using Chain
+        let (conn, routed) = match rest.split_once(" doesn't match any rule ") {
+            Some((conn, tail)) => (conn, tail),
+            None => rest.split_once(" match ")?,
+        };
+        let (rule, proxy) = routed.rsplit_once("using ")?;
+        fields.rule = some(rule.trim());
+        fields.proxy = some(proxy.trim());
+        conn
+    };
+
+    let (src, dst) = conn.split_once(" --> ")?;
+    let (src, process) = split_process(src.trim());
+    fields.source = some(src);
+    fields.process = process.and_then(some);
+    fields.destination = some(dst.trim());
+    Some(fields)
+}
+
+fn parse_singbox(payload: &str) -> Option<LogFields> {
+    // Optional `[connection-id elapsed] ` prefix
+    let payload = match payload.strip_prefix('[').and_then(|p| p.split_once("] ")) {
+        Some((_, rest)) => rest,
+        None => payload,
+    };
+    let (component, msg) = payload.split_once(": ")?;
+    let tag = || {
+        component
+            .split_once('[')
+            .and_then(|(_, t)| t.strip_suffix(']'))
+    };
+    let network = |packet: bool| Some(if packet { "UDP" } else { "TCP" }.to_owned());
+    let mut fields = LogFields::default();
+
+    if component.starts_with("inbound/") {
+        let (packet, msg) = match msg.strip_prefix("inbound packet connection ") {
+            Some(msg) => (true, msg),
+            None => (false, msg.strip_prefix("inbound connection ")?),
+        };
+        fields.network = network(packet);
+        if let Some(src) = msg.strip_prefix("from ") {
+            fields.source = some(src);
+        } else {
+            fields.destination = some(msg.strip_prefix("to ")?);
+        }
+    } else if component.starts_with("outbound/") {
+        let (packet, msg) = match msg.strip_prefix("outbound packet connection") {
+            Some(msg) => (true, msg),
+            None => (false, msg.strip_prefix("outbound connection")?),
+        };
+        fields.network = network(packet);
+        fields.proxy = tag().and_then(some);
+        fields.destination = msg.strip_prefix(" to ").and_then(some);
+    } else if component == "router" {
+        // match[3] domain_suffix=google.com => route(proxy)
+        let rest = msg.strip_prefix("match[")?.split_once("] ")?.1;
+        let (rule, action) = rest.split_once(" => ")?;
+        fields.rule = some(rule);
+        fields.proxy = some(action);
+    } else {
+        return None;
+    }
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mihomo_routed_connection() {
+        let f = parse("[TCP] 127.0.0.1:51234(firefox) --> www.google.com:443 match DomainSuffix(google.com) using Entry[vmess-ipdktc33]").unwrap();
+        assert_eq!(f.network.as_deref(), Some("TCP"));
+        assert_eq!(f.source.as_deref(), Some("127.0.0.1:51234"));
+        assert_eq!(f.process.as_deref(), Some("firefox"));
+        assert_eq!(f.destination.as_deref(), Some("www.google.com:443"));
+        assert_eq!(f.rule.as_deref(), Some("DomainSuffix(google.com)"));
+        assert_eq!(f.proxy.as_deref(), Some("Entry[vmess-ipdktc33]"));
+
+        let f = parse("[UDP] 127.0.0.1:40011 --> 1.1.1.1:53 doesn't match any rule using DIRECT")
+            .unwrap();
+        assert_eq!(f.rule, None);
+        assert_eq!(f.proxy.as_deref(), Some("DIRECT"));
+        assert_eq!(f.process, None);
+    }
+
+    #[test]
+    fn mihomo_dial_error() {
+        let f = parse(
+            "[UDP] dial DIRECT (match Match/) 127.0.0.1:40011 --> 1.1.1.1:53 error: i/o timeout",
+        )
+        .unwrap();
+        assert_eq!(f.proxy.as_deref(), Some("DIRECT"));
+        assert_eq!(f.rule.as_deref(), Some("Match"));
+        assert_eq!(f.destination.as_deref(), Some("1.1.1.1:53"));
+        assert_eq!(f.message.as_deref(), Some("i/o timeout"));
+
+        let f = parse("[TCP] dial Entry (match RuleSet/ads) 127.0.0.1:1 --> ads.example:443 error: connect failed").unwrap();
+        assert_eq!(f.rule.as_deref(), Some("RuleSet(ads)"));
+    }
+
+    #[test]
+    fn singbox_components() {
+        let f = parse(
+            "[3948271043 0ms] inbound/mixed[mixed-in]: inbound connection from 127.0.0.1:51234",
+        )
+        .unwrap();
+        assert_eq!(f.source.as_deref(), Some("127.0.0.1:51234"));
+        assert_eq!(f.network.as_deref(), Some("TCP"));
+
+        let f = parse("inbound/tun[tun-in]: inbound packet connection to 1.1.1.1:53").unwrap();
+        assert_eq!(f.destination.as_deref(), Some("1.1.1.1:53"));
+        assert_eq!(f.network.as_deref(), Some("UDP"));
+
+        let f = parse("router: match[3] domain_suffix=google.com => route(proxy)").unwrap();
+        assert_eq!(f.rule.as_deref(), Some("domain_suffix=google.com"));
+        assert_eq!(f.proxy.as_deref(), Some("route(proxy)"));
+
+        let f = parse("outbound/vmess[hk-01]: outbound connection to www.google.com:443").unwrap();
+        assert_eq!(f.proxy.as_deref(), Some("hk-01"));
+        assert_eq!(f.destination.as_deref(), Some("www.google.com:443"));
+    }
+
+    #[test]
+    fn unknown_formats_stay_raw() {
+        assert_eq!(parse("Start initial configuration in progress"), None);
+        assert_eq!(parse("[DNS] resolve google.com failed"), None);
+        assert_eq!(parse("dns: exchanged google.com A 300"), None);
+        assert_eq!(parse(""), None);
+    }
+}
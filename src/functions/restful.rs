@@ -1,15 +1,24 @@
 use crate::config::CONFIG;
 use minreq::Method;
 
-pub mod config_struct;
-pub mod core_detect;
 #[macro_use]
 mod utils;
+pub mod capability;
+pub mod client;
+pub mod config_struct;
+pub mod core_detect;
+mod http1;
+pub mod tls;
+#[cfg(unix)]
+mod unix;
 
+use client::client;
 use utils::*;
 
 const DEFAULT_PAYLOAD: &str = r#"'{"path": "", "payload": ""}'"#;
 const DEFAULT_TIMEOUT: u64 = 5;
+/// The core downloads the release before it answers
+const UPGRADE_TIMEOUT: u64 = 300;
 
 mod headers {
     pub const USER_AGENT: &str = "user-agent";
@@ -26,19 +35,46 @@ pub mod control {
     ///
     /// usually, an empty str is returned
     pub fn restart(payload: Option<String>) -> Result<()> {
-        request(
-            Method::Post,
-            "/restart",
-            Some(payload.unwrap_or(DEFAULT_PAYLOAD.to_string())),
-        )
-        .map(|_| ())
+        client().restart(payload)
     }
 
     /// Get clash core version
     ///
     /// for mihomo, it's like `{"meta": true, "version": "v1.1.1"}`
     pub fn version() -> Result<String> {
-        request(Method::Get, "/version", None).and_then(|r| r.as_str().map(|s| s.to_owned()))
+        client().version()
+    }
+
+    /// What mihomo's `/upgrade` endpoints replace
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Upgrade {
+        /// The core binary, from the alpha channel if `alpha`; mihomo
+        /// restarts itself afterwards
+        Core { alpha: bool },
+        /// The external UI (dashboard)
+        Ui,
+        /// GeoIP/GeoSite databases
+        Geo,
+    }
+
+    impl Upgrade {
+        pub fn path(self) -> String {
+            match self {
+                Self::Core { alpha: false } => "/upgrade".to_owned(),
+                Self::Core { alpha: true } => "/upgrade?channel=alpha".to_owned(),
+                Self::Ui => "/upgrade/ui".to_owned(),
+                Self::Geo => "/upgrade/geo".to_owned(),
+            }
+        }
+    }
+
+    /// Let the core upgrade itself
+    ///
+    /// API: POST /upgrade, /upgrade/ui, /upgrade/geo (mihomo only)
+    ///
+    /// Blocks until the core has downloaded the update, up to a few minutes.
+    pub fn upgrade(target: Upgrade) -> Result<String> {
+        client().upgrade(target)
     }
 
     /// Try GET `https://www.gstatic.com/generate_204`
@@ -60,14 +96,14 @@ pub mod cache {
     ///
     /// API: POST /cache/fakeip/flush
     pub fn flush_fakeip() -> Result<()> {
-        request(Method::Post, "/cache/fakeip/flush", None).map(|_| ())
+        client().flush_fakeip()
     }
 
     /// Flush DNS cache
     ///
     /// API: POST /cache/dns/flush
     pub fn flush_dns() -> Result<()> {
-        request(Method::Post, "/cache/dns/flush", None).map(|_| ())
+        client().flush_dns()
     }
 }
 
@@ -78,7 +114,27 @@ pub mod geo {
     ///
     /// API: POST /configs/geo
     pub fn upgrade_geo() -> Result<()> {
-        request(Method::Post, "/configs/geo", None).map(|_| ())
+        client().upgrade_geo()
+    }
+}
+
+pub mod traffic {
+    use serde::Deserialize;
+
+    /// One sample of WS /traffic, bytes per second
+    #[derive(Debug, Deserialize, Default, Clone, Copy)]
+    #[serde(default)]
+    pub struct Traffic {
+        pub up: u64,
+        pub down: u64,
+    }
+
+    /// One sample of WS /memory, bytes
+    #[derive(Debug, Deserialize, Default, Clone, Copy)]
+    #[serde(default)]
+    pub struct Memory {
+        pub inuse: u64,
+        pub oslimit: u64,
     }
 }
 
@@ -86,37 +142,15 @@ pub mod config {
     use super::*;
 
     pub fn fetch() -> Result<config_struct::ClashConfig> {
-        request(Method::Get, "/configs", None).and_then(|r| r.json())
+        client().fetch_config()
     }
 
     pub fn reload<S: AsRef<str>>(path: S) -> Result<String> {
-        request(
-            Method::Put,
-            "/configs?force=true",
-            Some(
-                serde_json::json!({
-                    "path": path.as_ref(),
-                    "payload": ""
-                })
-                .to_string(),
-            ),
-        )
-        .and_then(|r| {
-            if r.status_code >= 200 && r.status_code < 300 {
-                r.as_str().map(|s| s.to_owned()).map_err(|e| e.into())
-            } else {
-                let body = r.as_str().unwrap_or("(non-utf8 body)");
-                Err(minreq::Error::IoError(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    format!("HTTP {}: {body}", r.status_code),
-                )))
-            }
-        })
+        client().reload_config(path.as_ref())
     }
 
     pub fn patch(payload: String) -> Result<String> {
-        request(Method::Patch, "/configs", Some(payload))
-            .and_then(|r| r.as_str().map(|s| s.to_owned()))
+        client().patch_config(payload)
     }
 }
 
@@ -265,12 +299,15 @@ pub mod download {
     }
 }
 
+pub mod dns;
+pub mod providers;
 pub mod proxies;
+pub mod rules;
 
 pub mod connection {
     use super::*;
 
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
 
     #[cfg_attr(test, derive(Debug))]
     #[derive(Deserialize, Default)]
@@ -281,8 +318,8 @@ pub mod connection {
         pub connections: Option<Vec<Conn>>,
     }
 
-    #[cfg_attr(test, derive(Debug, Clone))]
-    #[derive(Deserialize)]
+    #[cfg_attr(test, derive(Debug))]
+    #[derive(Deserialize, Serialize, Clone)]
     pub struct Conn {
         pub id: String,
         pub metadata: ConnMetaData,
@@ -296,8 +333,8 @@ pub mod connection {
         pub rule_payload: Option<String>,
     }
 
-    #[cfg_attr(test, derive(Debug, Clone))]
-    #[derive(Deserialize)]
+    #[cfg_attr(test, derive(Debug))]
+    #[derive(Deserialize, Serialize, Clone)]
     #[serde(rename_all = "camelCase")]
     pub struct ConnMetaData {
         pub network: String,
@@ -324,12 +361,12 @@ pub mod connection {
 
     /// return [ConnInfo]
     pub fn get_connections() -> Result<ConnInfo> {
-        request(Method::Get, "/connections", None).and_then(|r| r.json())
+        client().get_connections()
     }
 
     /// Terminate all active connections
     pub fn terminate_all_connections() -> Result<()> {
-        request(Method::Delete, "/connections", None).map(|_| ())
+        client().terminate_all_connections()
     }
 
     /// if `id` is some, will try to terminate that connection,
@@ -340,31 +377,36 @@ pub mod connection {
     /// NOTE:
     /// Empty str is returned if connection is terminated successfully
     pub fn terminate_connection(id: Option<String>) -> Result<bool> {
-        request(
-            Method::Delete,
-            &format!(
-                "/connections{}",
-                id.map(|c| format!("/{c}")).unwrap_or_default()
-            ),
-            None,
-        )
-        .and_then(|r| {
-            r.as_str().map(|s| {
-                // try to catch failure
-                log::debug!("terminate conn:{s}");
-                s.is_empty()
-            })
-        })
+        client().terminate_connection(id)
     }
 }
 
 pub mod api_log {
     use super::*;
+    use crate::functions::logline::{self, LogFields};
 
+    #[derive(Clone, serde::Serialize)]
     pub struct LogEntry {
+        /// When clashtui received the entry, the core sends none
+        pub time: String,
+        #[serde(rename = "type")]
         pub type_: String,
         pub payload: String,
-        pub time: String,
+        /// Source, destination, rule and proxy of a known payload format
+        #[serde(flatten)]
+        pub fields: Option<Box<LogFields>>,
+    }
+
+    impl LogEntry {
+        /// An entry received now
+        pub fn new(type_: String, payload: String) -> Self {
+            Self {
+                time: timestamp(),
+                fields: logline::parse(&payload).map(Box::new),
+                type_,
+                payload,
+            }
+        }
     }
 
     pub(crate) fn timestamp() -> String {
@@ -406,6 +448,18 @@ pub mod api_log {
         format!("{yy:02}-{mo:02}-{dd:02} {hh:02}:{mm:02}:{ss:02}")
     }
 
+    /// [timestamp] for file names, `yymmdd-HHMMSS`
+    pub(crate) fn file_stamp() -> String {
+        timestamp()
+            .chars()
+            .filter_map(|c| match c {
+                '-' | ':' => None,
+                ' ' => Some('-'),
+                c => Some(c),
+            })
+            .collect()
+    }
+
     fn is_leap(y: i64) -> bool {
         (y % 4 == 0 && y % 100 != 0) || (y % 400 == 0)
     }
@@ -426,11 +480,7 @@ pub mod api_log {
                             .and_then(|p| p.as_str())
                             .unwrap_or("")
                             .to_owned();
-                        Some(LogEntry {
-                            type_,
-                            payload,
-                            time: timestamp(),
-                        })
+                        Some(LogEntry::new(type_, payload))
                     }
                     Err(_) => {
                         log::warn!("Failed to parse log line as JSON: {line}");
@@ -441,14 +491,32 @@ pub mod api_log {
             .collect()
     }
 
-    pub fn get_logs(level: Option<&str>) -> Result<Vec<LogEntry>> {
-        let url = match level {
+    fn logs_path(level: Option<&str>) -> String {
+        match level {
             Some(l) if !l.is_empty() && l != "unknown" => format!("/logs?level={l}"),
             _ => "/logs".to_owned(),
-        };
-        request(Method::Get, &url, None).and_then(|r| {
-            let body = r.as_str()?;
-            Ok(parse_log_entries(body))
+        }
+    }
+
+    pub fn get_logs(level: Option<&str>) -> Result<Vec<LogEntry>> {
+        request(Method::Get, &logs_path(level), None).map(|r| parse_log_entries(r.as_str()))
+    }
+
+    /// Pass every entry the core logs during the next `secs` seconds to `on_entry`
+    pub fn stream_logs(
+        level: Option<&str>,
+        secs: u64,
+        on_entry: &mut dyn FnMut(LogEntry),
+    ) -> Result<()> {
+        client().stream_lines(&logs_path(level), secs, &mut |line| {
+            parse_log_entries(line).into_iter().for_each(&mut *on_entry)
         })
     }
+
+    /// Entries the core logs during the next `secs` seconds
+    pub fn collect_logs(level: Option<&str>, secs: u64) -> Result<Vec<LogEntry>> {
+        let mut entries = Vec::new();
+        stream_logs(level, secs, &mut |e| entries.push(e))?;
+        Ok(entries)
+    }
 }
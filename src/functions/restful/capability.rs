@@ -0,0 +1,259 @@
+//! What the running core's controller can do
+//!
+//! mihomo and sing-box share the clash API, but sing-box only implements part
+//! of it and adds routes with new releases. Instead of branching on
+//! [CoreType], operations ask [supports] whether their endpoint exists.
+//!
+//! Both cores route with chi, which answers `404` for an unknown path and
+//! `405` for a known path with another method, so a `GET` tells whether a
+//! `POST`-only route exists without triggering it. Until a controller has been
+//! probed, or when a probe is inconclusive, the [assumed](Capabilities::assumed)
+//! set of the configured core is used.
+
+use std::collections::HashSet;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use super::client::ControllerClient;
+use super::{Method, Result, core_detect};
+use crate::config::{CONFIG, CoreType, controller_generation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// `tun` in `/configs`, patchable
+    Tun,
+    FlushFakeIp,
+    FlushDns,
+    DnsQuery,
+    /// `POST /configs/geo`
+    UpdateGeo,
+    UpgradeCore,
+    UpgradeUi,
+    UpgradeGeo,
+    ProxyProviders,
+    RuleProviders,
+}
+
+/// How a [Capability] is detected
+enum Probe {
+    /// The route exists
+    Route(&'static str),
+    /// `GET /configs` has the key
+    ConfigKey(&'static str),
+}
+
+impl Capability {
+    pub const ALL: [Capability; 10] = [
+        Self::Tun,
+        Self::FlushFakeIp,
+        Self::FlushDns,
+        Self::DnsQuery,
+        Self::UpdateGeo,
+        Self::UpgradeCore,
+        Self::UpgradeUi,
+        Self::UpgradeGeo,
+        Self::ProxyProviders,
+        Self::RuleProviders,
+    ];
+
+    fn probe(self) -> Probe {
+        match self {
+            Self::Tun => Probe::ConfigKey("tun"),
+            Self::FlushFakeIp => Probe::Route("/cache/fakeip/flush"),
+            Self::FlushDns => Probe::Route("/cache/dns/flush"),
+            Self::DnsQuery => Probe::Route("/dns/query"),
+            Self::UpdateGeo => Probe::Route("/configs/geo"),
+            Self::UpgradeCore => Probe::Route("/upgrade"),
+            Self::UpgradeUi => Probe::Route("/upgrade/ui"),
+            Self::UpgradeGeo => Probe::Route("/upgrade/geo"),
+            Self::ProxyProviders => Probe::Route("/providers/proxies"),
+            Self::RuleProviders => Probe::Route("/providers/rules"),
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tun => "TUN",
+            Self::FlushFakeIp => "flush fake-ip",
+            Self::FlushDns => "flush DNS",
+            Self::DnsQuery => "DNS query",
+            Self::UpdateGeo => "update GEO",
+            Self::UpgradeCore => "upgrade core",
+            Self::UpgradeUi => "upgrade UI",
+            Self::UpgradeGeo => "upgrade GEO",
+            Self::ProxyProviders => "proxy providers",
+            Self::RuleProviders => "rule providers",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub core: CoreType,
+    /// `/version` body of the probed core, empty if assumed
+    pub version: String,
+    supported: HashSet<Capability>,
+}
+
+impl Capabilities {
+    /// What `core` is known to support, before or without probing
+    pub fn assumed(core: CoreType) -> Self {
+        let supported = match core {
+            CoreType::Mihomo => Capability::ALL.into_iter().collect(),
+            CoreType::Singbox => HashSet::new(),
+        };
+        Self {
+            core,
+            version: String::new(),
+            supported,
+        }
+    }
+
+    pub fn supports(&self, cap: Capability) -> bool {
+        self.supported.contains(&cap)
+    }
+
+    /// Labels of the capabilities the core lacks
+    pub fn missing(&self) -> Vec<&'static str> {
+        Capability::ALL
+            .into_iter()
+            .filter(|cap| !self.supports(*cap))
+            .map(Capability::label)
+            .collect()
+    }
+}
+
+/// Whether a probe reply shows the route, `None` if it cannot tell
+fn route_exists(status_code: i32) -> Option<bool> {
+    match status_code {
+        404 => Some(false),
+        401 | 403 => None,
+        500.. => None,
+        _ => Some(true),
+    }
+}
+
+/// Detect the core behind `client` and probe every [Capability]
+pub fn probe(client: &dyn ControllerClient) -> Result<Capabilities> {
+    let version = client.version()?;
+    let core = core_detect::parse_core_type(&version).map_err(minreq::Error::SerdeJsonError)?;
+    let mut caps = Capabilities::assumed(core);
+    caps.version = version;
+
+    let config_keys: Option<serde_json::Map<String, serde_json::Value>> = client
+        .send(Method::Get, "/configs", None)
+        .ok()
+        .filter(|r| r.status_code == 200)
+        .and_then(|r| r.json().ok());
+    for cap in Capability::ALL {
+        let found = match cap.probe() {
+            Probe::Route(path) => client
+                .send(Method::Get, path, None)
+                .ok()
+                .and_then(|r| route_exists(r.status_code)),
+            Probe::ConfigKey(key) => config_keys
+                .as_ref()
+                .map(|keys| keys.get(key).is_some_and(|v| !v.is_null())),
+        };
+        match found {
+            Some(true) => {
+                caps.supported.insert(cap);
+            }
+            Some(false) => {
+                caps.supported.remove(&cap);
+            }
+            None => {}
+        }
+    }
+    Ok(caps)
+}
+
+/// Probed capabilities and the controller generation they belong to
+type Probed = Option<(u64, Arc<Capabilities>)>;
+static CURRENT: LazyLock<RwLock<Probed>> = LazyLock::new(|| RwLock::new(None));
+
+/// Capabilities of the current controller: probed if [set_current] was
+/// called since the last controller switch, assumed from the configured
+/// core otherwise
+pub fn current() -> Arc<Capabilities> {
+    let generation = controller_generation();
+    match CURRENT.read().unwrap().as_ref() {
+        Some((g, caps)) if *g == generation => Arc::clone(caps),
+        _ => Arc::new(Capabilities::assumed(CONFIG.core_type())),
+    }
+}
+
+/// Whether `/version` reports another core than the last probe
+pub fn needs_probe(version: &str) -> bool {
+    let generation = controller_generation();
+    CURRENT
+        .read()
+        .unwrap()
+        .as_ref()
+        .is_none_or(|(g, caps)| *g != generation || caps.version != version)
+}
+
+pub fn set_current(caps: Capabilities) {
+    *CURRENT.write().unwrap() = Some((controller_generation(), Arc::new(caps)));
+}
+
+/// Shorthand for `current().supports(cap)`
+pub fn supports(cap: Capability) -> bool {
+    current().supports(cap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::restful::client::FixtureClient;
+
+    #[test]
+    fn recorded_mihomo_supports_everything() {
+        let caps = probe(&FixtureClient::default()).unwrap();
+        assert_eq!(caps.core, CoreType::Mihomo);
+        assert_eq!(caps.missing(), ["proxy providers", "rule providers"]);
+        // Tun comes from configs.json; the providers routes are not recorded
+        assert!(caps.supports(Capability::Tun));
+        assert!(caps.supports(Capability::FlushFakeIp));
+        assert!(caps.supports(Capability::UpgradeGeo));
+        assert!(!caps.supports(Capability::ProxyProviders));
+    }
+
+    #[test]
+    fn singbox_lights_up_what_it_serves() {
+        let client = FixtureClient::empty()
+            .route(
+                Method::Get,
+                "/version",
+                200,
+                r#"{"meta":true,"version":"sing-box 1.13.11"}"#,
+            )
+            .route(
+                Method::Get,
+                "/configs",
+                200,
+                r#"{"mode":"rule","allow-lan":false}"#,
+            )
+            .route(Method::Post, "/cache/fakeip/flush", 204, "")
+            .route(Method::Get, "/dns/query", 400, "name is required");
+        assert!(!Capabilities::assumed(CoreType::Singbox).supports(Capability::FlushFakeIp));
+        let caps = probe(&client).unwrap();
+        assert_eq!(caps.core, CoreType::Singbox);
+        assert!(caps.supports(Capability::FlushFakeIp));
+        assert!(caps.supports(Capability::DnsQuery));
+        assert!(!caps.supports(Capability::Tun));
+        assert!(!caps.supports(Capability::FlushDns));
+        assert!(!caps.supports(Capability::UpgradeCore));
+    }
+
+    #[test]
+    fn inconclusive_probes_keep_assumption() {
+        let client = FixtureClient::empty()
+            .route(Method::Get, "/version", 200, r#"{"version":"v1.19.2"}"#)
+            .route(Method::Get, "/configs", 401, "Unauthorized")
+            .route(Method::Get, "/upgrade", 401, "Unauthorized");
+        let caps = probe(&client).unwrap();
+        assert!(caps.supports(Capability::Tun));
+        assert!(caps.supports(Capability::UpgradeCore));
+        assert!(!caps.supports(Capability::UpgradeUi));
+    }
+}
@@ -0,0 +1,633 @@
+//! Controller API client
+//!
+//! Every controller call goes through the process-wide [ControllerClient]
+//! returned by [client]. [MinreqClient] talks to the configured core;
+//! tests install a `FixtureClient`, which answers from recorded JSON.
+
+use super::*;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::{Duration, Instant};
+
+use super::config_struct::ClashConfig;
+use super::connection::ConnInfo;
+use super::control::Upgrade;
+use super::proxies::ProxiesResponse;
+
+/// Status and body of a controller response
+#[derive(Clone, Debug)]
+pub struct Reply {
+    pub status_code: i32,
+    body: String,
+}
+
+impl Reply {
+    pub fn new(status_code: i32, body: impl Into<String>) -> Self {
+        Self {
+            status_code,
+            body: body.into(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.body
+    }
+
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_str(&self.body).map_err(minreq::Error::SerdeJsonError)
+    }
+}
+
+/// Access to a core's external controller.
+///
+/// Implementors only provide [send](ControllerClient::send); the endpoint
+/// methods are built on top of it.
+pub trait ControllerClient: Send + Sync {
+    /// Send one request, e.g. `GET /proxies`
+    fn send(&self, method: Method, path: &str, payload: Option<String>) -> Result<Reply>;
+
+    /// [send](ControllerClient::send) for requests that outlast [timeout](ControllerClient::timeout)
+    fn send_slow(
+        &self,
+        method: Method,
+        path: &str,
+        payload: Option<String>,
+        _timeout: u64,
+    ) -> Result<Reply> {
+        self.send(method, path, payload)
+    }
+
+    /// Request timeout in seconds, also the budget of delay tests
+    fn timeout(&self) -> u64 {
+        DEFAULT_TIMEOUT
+    }
+
+    /// Lines of a streaming endpoint such as `/logs`, read for `secs` seconds
+    fn stream_lines(&self, path: &str, _secs: u64, on_line: &mut dyn FnMut(&str)) -> Result<()> {
+        let reply = self
+            .send(Method::Get, path, None)
+            .and_then(ensure_success)?;
+        reply
+            .as_str()
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+            .for_each(on_line);
+        Ok(())
+    }
+
+    // ── Control ─────────────────────────────────────────────────
+
+    /// Get clash core version
+    ///
+    /// for mihomo, it's like `{"meta": true, "version": "v1.1.1"}`
+    fn version(&self) -> Result<String> {
+        self.send(Method::Get, "/version", None)
+            .map(|r| r.as_str().to_owned())
+    }
+
+    /// Restart clash core via http
+    ///
+    /// usually, an empty str is returned
+    fn restart(&self, payload: Option<String>) -> Result<()> {
+        self.send(
+            Method::Post,
+            "/restart",
+            Some(payload.unwrap_or(DEFAULT_PAYLOAD.to_string())),
+        )
+        .map(|_| ())
+    }
+
+    /// Let mihomo download and apply an upgrade itself, see [Upgrade]
+    fn upgrade(&self, target: Upgrade) -> Result<String> {
+        self.send_slow(Method::Post, &target.path(), None, UPGRADE_TIMEOUT)
+            .and_then(ensure_success)
+            .map(|r| r.as_str().to_owned())
+    }
+
+    // ── Proxies ─────────────────────────────────────────────────
+
+    fn fetch_proxies(&self) -> Result<ProxiesResponse> {
+        self.send(Method::Get, "/proxies", None)
+            .and_then(|r| r.json())
+    }
+
+    fn select_proxy(&self, group: &str, node: &str) -> Result<()> {
+        let payload = serde_json::json!({ "name": node }).to_string();
+        self.send(
+            Method::Put,
+            &format!("/proxies/{}", encode_path(group)),
+            Some(payload),
+        )
+        .map(|_| ())
+    }
+
+    fn test_proxy_delay(&self, name: &str, url: Option<&str>, timeout: u64) -> Result<Option<u64>> {
+        let endpoint = format!(
+            "/proxies/{}/delay?url={}&timeout={timeout}",
+            encode_path(name),
+            proxies::encode_query(url.unwrap_or(proxies::DEFAULT_TEST_URL))
+        );
+        self.send(Method::Get, &endpoint, None).and_then(|r| {
+            let v: serde_json::Value = r.json()?;
+            let delay = v.get("delay").and_then(|d| {
+                d.as_u64()
+                    .or_else(|| d.as_str().and_then(|s| s.parse().ok()))
+            });
+            Ok(delay.filter(|&d| d > 0))
+        })
+    }
+
+    fn test_group_delay(
+        &self,
+        name: &str,
+        url: Option<&str>,
+        timeout: u64,
+    ) -> Result<HashMap<String, u64>> {
+        let endpoint = format!(
+            "/group/{}/delay?url={}&timeout={timeout}",
+            encode_path(name),
+            proxies::encode_query(url.unwrap_or(proxies::DEFAULT_TEST_URL))
+        );
+        self.send(Method::Get, &endpoint, None).and_then(|r| {
+            let v: serde_json::Value = r.json()?;
+            let map = v
+                .as_object()
+                .map(|obj| {
+                    obj.iter()
+                        .filter_map(|(k, v)| {
+                            let delay = v
+                                .as_u64()
+                                .or_else(|| v.as_str().and_then(|s| s.parse().ok()))?;
+                            (delay > 0).then(|| (k.clone(), delay))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            Ok(map)
+        })
+    }
+
+    // ── Connections ─────────────────────────────────────────────
+
+    fn get_connections(&self) -> Result<ConnInfo> {
+        self.send(Method::Get, "/connections", None)
+            .and_then(|r| r.json())
+    }
+
+    fn terminate_all_connections(&self) -> Result<()> {
+        self.send(Method::Delete, "/connections", None).map(|_| ())
+    }
+
+    /// Empty body is returned if the connection is terminated successfully
+    fn terminate_connection(&self, id: Option<String>) -> Result<bool> {
+        self.send(
+            Method::Delete,
+            &format!(
+                "/connections{}",
+                id.map(|c| format!("/{c}")).unwrap_or_default()
+            ),
+            None,
+        )
+        .map(|r| {
+            // try to catch failure
+            log::debug!("terminate conn:{}", r.as_str());
+            r.as_str().is_empty()
+        })
+    }
+
+    // ── Configs ─────────────────────────────────────────────────
+
+    fn fetch_config(&self) -> Result<ClashConfig> {
+        self.send(Method::Get, "/configs", None)
+            .and_then(|r| r.json())
+    }
+
+    fn reload_config(&self, path: &str) -> Result<String> {
+        self.send(
+            Method::Put,
+            "/configs?force=true",
+            Some(serde_json::json!({ "path": path, "payload": "" }).to_string()),
+        )
+        .and_then(ensure_success)
+        .map(|r| r.as_str().to_owned())
+    }
+
+    fn patch_config(&self, payload: String) -> Result<String> {
+        self.send(Method::Patch, "/configs", Some(payload))
+            .map(|r| r.as_str().to_owned())
+    }
+
+    // ── Cache ───────────────────────────────────────────────────
+
+    fn flush_fakeip(&self) -> Result<()> {
+        self.send(Method::Post, "/cache/fakeip/flush", None)
+            .map(|_| ())
+    }
+
+    fn flush_dns(&self) -> Result<()> {
+        self.send(Method::Post, "/cache/dns/flush", None)
+            .map(|_| ())
+    }
+
+    // ── Geo ─────────────────────────────────────────────────────
+
+    fn upgrade_geo(&self) -> Result<()> {
+        self.send(Method::Post, "/configs/geo", None).map(|_| ())
+    }
+}
+
+/// Client for the controller of the configured core, see [CONFIG]
+///
+/// `unix://` controllers are reached over the socket and `https://` ones
+/// with the trust from `controller_tls`; plain `http://` goes through minreq.
+pub struct MinreqClient;
+
+impl ControllerClient for MinreqClient {
+    fn send(&self, method: Method, path: &str, payload: Option<String>) -> Result<Reply> {
+        self.send_slow(method, path, payload, self.timeout())
+    }
+
+    fn send_slow(
+        &self,
+        method: Method,
+        path: &str,
+        payload: Option<String>,
+        timeout: u64,
+    ) -> Result<Reply> {
+        if path != "/version" && crate::config::is_core_mismatch() {
+            return Err(minreq::Error::IoError(std::io::Error::other(
+                "core mismatch",
+            )));
+        }
+        let controller = CONFIG.controller_for_core();
+        if let Some(sock) = controller.strip_prefix("unix://") {
+            #[cfg(unix)]
+            return super::unix::send(
+                sock,
+                method,
+                path,
+                payload,
+                CONFIG.secret_for_core(),
+                timeout,
+            );
+            #[cfg(not(unix))]
+            return Err(minreq::Error::IoError(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("unix socket controller {sock} is not supported on this platform"),
+            )));
+        }
+        if controller.starts_with("https://") {
+            return super::tls::send(
+                controller,
+                method,
+                path,
+                payload,
+                CONFIG.secret_for_core(),
+                timeout,
+            );
+        }
+        let mut req = minreq::Request::new(method, format!("{controller}{path}"));
+        if let Some(kv) = payload {
+            req = req
+                .with_header("Content-Type", "application/json")
+                .with_body(kv);
+        }
+        if let Some(s) = CONFIG.secret_for_core() {
+            req = req.with_header(headers::AUTHORIZATION, format!("Bearer {s}"));
+        }
+        let r = req.with_timeout(timeout).send()?;
+        Ok(Reply::new(
+            r.status_code,
+            String::from_utf8_lossy(r.as_bytes()),
+        ))
+    }
+
+    fn timeout(&self) -> u64 {
+        timeout!()
+    }
+
+    fn stream_lines(&self, path: &str, secs: u64, on_line: &mut dyn FnMut(&str)) -> Result<()> {
+        if crate::config::is_core_mismatch() {
+            return Err(minreq::Error::IoError(std::io::Error::other(
+                "core mismatch",
+            )));
+        }
+        let deadline = Instant::now() + Duration::from_secs(secs);
+        // Short reads so the deadline is noticed on a quiet stream
+        let tick = Some(Duration::from_secs(1));
+        let controller = CONFIG.controller_for_core();
+        let secret = CONFIG.secret_for_core();
+        if let Some(sock) = controller.strip_prefix("unix://") {
+            #[cfg(unix)]
+            {
+                let mut stream = std::os::unix::net::UnixStream::connect(sock)?;
+                stream.set_read_timeout(tick)?;
+                return super::http1::stream_lines(
+                    &mut stream,
+                    "localhost",
+                    path,
+                    secret,
+                    deadline,
+                    on_line,
+                );
+            }
+            #[cfg(not(unix))]
+            return Err(minreq::Error::IoError(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                format!("unix socket controller {sock} is not supported on this platform"),
+            )));
+        }
+        if let Some((host, port)) = super::tls::host_port(controller) {
+            let mut stream = super::tls::connect(
+                super::tls::controller_config()?,
+                host,
+                port,
+                Duration::from_secs(self.timeout()),
+            )?;
+            stream.sock.set_read_timeout(tick)?;
+            return super::http1::stream_lines(&mut stream, host, path, secret, deadline, on_line);
+        }
+        let addr = controller
+            .strip_prefix("http://")
+            .unwrap_or(controller)
+            .split('/')
+            .next()
+            .unwrap_or_default();
+        let mut stream = std::net::TcpStream::connect(addr)?;
+        stream.set_read_timeout(tick)?;
+        super::http1::stream_lines(&mut stream, addr, path, secret, deadline, on_line)
+    }
+}
+
+static CLIENT: LazyLock<RwLock<Arc<dyn ControllerClient>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(MinreqClient)));
+
+/// The client every `restful` function goes through
+pub fn client() -> Arc<dyn ControllerClient> {
+    Arc::clone(&CLIENT.read().unwrap())
+}
+
+/// Replace the process-wide client
+#[cfg(test)]
+pub fn set_client(client: Arc<dyn ControllerClient>) {
+    *CLIENT.write().unwrap() = client;
+}
+
+/// Route every `restful` call of this test binary to the recorded session
+#[cfg(test)]
+pub fn use_fixtures() {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| set_client(Arc::new(FixtureClient::default())));
+}
+
+#[cfg(test)]
+pub use fixture::FixtureClient;
+
+#[cfg(test)]
+mod fixture {
+    use super::*;
+    use std::sync::Mutex;
+
+    macro_rules! fixture {
+        ($path:literal) => {
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $path))
+        };
+    }
+
+    /// Answers from recorded controller responses.
+    ///
+    /// Routes are matched in order on method and path, ignoring the query;
+    /// `*` in a pattern matches one path segment. Like the cores' router,
+    /// a known path with another method gets a 405 and an unknown one a 404.
+    pub struct FixtureClient {
+        routes: Vec<(Method, &'static str, Reply)>,
+        calls: Mutex<Vec<String>>,
+    }
+
+    impl Default for FixtureClient {
+        /// A recorded mihomo session
+        fn default() -> Self {
+            Self::empty()
+                .route(
+                    Method::Get,
+                    "/version",
+                    200,
+                    r#"{"meta":true,"version":"v1.19.2"}"#,
+                )
+                .route(
+                    Method::Get,
+                    "/configs",
+                    200,
+                    fixture!("src/functions/restful/tests/fixtures/configs.json"),
+                )
+                .route(
+                    Method::Get,
+                    "/proxies",
+                    200,
+                    fixture!("src/tui/tab/proxies/tests/fixtures/proxies.json"),
+                )
+                .route(Method::Get, "/proxies/*/delay", 200, r#"{"delay":231}"#)
+                .route(
+                    Method::Get,
+                    "/group/*/delay",
+                    200,
+                    fixture!("src/functions/restful/tests/fixtures/group_delay.json"),
+                )
+                .route(Method::Put, "/proxies/*", 204, "")
+                .route(
+                    Method::Get,
+                    "/connections",
+                    200,
+                    fixture!("src/functions/restful/tests/fixtures/connections.json"),
+                )
+                .route(Method::Delete, "/connections", 204, "")
+                .route(Method::Delete, "/connections/*", 204, "")
+                .route(Method::Patch, "/configs", 204, "")
+                .route(Method::Put, "/configs", 204, "")
+                .route(Method::Post, "/configs/geo", 204, "")
+                .route(Method::Post, "/cache/*/flush", 204, "")
+                .route(Method::Post, "/upgrade", 200, r#"{"status":"ok"}"#)
+                .route(Method::Post, "/upgrade/*", 200, r#"{"status":"ok"}"#)
+                .route(
+                    Method::Get,
+                    "/dns/query",
+                    200,
+                    fixture!("src/functions/restful/tests/fixtures/dns_query.json"),
+                )
+                .route(
+                    Method::Get,
+                    "/logs",
+                    200,
+                    fixture!("src/functions/restful/tests/fixtures/logs.jsonl"),
+                )
+        }
+    }
+
+    impl FixtureClient {
+        pub fn empty() -> Self {
+            Self {
+                routes: Vec::new(),
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Answer `method pattern` with `body`; earlier routes win
+        pub fn route(
+            mut self,
+            method: Method,
+            pattern: &'static str,
+            status_code: i32,
+            body: &str,
+        ) -> Self {
+            self.routes
+                .push((method, pattern, Reply::new(status_code, body)));
+            self
+        }
+
+        /// Requests seen so far, as `METHOD /path?query`
+        pub fn calls(&self) -> Vec<String> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    fn path_matches(pattern: &str, path: &str) -> bool {
+        let path = path.split_once('?').map_or(path, |(p, _)| p);
+        let mut pat = pattern.split('/');
+        let mut seg = path.split('/');
+        loop {
+            match (pat.next(), seg.next()) {
+                (None, None) => return true,
+                (Some("*"), Some(s)) if !s.is_empty() => {}
+                (Some(p), Some(s)) if p == s => {}
+                _ => return false,
+            }
+        }
+    }
+
+    impl ControllerClient for FixtureClient {
+        fn send(&self, method: Method, path: &str, _payload: Option<String>) -> Result<Reply> {
+            self.calls.lock().unwrap().push(format!("{method} {path}"));
+            Ok(self
+                .routes
+                .iter()
+                .find(|(m, p, _)| *m == method && path_matches(p, path))
+                .map(|(_, _, reply)| reply.clone())
+                .unwrap_or_else(|| {
+                    if self.routes.iter().any(|(_, p, _)| path_matches(p, path)) {
+                        Reply::new(405, "")
+                    } else {
+                        Reply::new(404, "404 page not found")
+                    }
+                }))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn pattern_matching() {
+            assert!(path_matches("/proxies", "/proxies"));
+            assert!(path_matches("/proxies/*", "/proxies/GLOBAL"));
+            assert!(path_matches(
+                "/proxies/*/delay",
+                "/proxies/a%20b/delay?url=x"
+            ));
+            assert!(!path_matches("/proxies/*", "/proxies"));
+            assert!(!path_matches("/proxies", "/proxies/GLOBAL"));
+        }
+
+        #[test]
+        fn unmatched_is_404_and_calls_are_recorded() {
+            let c = FixtureClient::empty().route(Method::Get, "/version", 200, "{}");
+            assert_eq!(
+                c.send(Method::Get, "/version", None).unwrap().status_code,
+                200
+            );
+            assert_eq!(
+                c.send(Method::Get, "/rules", None).unwrap().status_code,
+                404
+            );
+            assert_eq!(
+                c.send(Method::Post, "/version", None).unwrap().status_code,
+                405
+            );
+            assert_eq!(
+                c.calls(),
+                vec!["GET /version", "GET /rules", "POST /version"]
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_endpoints_parse() {
+        let c = FixtureClient::default();
+        assert!(c.version().unwrap().contains("v1.19.2"));
+        assert!(c.fetch_proxies().unwrap().proxies.contains_key("GLOBAL"));
+        let conns = c.get_connections().unwrap();
+        assert_eq!(conns.connections.unwrap().len(), 3);
+        let cfg = c.fetch_config().unwrap();
+        assert_eq!(cfg.mode.to_string(), "Rule");
+        let logs = c.send(Method::Get, "/logs?level=info", None).unwrap();
+        assert_eq!(api_log::parse_log_entries(logs.as_str()).len(), 2);
+        assert!(c.flush_dns().is_ok());
+    }
+
+    #[test]
+    fn streamed_logs_are_collected() {
+        let c = FixtureClient::default();
+        let mut lines = Vec::new();
+        c.stream_lines("/logs?level=warning", 1, &mut |l| lines.push(l.to_owned()))
+            .unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(c.stream_lines("/nope", 1, &mut |_| {}).is_err());
+    }
+
+    #[test]
+    fn delay_tests_parse_recorded_results() {
+        let c = FixtureClient::default();
+        assert_eq!(c.test_proxy_delay("n1", None, 5000).unwrap(), Some(231));
+        let group = c.test_group_delay("Sl-pvd0", None, 5000).unwrap();
+        // 0 means timeout and is dropped
+        assert_eq!(group.get("vmess-ipdktc33"), Some(&120));
+        assert!(!group.contains_key("DIRECT"));
+        assert_eq!(
+            c.calls()[1],
+            "GET /group/Sl-pvd0/delay?url=https://www.gstatic.com/generate_204&timeout=5000"
+        );
+    }
+
+    #[test]
+    fn terminate_reports_empty_body_as_success() {
+        let c = FixtureClient::default();
+        assert!(c.terminate_connection(Some("abc".to_owned())).unwrap());
+        assert_eq!(c.calls(), vec!["DELETE /connections/abc"]);
+    }
+
+    #[test]
+    fn upgrade_posts_to_target_path() {
+        let c = FixtureClient::default();
+        assert_eq!(
+            c.upgrade(Upgrade::Core { alpha: true }).unwrap(),
+            r#"{"status":"ok"}"#
+        );
+        c.upgrade(Upgrade::Geo).unwrap();
+        assert_eq!(
+            c.calls(),
+            vec!["POST /upgrade?channel=alpha", "POST /upgrade/geo"]
+        );
+    }
+
+    #[test]
+    fn reload_surfaces_http_errors() {
+        let c = FixtureClient::empty().route(Method::Put, "/configs", 400, "bad path");
+        let e = c.reload_config("/nope").unwrap_err();
+        assert_eq!(e.to_string(), "HTTP 400: bad path");
+    }
+}
@@ -1,7 +1,5 @@
 use serde::Deserialize;
 
-use super::*;
-
 /// Response from the `/version` endpoint.
 ///
 /// Sing-box ≥1.13.x also returns `"meta": true` in its clash API emulation,
@@ -11,19 +9,22 @@ struct VersionResponse {
     version: String,
 }
 
-/// Detect which core is actually running by querying `/version`.
+/// Detect which core is actually running from a `/version` body.
 ///
 /// Checks the `version` field: sing-box reports e.g. `"sing-box 1.13.11"`,
 /// mihomo reports e.g. `"v1.18.10"`.
-pub fn detect_core_type() -> Result<crate::config::CoreType> {
-    request(Method::Get, "/version", None).and_then(|r| {
-        let v: VersionResponse = r.json()?;
-        if v.version.contains("sing-box") {
-            Ok(crate::config::CoreType::Singbox)
+pub fn parse_core_type(body: &str) -> serde_json::Result<crate::config::CoreType> {
+    serde_json::from_str::<VersionResponse>(body).map(|v| v.core_type())
+}
+
+impl VersionResponse {
+    fn core_type(&self) -> crate::config::CoreType {
+        if self.version.contains("sing-box") {
+            crate::config::CoreType::Singbox
         } else {
-            Ok(crate::config::CoreType::Mihomo)
+            crate::config::CoreType::Mihomo
         }
-    })
+    }
 }
 
 #[cfg(test)]
@@ -43,4 +44,17 @@ mod tests {
         let v: VersionResponse = serde_json::from_str(json).unwrap();
         assert!(!v.version.contains("sing-box"));
     }
+
+    #[test]
+    fn parse_core_type_from_body() {
+        assert_eq!(
+            parse_core_type(r#"{"version": "sing-box 1.13.11"}"#).unwrap(),
+            crate::config::CoreType::Singbox
+        );
+        assert_eq!(
+            parse_core_type(r#"{"meta": true, "version": "v1.18.10"}"#).unwrap(),
+            crate::config::CoreType::Mihomo
+        );
+        assert!(parse_core_type("404 page not found").is_err());
+    }
 }
@@ -0,0 +1,263 @@
+use super::*;
+use serde::Deserialize;
+use std::net::IpAddr;
+
+/// mihomo's `dns.fake-ip-range` default
+const DEFAULT_FAKE_IP_RANGE: &str = "198.18.0.1/16";
+
+/// Answer of `/dns/query`, field names follow the core's JSON
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct DnsResponse {
+    /// RCODE, `0` is NOERROR
+    pub status: u16,
+    pub question: Vec<DnsQuestion>,
+    pub answer: Vec<DnsRecord>,
+    pub authority: Vec<DnsRecord>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct DnsQuestion {
+    pub name: String,
+    pub qtype: u16,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct DnsRecord {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub rtype: u16,
+    #[serde(rename = "TTL")]
+    pub ttl: u32,
+    /// Record data as the core prints it, e.g. `1.2.3.4` or `10 mx.example.com.`
+    pub data: String,
+}
+
+impl DnsRecord {
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.data.trim().parse().ok()
+    }
+}
+
+/// Resolve `name` through the core's DNS, `qtype` is a record type name like `AAAA`
+///
+/// API: GET /dns/query?name={name}&type={qtype}
+///
+/// Only mihomo has this endpoint.
+pub fn query(name: &str, qtype: &str) -> Result<DnsResponse> {
+    request(
+        Method::Get,
+        &format!(
+            "/dns/query?name={}&type={}",
+            encode_path(name),
+            encode_path(&qtype.to_ascii_uppercase())
+        ),
+        None,
+    )
+    .and_then(ensure_success)
+    .and_then(|r| r.json())
+}
+
+impl DnsResponse {
+    /// One line per record, fake-ip answers are marked
+    pub fn build(&self, fake_ip: &FakeIpRange) -> Vec<String> {
+        let mut lines = vec![format!("status: {}", rcode_name(self.status))];
+        if let Some(q) = self.question.first() {
+            lines.push(format!("question: {} {}", q.name, type_name(q.qtype)));
+        }
+        if self.answer.is_empty() {
+            lines.push("no answer".to_owned());
+        }
+        for (section, records) in [("answer", &self.answer), ("authority", &self.authority)] {
+            for r in records {
+                let mark = if r.ip().is_some_and(|ip| fake_ip.contains(ip)) {
+                    "  (fake-ip)"
+                } else {
+                    ""
+                };
+                lines.push(format!(
+                    "{section}: {} {} ttl={} {}{mark}",
+                    r.name,
+                    type_name(r.rtype),
+                    r.ttl,
+                    r.data.trim()
+                ));
+            }
+        }
+        lines
+    }
+}
+
+pub fn type_name(rtype: u16) -> String {
+    match rtype {
+        1 => "A",
+        2 => "NS",
+        5 => "CNAME",
+        6 => "SOA",
+        12 => "PTR",
+        15 => "MX",
+        16 => "TXT",
+        28 => "AAAA",
+        33 => "SRV",
+        64 => "SVCB",
+        65 => "HTTPS",
+        _ => return format!("TYPE{rtype}"),
+    }
+    .to_owned()
+}
+
+fn rcode_name(rcode: u16) -> String {
+    match rcode {
+        0 => "NOERROR",
+        1 => "FORMERR",
+        2 => "SERVFAIL",
+        3 => "NXDOMAIN",
+        4 => "NOTIMP",
+        5 => "REFUSED",
+        _ => return format!("RCODE{rcode}"),
+    }
+    .to_owned()
+}
+
+/// The networks the core hands fake IPs out of
+#[derive(Debug, Clone)]
+pub struct FakeIpRange(Vec<(IpAddr, u8)>);
+
+impl Default for FakeIpRange {
+    fn default() -> Self {
+        Self(parse_cidr(DEFAULT_FAKE_IP_RANGE).into_iter().collect())
+    }
+}
+
+impl FakeIpRange {
+    /// `dns.fake-ip-range(6)` of a mihomo config, the default for missing keys
+    pub fn from_config(config: &serde_yml::Value) -> Self {
+        let dns = &config["dns"];
+        let v4 = dns["fake-ip-range"]
+            .as_str()
+            .unwrap_or(DEFAULT_FAKE_IP_RANGE);
+        let v6 = dns["fake-ip-range6"].as_str();
+        Self(
+            std::iter::once(v4)
+                .chain(v6)
+                .filter_map(parse_cidr)
+                .collect(),
+        )
+    }
+
+    /// Ranges of the local core's running config, the default for a remote
+    /// controller or when the file cannot be read
+    pub fn current() -> Self {
+        if CONFIG.is_remote() {
+            return Self::default();
+        }
+        std::fs::read_to_string(&CONFIG.cfg_file.mihomo.core.config_path)
+            .ok()
+            .and_then(|s| serde_yml::from_str(&s).ok())
+            .map(|v| Self::from_config(&v))
+            .unwrap_or_default()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|&(net, prefix)| match (net, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                same_prefix(u32::from(net).into(), u32::from(ip).into(), prefix, 32)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                same_prefix(u128::from(net), u128::from(ip), prefix, 128)
+            }
+            _ => false,
+        })
+    }
+}
+
+fn same_prefix(a: u128, b: u128, prefix: u8, bits: u8) -> bool {
+    let shift = bits.saturating_sub(prefix.min(bits));
+    shift >= bits || (a >> shift) == (b >> shift)
+}
+
+fn parse_cidr(s: &str) -> Option<(IpAddr, u8)> {
+    let (ip, prefix) = s.trim().split_once('/')?;
+    Some((ip.parse().ok()?, prefix.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = r#"{"AD":false,"Answer":[
+        {"TTL":1,"data":"198.18.0.42","name":"example.com.","type":1}],
+        "CD":false,"Question":[{"Name":"example.com.","Qtype":1,"Qclass":1}],
+        "RA":true,"RD":true,"Status":0,"TC":false}"#;
+
+    #[test]
+    fn parse_mihomo_answer() {
+        let resp: DnsResponse = serde_json::from_str(BODY).unwrap();
+        assert_eq!(resp.status, 0);
+        assert_eq!(resp.question[0].name, "example.com.");
+        assert_eq!(resp.answer[0].ttl, 1);
+        assert_eq!(resp.answer[0].ip(), Some("198.18.0.42".parse().unwrap()));
+        assert!(resp.authority.is_empty());
+    }
+
+    #[test]
+    fn build_marks_fake_ip() {
+        let resp: DnsResponse = serde_json::from_str(BODY).unwrap();
+        let lines = resp.build(&FakeIpRange::default());
+        assert_eq!(
+            lines,
+            [
+                "status: NOERROR",
+                "question: example.com. A",
+                "answer: example.com. A ttl=1 198.18.0.42  (fake-ip)",
+            ]
+        );
+    }
+
+    #[test]
+    fn build_nxdomain_without_answer() {
+        let resp: DnsResponse = serde_json::from_str(
+            r#"{"Status":3,"Question":[{"Name":"nope.invalid.","Qtype":28,"Qclass":1}],
+                "Authority":[{"TTL":900,"data":"a.root-servers.net. nstld.verisign-grs.com. 1 1800 900 604800 86400","name":".","type":6}]}"#,
+        )
+        .unwrap();
+        let lines = resp.build(&FakeIpRange::default());
+        assert_eq!(lines[0], "status: NXDOMAIN");
+        assert_eq!(lines[1], "question: nope.invalid. AAAA");
+        assert_eq!(lines[2], "no answer");
+        assert!(lines[3].starts_with("authority: . SOA ttl=900 a.root-servers.net."));
+    }
+
+    #[test]
+    fn fake_ip_range_from_config() {
+        let config: serde_yml::Value = serde_yml::from_str(
+            "dns:\n  fake-ip-range: 28.0.0.1/8\n  fake-ip-range6: fdfe:dcba:9876::1/64",
+        )
+        .unwrap();
+        let range = FakeIpRange::from_config(&config);
+        assert!(range.contains("28.1.2.3".parse().unwrap()));
+        assert!(!range.contains("198.18.0.1".parse().unwrap()));
+        assert!(range.contains("fdfe:dcba:9876::5".parse().unwrap()));
+        assert!(!range.contains("fdfe:dcba:9877::5".parse().unwrap()));
+
+        let range = FakeIpRange::from_config(&serde_yml::Value::Null);
+        assert!(range.contains("198.18.255.1".parse().unwrap()));
+        assert!(!range.contains("198.19.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn unknown_types_are_numbered() {
+        assert_eq!(type_name(28), "AAAA");
+        assert_eq!(type_name(99), "TYPE99");
+        assert_eq!(rcode_name(9), "RCODE9");
+    }
+
+    #[test]
+    fn query_recorded_answer() {
+        client::use_fixtures();
+        let resp = query("example.com", "a").unwrap();
+        assert_eq!(resp.answer[0].data, "198.18.0.42");
+    }
+}
@@ -0,0 +1,420 @@
+//! Minimal HTTP/1.1 exchange over any byte stream
+//!
+//! minreq only speaks plain TCP and trusts a fixed set of roots, so unix
+//! socket and pinned-TLS controllers write the request by hand. Every
+//! request asks for `Connection: close`.
+
+use super::client::Reply;
+use super::*;
+use std::io::{ErrorKind, Read, Write};
+use std::time::Instant;
+
+pub fn exchange<S: Read + Write>(
+    stream: &mut S,
+    host: &str,
+    method: Method,
+    path: &str,
+    payload: Option<String>,
+    secret: Option<&str>,
+) -> Result<Reply> {
+    stream.write_all(&encode_request(host, method, path, payload, secret))?;
+    stream.flush()?;
+
+    let mut response = ResponseReader::default();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match stream.read(&mut buf) {
+            Ok(n) => n,
+            // TLS peers may close without close_notify once the body is sent
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => 0,
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(reply) = response.feed(&buf[..n], n == 0)? {
+            return Ok(reply);
+        }
+    }
+}
+
+/// GET a streaming endpoint such as `/logs` and hand every body line to
+/// `on_line` until the server closes or `deadline` passes
+///
+/// `stream` needs a short read timeout, the deadline is checked between reads.
+pub fn stream_lines<S: Read + Write>(
+    stream: &mut S,
+    host: &str,
+    path: &str,
+    secret: Option<&str>,
+    deadline: Instant,
+    on_line: &mut dyn FnMut(&str),
+) -> Result<()> {
+    stream.write_all(&encode_request(host, Method::Get, path, None, secret))?;
+    stream.flush()?;
+
+    let mut raw = Vec::new();
+    // `Some(chunked)` once the head is in
+    let mut chunked = None;
+    let mut text = Vec::new();
+    let mut buf = [0u8; 8192];
+    while Instant::now() < deadline {
+        let n = match stream.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        raw.extend_from_slice(&buf[..n]);
+        if chunked.is_none() {
+            let Some(split) = raw.windows(4).position(|w| w == b"\r\n\r\n") else {
+                continue;
+            };
+            let head = parse_head(&String::from_utf8_lossy(&raw[..split]))?;
+            if !(200..300).contains(&head.status_code) {
+                return Err(minreq::Error::IoError(std::io::Error::other(format!(
+                    "HTTP {}",
+                    head.status_code
+                ))));
+            }
+            raw.drain(..split + 4);
+            chunked = Some(head.chunked);
+        }
+        if chunked == Some(true) {
+            take_chunks(&mut raw, &mut text);
+        } else {
+            text.append(&mut raw);
+        }
+        while let Some(eol) = text.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = text.drain(..=eol).collect();
+            let line = String::from_utf8_lossy(&line);
+            if !line.trim().is_empty() {
+                on_line(line.trim());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn encode_request(
+    host: &str,
+    method: Method,
+    path: &str,
+    payload: Option<String>,
+    secret: Option<&str>,
+) -> Vec<u8> {
+    let mut head = format!("{method} {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n");
+    if let Some(s) = secret {
+        head.push_str(&format!("{}: Bearer {s}\r\n", headers::AUTHORIZATION));
+    }
+    let body = payload.unwrap_or_default();
+    if !body.is_empty() {
+        head.push_str("Content-Type: application/json\r\n");
+    }
+    head.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+    let mut out = head.into_bytes();
+    out.extend_from_slice(body.as_bytes());
+    out
+}
+
+fn malformed(what: &str) -> minreq::Error {
+    minreq::Error::IoError(std::io::Error::other(format!(
+        "malformed response from controller: {what}"
+    )))
+}
+
+#[derive(Clone, Copy)]
+struct Head {
+    status_code: i32,
+    chunked: bool,
+    length: Option<usize>,
+}
+
+/// Status line and headers, without the blank line
+fn parse_head(head: &str) -> Result<Head> {
+    let mut lines = head.split("\r\n");
+    let status_code = lines
+        .next()
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|c| c.parse::<i32>().ok())
+        .ok_or_else(|| malformed("bad status line"))?;
+
+    let mut chunked = false;
+    let mut length = None;
+    for line in lines {
+        let Some((k, v)) = line.split_once(':') else {
+            continue;
+        };
+        let v = v.trim();
+        if k.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = v.eq_ignore_ascii_case("chunked");
+        } else if k.eq_ignore_ascii_case("content-length") {
+            length = v.parse::<usize>().ok();
+        }
+    }
+    if status_code == 204 || status_code == 304 {
+        length = Some(0);
+    }
+    Ok(Head {
+        status_code,
+        chunked,
+        length,
+    })
+}
+
+/// A response read as it arrives: the head is parsed once and every chunk
+/// size line only once, so a large body costs no more than reading it
+#[derive(Default)]
+struct ResponseReader {
+    raw: Vec<u8>,
+    /// Bytes of `raw` looked at: searched for the end of the head until
+    /// `head` is set, then the head and the complete chunks
+    pos: usize,
+    head: Option<Head>,
+    /// The body of a chunked response so far
+    body: Vec<u8>,
+}
+
+impl ResponseReader {
+    /// Add `bytes`, `None` while more are needed; `eof` means no more will come
+    fn feed(&mut self, bytes: &[u8], eof: bool) -> Result<Option<Reply>> {
+        self.raw.extend_from_slice(bytes);
+        let head = match self.head {
+            Some(head) => head,
+            None => {
+                // The terminator may straddle two reads
+                let from = self.pos.saturating_sub(3);
+                let Some(split) = self.raw[from..].windows(4).position(|w| w == b"\r\n\r\n") else {
+                    self.pos = self.raw.len();
+                    return if eof {
+                        Err(malformed("no header terminator"))
+                    } else {
+                        Ok(None)
+                    };
+                };
+                let split = from + split;
+                let head = parse_head(&String::from_utf8_lossy(&self.raw[..split]))?;
+                self.head = Some(head);
+                self.pos = split + 4;
+                head
+            }
+        };
+
+        let body = if head.chunked {
+            if !self.read_chunks()? {
+                return if eof {
+                    Err(malformed("truncated chunked body"))
+                } else {
+                    Ok(None)
+                };
+            }
+            std::mem::take(&mut self.body)
+        } else {
+            let body = &self.raw[self.pos..];
+            match head.length {
+                Some(n) if body.len() >= n => body[..n].to_vec(),
+                Some(_) if eof => return Err(malformed("truncated body")),
+                None if eof => body.to_vec(),
+                _ => return Ok(None),
+            }
+        };
+        Ok(Some(Reply::new(
+            head.status_code,
+            String::from_utf8_lossy(&body),
+        )))
+    }
+
+    /// Move the complete chunks after `pos` to `body`, `true` once the last
+    /// chunk is in
+    fn read_chunks(&mut self) -> Result<bool> {
+        loop {
+            let rest = &self.raw[self.pos..];
+            let Some(eol) = rest.windows(2).position(|w| w == b"\r\n") else {
+                return Ok(false);
+            };
+            let size = std::str::from_utf8(&rest[..eol])
+                .ok()
+                .and_then(|l| usize::from_str_radix(l.split(';').next()?.trim(), 16).ok())
+                .ok_or_else(|| malformed("bad chunk size"))?;
+            if size == 0 {
+                return Ok(true);
+            }
+            let end = eol + 2 + size;
+            if rest.len() < end + 2 {
+                return Ok(false);
+            }
+            self.body.extend_from_slice(&rest[eol + 2..end]);
+            self.pos += end + 2;
+        }
+    }
+}
+
+/// Move the complete chunks at the front of `raw` to `out`
+fn take_chunks(raw: &mut Vec<u8>, out: &mut Vec<u8>) {
+    while let Some(eol) = raw.windows(2).position(|w| w == b"\r\n") {
+        let Some(size) = std::str::from_utf8(&raw[..eol])
+            .ok()
+            .and_then(|l| usize::from_str_radix(l.split(';').next()?.trim(), 16).ok())
+        else {
+            return;
+        };
+        let end = eol + 2 + size;
+        if raw.len() < end + 2 {
+            return;
+        }
+        out.extend_from_slice(&raw[eol + 2..end]);
+        raw.drain(..end + 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_response(raw: &[u8], eof: bool) -> Result<Option<Reply>> {
+        ResponseReader::default().feed(raw, eof)
+    }
+
+    #[test]
+    fn request_carries_secret_and_body() {
+        let req = encode_request(
+            "localhost",
+            Method::Patch,
+            "/configs",
+            Some(r#"{"mode":"rule"}"#.to_owned()),
+            Some("s3cret"),
+        );
+        let req = String::from_utf8(req).unwrap();
+        assert!(req.starts_with("PATCH /configs HTTP/1.1\r\nHost: localhost\r\n"));
+        assert!(req.contains("authorization: Bearer s3cret\r\n"));
+        assert!(req.contains("Content-Length: 15\r\n"));
+        assert!(req.ends_with("\r\n\r\n{\"mode\":\"rule\"}"));
+    }
+
+    #[test]
+    fn parse_content_length_response() {
+        let raw = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 13\r\n\r\n{\"meta\":true}";
+        let r = parse_response(raw, false).unwrap().unwrap();
+        assert_eq!(r.status_code, 200);
+        assert_eq!(r.as_str(), r#"{"meta":true}"#);
+        // Body not complete yet
+        assert!(
+            parse_response(&raw[..raw.len() - 3], false)
+                .unwrap()
+                .is_none()
+        );
+        assert!(parse_response(&raw[..raw.len() - 3], true).is_err());
+    }
+
+    #[test]
+    fn parse_chunked_response() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n6;x=y\r\n world\r\n0\r\n\r\n";
+        let r = parse_response(raw, false).unwrap().unwrap();
+        assert_eq!(r.as_str(), "hello world");
+        assert!(parse_response(&raw[..60], false).unwrap().is_none());
+        assert!(parse_response(&raw[..60], true).is_err());
+    }
+
+    #[test]
+    fn parse_without_length_reads_to_eof() {
+        let raw = b"HTTP/1.1 200 OK\r\n\r\nabc";
+        assert!(parse_response(raw, false).unwrap().is_none());
+        assert_eq!(parse_response(raw, true).unwrap().unwrap().as_str(), "abc");
+    }
+
+    /// Reads from `reply`, writes go to `sent`
+    struct Canned {
+        reply: std::io::Cursor<Vec<u8>>,
+        sent: Vec<u8>,
+    }
+
+    impl Read for Canned {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            // A few bytes at a time, like a slow stream
+            let n = buf.len().min(7);
+            self.reply.read(&mut buf[..n])
+        }
+    }
+
+    impl Write for Canned {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.sent.write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn exchange_reads_in_pieces() {
+        let body = "x".repeat(20_000);
+        let raw = format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{body}\r\n3\r\nend\r\n0\r\n\r\n",
+            body.len()
+        );
+        let mut stream = Canned {
+            reply: std::io::Cursor::new(raw.into_bytes()),
+            sent: Vec::new(),
+        };
+        let r = exchange(&mut stream, "localhost", Method::Get, "/", None, None).unwrap();
+        assert_eq!(r.as_str(), format!("{body}end"));
+
+        let raw = "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabc";
+        let mut stream = Canned {
+            reply: std::io::Cursor::new(raw.as_bytes().to_vec()),
+            sent: Vec::new(),
+        };
+        let r = exchange(&mut stream, "localhost", Method::Get, "/", None, None).unwrap();
+        assert_eq!(r.as_str(), "abc");
+    }
+
+    #[test]
+    fn stream_lines_across_chunks() {
+        let body = "{\"type\":\"info\"}\n{\"type\":\"warning\"}\n";
+        let (a, b) = body.split_at(10);
+        let raw = format!(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{a}\r\n{:x}\r\n{b}\r\n",
+            a.len(),
+            b.len()
+        );
+        let mut stream = Canned {
+            reply: std::io::Cursor::new(raw.into_bytes()),
+            sent: Vec::new(),
+        };
+        let deadline = Instant::now() + std::time::Duration::from_secs(5);
+        let mut lines = Vec::new();
+        stream_lines(
+            &mut stream,
+            "localhost",
+            "/logs",
+            None,
+            deadline,
+            &mut |l| lines.push(l.to_owned()),
+        )
+        .unwrap();
+        assert_eq!(lines, [r#"{"type":"info"}"#, r#"{"type":"warning"}"#]);
+        assert!(stream.sent.starts_with(b"GET /logs HTTP/1.1\r\n"));
+
+        let mut stream = Canned {
+            reply: std::io::Cursor::new(b"HTTP/1.1 401 Unauthorized\r\n\r\n".to_vec()),
+            sent: Vec::new(),
+        };
+        let e = stream_lines(
+            &mut stream,
+            "localhost",
+            "/logs",
+            None,
+            deadline,
+            &mut |_| {},
+        );
+        assert!(e.unwrap_err().to_string().contains("HTTP 401"));
+    }
+
+    #[test]
+    fn no_content_and_garbage() {
+        let r = parse_response(b"HTTP/1.1 204 No Content\r\n\r\n", false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(r.status_code, 204);
+        assert!(r.as_str().is_empty());
+        assert!(parse_response(b"garbage", true).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use super::*;
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Default)]
+pub struct ProxyProvidersResponse {
+    #[serde(default)]
+    pub providers: IndexMap<String, ProxyProvider>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProxyProvider {
+    pub name: String,
+    /// `HTTP`, `File`, `Inline`, or `Compatible` for the implicit group wrappers
+    pub vehicle_type: String,
+    pub proxies: Vec<super::proxies::Proxy>,
+    pub updated_at: Option<String>,
+    pub subscription_info: Option<SubscriptionInfo>,
+    pub test_url: Option<String>,
+}
+
+impl ProxyProvider {
+    /// `Compatible` providers are generated by the core for groups
+    /// and can be neither updated nor health-checked on their own
+    pub fn is_compatible(&self) -> bool {
+        self.vehicle_type == "Compatible"
+    }
+}
+
+/// mihomo serializes this struct without json tags
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct SubscriptionInfo {
+    pub upload: u64,
+    pub download: u64,
+    pub total: u64,
+    pub expire: u64,
+}
+
+/// Get all proxy providers
+///
+/// API: GET /providers/proxies
+///
+/// Cores without proxy providers answer 404, which is reported as an empty map.
+pub fn fetch_proxy_providers() -> Result<IndexMap<String, ProxyProvider>> {
+    let r = request(Method::Get, "/providers/proxies", None)?;
+    if r.status_code == 404 {
+        return Ok(IndexMap::new());
+    }
+    r.json::<ProxyProvidersResponse>().map(|r| r.providers)
+}
+
+/// Re-download a proxy provider
+///
+/// API: PUT /providers/proxies/{name}
+pub fn update_proxy_provider(name: &str) -> Result<()> {
+    request(
+        Method::Put,
+        &format!("/providers/proxies/{}", encode_path(name)),
+        None,
+    )
+    .and_then(ensure_success)
+    .map(|_| ())
+}
+
+/// Run a health check over every node of a proxy provider
+///
+/// API: GET /providers/proxies/{name}/healthcheck
+pub fn healthcheck_proxy_provider(name: &str) -> Result<()> {
+    request(
+        Method::Get,
+        &format!("/providers/proxies/{}/healthcheck", encode_path(name)),
+        None,
+    )
+    .and_then(ensure_success)
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_proxy_providers() {
+        let body = r#"{"providers":{
+            "default":{"name":"default","type":"Proxy","vehicleType":"Compatible","proxies":[]},
+            "sub":{"name":"sub","type":"Proxy","vehicleType":"HTTP",
+                "updatedAt":"2026-01-02T03:04:05.678+08:00","testUrl":"https://www.gstatic.com/generate_204",
+                "subscriptionInfo":{"Upload":1,"Download":2,"Total":10,"Expire":1800000000},
+                "proxies":[{"name":"n1","type":"Vmess","history":[]},{"name":"n2","type":"Trojan","history":[]}]}
+        }}"#;
+        let resp: ProxyProvidersResponse = serde_json::from_str(body).unwrap();
+        assert!(resp.providers["default"].is_compatible());
+        let sub = &resp.providers["sub"];
+        assert!(!sub.is_compatible());
+        assert_eq!(sub.proxies.len(), 2);
+        let info = sub.subscription_info.as_ref().unwrap();
+        assert_eq!(info.download, 2);
+        assert_eq!(info.total, 10);
+        assert_eq!(info.expire, 1800000000);
+    }
+
+    #[test]
+    fn encode_provider_name() {
+        assert_eq!(encode_path("my sub"), "my%20sub");
+    }
+}
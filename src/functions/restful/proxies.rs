@@ -3,20 +3,9 @@ use indexmap::IndexMap;
 use serde::Deserialize;
 use std::collections::HashMap;
 
-const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
+pub const DEFAULT_TEST_URL: &str = "https://www.gstatic.com/generate_204";
 
-fn encode_path(s: &str) -> String {
-    s.bytes()
-        .map(|b| match b {
-            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
-                (b as char).to_string()
-            }
-            _ => format!("%{:02X}", b),
-        })
-        .collect::<String>()
-}
-
-fn encode_query(s: &str) -> String {
+pub(super) fn encode_query(s: &str) -> String {
     s.bytes()
         .map(|b| match b {
             b'A'..=b'Z'
@@ -112,7 +101,7 @@ impl<'de> Deserialize<'de> for DelayInfo {
 }
 
 pub fn fetch_proxies() -> Result<ProxiesResponse> {
-    request(Method::Get, "/proxies", None).and_then(|r| r.json())
+    client().fetch_proxies()
 }
 
 pub fn get_proxy(name: &str) -> Result<Proxy> {
@@ -125,30 +114,11 @@ pub fn get_proxy(name: &str) -> Result<Proxy> {
 }
 
 pub fn select_proxy(group: &str, node: &str) -> Result<()> {
-    let payload = serde_json::json!({ "name": node }).to_string();
-    request(
-        Method::Put,
-        &format!("/proxies/{}", encode_path(group)),
-        Some(payload),
-    )
-    .map(|_| ())
+    client().select_proxy(group, node)
 }
 
 pub fn test_proxy_delay(name: &str, url: Option<&str>, timeout: u64) -> Result<Option<u64>> {
-    let name_enc = encode_path(name);
-    let test_url = url.unwrap_or(DEFAULT_TEST_URL);
-    let endpoint = format!(
-        "/proxies/{name_enc}/delay?url={}&timeout={timeout}",
-        encode_query(test_url)
-    );
-    request(Method::Get, &endpoint, None).and_then(|r| {
-        let v: serde_json::Value = r.json()?;
-        let delay = v.get("delay").and_then(|d| {
-            d.as_u64()
-                .or_else(|| d.as_str().and_then(|s| s.parse().ok()))
-        });
-        Ok(delay.filter(|&d| d > 0))
-    })
+    client().test_proxy_delay(name, url, timeout)
 }
 
 pub fn test_group_delay(
@@ -156,31 +126,5 @@ pub fn test_group_delay(
     url: Option<&str>,
     timeout: u64,
 ) -> Result<HashMap<String, u64>> {
-    let name_enc = encode_path(name);
-    let test_url = url.unwrap_or(DEFAULT_TEST_URL);
-    let endpoint = format!(
-        "/group/{name_enc}/delay?url={}&timeout={timeout}",
-        encode_query(test_url)
-    );
-    request(Method::Get, &endpoint, None).and_then(|r| {
-        let v: serde_json::Value = r.json()?;
-        let map = v
-            .as_object()
-            .map(|obj| {
-                obj.iter()
-                    .filter_map(|(k, v)| {
-                        let delay = v
-                            .as_u64()
-                            .or_else(|| v.as_str().and_then(|s| s.parse().ok()))?;
-                        if delay > 0 {
-                            Some((k.clone(), delay))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-        Ok(map)
-    })
+    client().test_group_delay(name, url, timeout)
 }
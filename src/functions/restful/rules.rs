@@ -0,0 +1,131 @@
+use super::*;
+use indexmap::IndexMap;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Default)]
+pub struct RulesResponse {
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+pub struct Rule {
+    #[serde(rename = "type")]
+    pub rule_type: String,
+    #[serde(default)]
+    pub payload: String,
+    #[serde(default)]
+    pub proxy: String,
+    /// mihomo only: number of entries for `RuleSet` rules, `-1` otherwise
+    #[serde(default)]
+    pub size: Option<i64>,
+    /// mihomo only: hit/miss statistics
+    #[serde(default)]
+    pub extra: Option<RuleExtra>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RuleExtra {
+    pub disabled: bool,
+    pub hit_count: u64,
+    pub hit_at: Option<String>,
+    pub miss_count: u64,
+    pub miss_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+pub struct RuleProvidersResponse {
+    #[serde(default)]
+    pub providers: IndexMap<String, RuleProvider>,
+}
+
+#[derive(Debug, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RuleProvider {
+    pub name: String,
+    pub behavior: String,
+    pub format: Option<String>,
+    pub rule_count: u64,
+    pub updated_at: Option<String>,
+    pub vehicle_type: String,
+}
+
+/// Get all rules in match order
+///
+/// API: GET /rules
+pub fn fetch_rules() -> Result<Vec<Rule>> {
+    request(Method::Get, "/rules", None)
+        .and_then(|r| r.json::<RulesResponse>())
+        .map(|r| r.rules)
+}
+
+/// Get rule-provider metadata
+///
+/// API: GET /providers/rules
+///
+/// Cores without rule providers (e.g. sing-box) answer 404, which is
+/// reported as an empty map.
+pub fn fetch_rule_providers() -> Result<IndexMap<String, RuleProvider>> {
+    let r = request(Method::Get, "/providers/rules", None)?;
+    if r.status_code == 404 {
+        return Ok(IndexMap::new());
+    }
+    r.json::<RuleProvidersResponse>().map(|r| r.providers)
+}
+
+/// Re-download a rule provider
+///
+/// API: PUT /providers/rules/{name}
+pub fn update_rule_provider(name: &str) -> Result<()> {
+    request(
+        Method::Put,
+        &format!("/providers/rules/{}", encode_path(name)),
+        None,
+    )
+    .and_then(ensure_success)
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_mihomo_rules_with_extra() {
+        let body = r#"{"rules":[
+            {"index":0,"type":"DomainSuffix","payload":"google.com","proxy":"Proxy","size":-1,
+             "extra":{"disabled":false,"hitAt":"2026-01-01T00:00:00Z","hitCount":12,"missAt":"","missCount":3}},
+            {"index":1,"type":"RuleSet","payload":"cn","proxy":"DIRECT","size":4200}
+        ]}"#;
+        let resp: RulesResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(resp.rules.len(), 2);
+        assert_eq!(resp.rules[0].rule_type, "DomainSuffix");
+        let extra = resp.rules[0].extra.as_ref().unwrap();
+        assert_eq!(extra.hit_count, 12);
+        assert_eq!(extra.miss_count, 3);
+        assert_eq!(resp.rules[1].size, Some(4200));
+        assert!(resp.rules[1].extra.is_none());
+    }
+
+    #[test]
+    fn parse_singbox_rules_without_extra() {
+        let body =
+            r#"{"rules":[{"type":"default","payload":"domain_suffix=[.cn]","proxy":"direct"}]}"#;
+        let resp: RulesResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(resp.rules[0].proxy, "direct");
+        assert!(resp.rules[0].size.is_none());
+        assert!(resp.rules[0].extra.is_none());
+    }
+
+    #[test]
+    fn parse_rule_providers() {
+        let body = r#"{"providers":{"cn":{"behavior":"Domain","format":"MrsRule","name":"cn",
+            "ruleCount":4200,"type":"Rule","updatedAt":"2026-01-01T00:00:00Z","vehicleType":"HTTP"}}}"#;
+        let resp: RuleProvidersResponse = serde_json::from_str(body).unwrap();
+        let p = &resp.providers["cn"];
+        assert_eq!(p.rule_count, 4200);
+        assert_eq!(p.vehicle_type, "HTTP");
+        assert_eq!(p.behavior, "Domain");
+    }
+}
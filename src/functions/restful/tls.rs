@@ -0,0 +1,375 @@
+//! TLS to an `https://` external controller, see [ControllerTls]
+//!
+//! Used for both REST requests and WebSocket streams so the secret never
+//! crosses the network in cleartext.
+
+use super::client::Reply;
+use super::*;
+use crate::config::ControllerTls;
+use base64::Engine;
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, ClientConnection, RootCertStore, ServerName, StreamOwned};
+use std::collections::HashMap;
+use std::io;
+use std::net::TcpStream;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, SystemTime};
+
+pub type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// Keyed by controller name, None for the local core
+type ConfigCache = HashMap<Option<String>, Arc<ClientConfig>>;
+
+/// Client config for the active controller, built once per controller
+///
+/// Errors are not cached, so a fixed `ca_file` is picked up on the next request.
+pub fn controller_config() -> io::Result<Arc<ClientConfig>> {
+    static CONFIG_CACHE: LazyLock<Mutex<ConfigCache>> = LazyLock::new(Default::default);
+    let name = CONFIG.active_controller().map(|e| e.name.clone());
+    let mut cache = CONFIG_CACHE.lock().unwrap();
+    if let Some(config) = cache.get(&name) {
+        return Ok(config.clone());
+    }
+    let config = client_config(CONFIG.controller_tls())?;
+    cache.insert(name, config.clone());
+    Ok(config)
+}
+
+pub fn client_config(tls: &ControllerTls) -> io::Result<Arc<ClientConfig>> {
+    let mut roots = RootCertStore::empty();
+    match tls.ca_file.as_deref().filter(|s| !s.is_empty()) {
+        Some(path) => {
+            let pem = std::fs::read_to_string(path).map_err(|e| {
+                io::Error::new(e.kind(), format!("controller_tls.ca_file {path}: {e}"))
+            })?;
+            let certs = parse_pem_certs(&pem)?;
+            if certs.is_empty() {
+                return Err(io::Error::other(format!(
+                    "controller_tls.ca_file {path}: no certificate found"
+                )));
+            }
+            for cert in &certs {
+                roots
+                    .add(cert)
+                    .map_err(|e| io::Error::other(format!("controller_tls.ca_file {path}: {e}")))?;
+            }
+        }
+        None => roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        })),
+    }
+
+    let builder = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots.clone());
+    let mut config = builder.with_no_client_auth();
+    if let Some(pin) = tls.pin_sha256.as_deref().filter(|s| !s.is_empty()) {
+        let chain = tls
+            .ca_file
+            .as_deref()
+            .is_some_and(|s| !s.is_empty())
+            .then(|| WebPkiVerifier::new(roots, None));
+        config
+            .dangerous()
+            .set_certificate_verifier(Arc::new(PinVerifier {
+                pin: parse_pin(pin)?,
+                chain,
+            }));
+    }
+    Ok(Arc::new(config))
+}
+
+/// Connect to `host:port` and finish the handshake within `timeout`.
+///
+/// The same timeout is left on the socket for reads and writes.
+pub fn connect(
+    config: Arc<ClientConfig>,
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> io::Result<TlsStream> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addr = std::net::ToSocketAddrs::to_socket_addrs(&(host, port))?
+        .next()
+        .ok_or_else(|| io::Error::other(format!("cannot resolve {host}")))?;
+    let sock = TcpStream::connect_timeout(&addr, timeout)?;
+    sock.set_read_timeout(Some(timeout))?;
+    sock.set_write_timeout(Some(timeout))?;
+    let name =
+        ServerName::try_from(host).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let conn = ClientConnection::new(config, name).map_err(io::Error::other)?;
+    let mut stream = StreamOwned::new(conn, sock);
+    while stream.conn.is_handshaking() {
+        stream.conn.complete_io(&mut stream.sock)?;
+    }
+    Ok(stream)
+}
+
+/// Split `https://host:port[/...]` into host and port
+pub fn host_port(controller: &str) -> Option<(&str, u16)> {
+    let addr = controller.strip_prefix("https://")?;
+    let addr = addr.split('/').next()?;
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => Some((host, port.parse().ok()?)),
+        _ => Some((addr, 443)),
+    }
+}
+
+/// REST request to an https controller
+pub fn send(
+    controller: &str,
+    method: Method,
+    path: &str,
+    payload: Option<String>,
+    secret: Option<&str>,
+    timeout: u64,
+) -> Result<Reply> {
+    let (host, port) = host_port(controller).ok_or_else(|| {
+        minreq::Error::IoError(io::Error::other(format!("bad controller {controller}")))
+    })?;
+    let mut stream = connect(
+        controller_config()?,
+        host,
+        port,
+        Duration::from_secs(timeout.max(1)),
+    )?;
+    http1::exchange(&mut stream, host, method, path, payload, secret)
+}
+
+fn parse_pem_certs(pem: &str) -> io::Result<Vec<Certificate>> {
+    let mut certs = Vec::new();
+    let mut body: Option<String> = None;
+    for line in pem.lines().map(str::trim) {
+        if line == "-----BEGIN CERTIFICATE-----" {
+            body = Some(String::new());
+        } else if line == "-----END CERTIFICATE-----" {
+            let b64 = body.take().unwrap_or_default();
+            let der = base64::engine::general_purpose::STANDARD
+                .decode(b64)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            certs.push(Certificate(der));
+        } else if let Some(b) = body.as_mut() {
+            b.push_str(line);
+        }
+    }
+    Ok(certs)
+}
+
+fn parse_pin(pin: &str) -> io::Result<[u8; 32]> {
+    let hex: String = pin.chars().filter(|c| *c != ':').collect();
+    let bad = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("controller_tls.pin_sha256 is not a SHA-256 hex digest: {pin}"),
+        )
+    };
+    if hex.len() != 64 {
+        return Err(bad());
+    }
+    let mut out = [0u8; 32];
+    for (i, b) in out.iter_mut().enumerate() {
+        *b = u8::from_str_radix(hex.get(i * 2..i * 2 + 2).ok_or_else(bad)?, 16)
+            .map_err(|_| bad())?;
+    }
+    Ok(out)
+}
+
+/// Accept the server only if its certificate hashes to `pin`,
+/// and, with a `ca_file`, also chains to that CA
+struct PinVerifier {
+    pin: [u8; 32],
+    chain: Option<WebPkiVerifier>,
+}
+
+impl ServerCertVerifier for PinVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if let Some(chain) = &self.chain {
+            chain.verify_server_cert(
+                end_entity,
+                intermediates,
+                server_name,
+                scts,
+                ocsp_response,
+                now,
+            )?;
+        }
+        let digest = ring::digest::digest(&ring::digest::SHA256, &end_entity.0);
+        if digest.as_ref() == self.pin {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(
+                "controller certificate does not match controller_tls.pin_sha256".to_owned(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/functions/restful/tests/fixtures/tls/",
+                $name
+            ))
+        };
+    }
+
+    pub(crate) const CONTROLLER_PIN: &str = "A2:4F:21:47:E0:95:99:BB:95:8C:1A:14:BC:34:2F:43:4F:F3:B6:26:19:46:27:DE:D1:70:9D:73:FE:3F:AE:E0";
+
+    pub(crate) fn ca_file() -> String {
+        concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/src/functions/restful/tests/fixtures/tls/ca.pem"
+        )
+        .to_owned()
+    }
+
+    /// Serve one TLS connection on 127.0.0.1 with the fixture certificate
+    pub(crate) fn serve_once(
+        handle: impl FnOnce(&mut StreamOwned<rustls::ServerConnection, TcpStream>) + Send + 'static,
+    ) -> (u16, std::thread::JoinHandle<()>) {
+        let key = parse_pem_key(fixture!("controller.key"));
+        let certs = parse_pem_certs(fixture!("controller.pem")).unwrap();
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (sock, _) = listener.accept().unwrap();
+            sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let conn = rustls::ServerConnection::new(Arc::new(config)).unwrap();
+            let mut stream = StreamOwned::new(conn, sock);
+            handle(&mut stream);
+        });
+        (port, server)
+    }
+
+    fn parse_pem_key(pem: &str) -> rustls::PrivateKey {
+        let b64: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+        rustls::PrivateKey(
+            base64::engine::general_purpose::STANDARD
+                .decode(b64)
+                .unwrap(),
+        )
+    }
+
+    fn version_with(tls: ControllerTls) -> io::Result<String> {
+        let (port, server) = serve_once(|s| {
+            let mut buf = [0u8; 1024];
+            // A rejected handshake fails the read on this side as well
+            let Ok(n) = s.read(&mut buf) else {
+                return;
+            };
+            assert!(buf[..n].starts_with(b"GET /version HTTP/1.1\r\nHost: 127.0.0.1\r\n"));
+            s.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\n{\"meta\":true}")
+                .unwrap();
+            s.flush().unwrap();
+        });
+        let result = connect(
+            client_config(&tls)?,
+            "127.0.0.1",
+            port,
+            Duration::from_secs(5),
+        )
+        .and_then(|mut stream| {
+            http1::exchange(
+                &mut stream,
+                "127.0.0.1",
+                Method::Get,
+                "/version",
+                None,
+                None,
+            )
+            .map_err(io::Error::other)
+        })
+        .map(|r| r.as_str().to_owned());
+        server.join().unwrap();
+        result
+    }
+
+    #[test]
+    fn pin_parsing() {
+        let pin = parse_pin(CONTROLLER_PIN).unwrap();
+        assert_eq!(pin[0], 0xa2);
+        assert_eq!(pin[31], 0xe0);
+        assert_eq!(
+            parse_pin(&CONTROLLER_PIN.replace(':', "").to_lowercase()).unwrap(),
+            pin
+        );
+        assert!(parse_pin("abcd").is_err());
+        assert!(parse_pin(&"zz".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn controller_host_port() {
+        assert_eq!(
+            host_port("https://192.168.1.1:9443"),
+            Some(("192.168.1.1", 9443))
+        );
+        assert_eq!(host_port("https://router.lan/"), Some(("router.lan", 443)));
+        assert_eq!(host_port("https://[::1]:9443"), Some(("[::1]", 9443)));
+        assert_eq!(host_port("http://127.0.0.1:9090"), None);
+    }
+
+    #[test]
+    fn trusts_configured_ca() {
+        let tls = ControllerTls {
+            ca_file: Some(ca_file()),
+            pin_sha256: None,
+        };
+        assert_eq!(version_with(tls).unwrap(), r#"{"meta":true}"#);
+    }
+
+    #[test]
+    fn trusts_pinned_certificate() {
+        let tls = ControllerTls {
+            ca_file: None,
+            pin_sha256: Some(CONTROLLER_PIN.to_owned()),
+        };
+        assert_eq!(version_with(tls).unwrap(), r#"{"meta":true}"#);
+    }
+
+    #[test]
+    fn rejects_wrong_pin_and_unknown_ca() {
+        let tls = ControllerTls {
+            ca_file: None,
+            pin_sha256: Some("00".repeat(32)),
+        };
+        let e = version_with(tls).unwrap_err();
+        assert!(e.to_string().contains("pin_sha256"), "{e}");
+
+        // The fixture CA is not a public root
+        assert!(version_with(ControllerTls::default()).is_err());
+    }
+
+    #[test]
+    fn missing_ca_file_is_reported() {
+        let tls = ControllerTls {
+            ca_file: Some("/nonexistent/ca.pem".to_owned()),
+            pin_sha256: None,
+        };
+        let e = client_config(&tls).unwrap_err();
+        assert!(e.to_string().contains("/nonexistent/ca.pem"));
+    }
+}
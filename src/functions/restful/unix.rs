@@ -0,0 +1,61 @@
+//! Requests over a unix domain socket, for mihomo's `external-controller-unix`
+
+use super::client::Reply;
+use super::*;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+pub fn send(
+    sock: &str,
+    method: Method,
+    path: &str,
+    payload: Option<String>,
+    secret: Option<&str>,
+    timeout: u64,
+) -> Result<Reply> {
+    let mut stream = UnixStream::connect(sock)?;
+    let timeout = Some(Duration::from_secs(timeout.max(1)));
+    stream.set_read_timeout(timeout)?;
+    stream.set_write_timeout(timeout)?;
+    http1::exchange(&mut stream, "localhost", method, path, payload, secret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn round_trip_over_socket() {
+        let sock = std::env::temp_dir().join(format!("clashtui-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&sock);
+        let listener = UnixListener::bind(&sock).unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut s, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let n = s.read(&mut buf).unwrap();
+            s.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}")
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+        let r = send(
+            sock.to_str().unwrap(),
+            Method::Get,
+            "/version",
+            None,
+            None,
+            5,
+        )
+        .unwrap();
+        assert_eq!(r.status_code, 200);
+        assert_eq!(r.as_str(), "{}");
+        assert!(
+            server
+                .join()
+                .unwrap()
+                .starts_with("GET /version HTTP/1.1\r\n")
+        );
+        let _ = std::fs::remove_file(&sock);
+    }
+}
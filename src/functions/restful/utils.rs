@@ -10,22 +10,30 @@ pub fn request(
     method: minreq::Method,
     sub_url: &str,
     payload: Option<String>,
-) -> Result<minreq::Response> {
-    if sub_url != "/version" && crate::config::is_core_mismatch() {
-        return Err(minreq::Error::IoError(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "core mismatch",
-        )));
-    }
-    let controller = CONFIG.controller_for_core();
-    let mut req = minreq::Request::new(method, format!("{controller}{sub_url}"));
-    if let Some(kv) = payload {
-        req = req
-            .with_header("Content-Type", "application/json")
-            .with_body(kv);
-    }
-    if let Some(s) = CONFIG.secret_for_core() {
-        req = req.with_header(headers::AUTHORIZATION, format!("Bearer {s}"));
+) -> Result<client::Reply> {
+    client().send(method, sub_url, payload)
+}
+
+/// Turn a non-2xx response into an error carrying the response body
+pub fn ensure_success(r: client::Reply) -> Result<client::Reply> {
+    if (200..300).contains(&r.status_code) {
+        Ok(r)
+    } else {
+        Err(minreq::Error::IoError(std::io::Error::other(format!(
+            "HTTP {}: {}",
+            r.status_code,
+            r.as_str()
+        ))))
     }
-    req.with_timeout(timeout!()).send().map_err(|e| e.into())
+}
+
+pub fn encode_path(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect::<String>()
 }
@@ -0,0 +1,247 @@
+//! Release downloads from GitHub, the fallback when the core cannot upgrade itself
+
+use anyhow::{Result, anyhow, bail};
+use std::path::{Path, PathBuf};
+
+use crate::config::CONFIG;
+
+const GITHUB_API: &str = "https://api.github.com";
+pub const CLASHTUI_REPO: &str = "JohanChane/clashtui";
+pub const MIHOMO_REPO: &str = "MetaCubeX/mihomo";
+
+#[derive(serde::Deserialize)]
+pub struct GhRelease {
+    pub tag_name: String,
+    pub assets: Vec<GhAsset>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct GhAsset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+/// `release_api` of the config file without the trailing slash, GitHub by default
+pub fn release_api() -> String {
+    CONFIG
+        .cfg_file
+        .release_api
+        .as_deref()
+        .map(|s| s.trim_end_matches('/'))
+        .filter(|s| !s.is_empty())
+        .unwrap_or(GITHUB_API)
+        .to_owned()
+}
+
+/// The latest release of `repo`, or the latest pre-release too if `ci`
+pub fn fetch_latest_release(api: &str, repo: &str, ci: bool) -> Result<GhRelease> {
+    let url = if ci {
+        format!("{api}/repos/{repo}/releases?per_page=1")
+    } else {
+        format!("{api}/repos/{repo}/releases/latest")
+    };
+
+    let mut releases: Vec<GhRelease> = if ci {
+        minreq::get(url)
+            .with_header("User-Agent", "clashtui")
+            .with_timeout(10)
+            .send()
+            .map_err(|e| anyhow!("Failed to fetch releases: {e}"))?
+            .json()
+            .map_err(|e| anyhow!("Failed to parse releases: {e}"))?
+    } else {
+        vec![
+            minreq::get(url)
+                .with_header("User-Agent", "clashtui")
+                .with_timeout(10)
+                .send()
+                .map_err(|e| anyhow!("Failed to fetch latest release: {e}"))?
+                .json()
+                .map_err(|e| anyhow!("Failed to parse release: {e}"))?,
+        ]
+    };
+
+    if releases.is_empty() {
+        bail!("No releases found");
+    }
+    Ok(releases.remove(0))
+}
+
+pub fn find_linux_asset(assets: &[GhAsset]) -> Result<&GhAsset> {
+    assets
+        .iter()
+        .find(|a| {
+            let n = a.name.to_lowercase();
+            n.contains("linux") && !n.contains("musl") && !n.contains("aarch")
+        })
+        .or_else(|| assets.first())
+        .ok_or_else(|| anyhow!("No suitable asset found"))
+}
+
+pub fn download_to_path(url: &str, dest: &Path) -> Result<()> {
+    let response = minreq::get(url)
+        .with_header("User-Agent", "clashtui")
+        .with_timeout(300)
+        .send()
+        .map_err(|e| anyhow!("Download failed: {e}"))?;
+    if response.status_code >= 400 {
+        bail!("Download failed: HTTP {}", response.status_code);
+    }
+
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(dest, response.as_bytes())?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mut perms = std::fs::metadata(dest)?.permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(dest, perms)?;
+    }
+    Ok(())
+}
+
+/// Where a downloaded mihomo binary goes, next to the core's config file
+pub fn mihomo_path() -> Result<PathBuf> {
+    let path = &CONFIG.cfg_file.mihomo.core.config_path;
+    Ok(if path.is_empty() {
+        std::env::current_dir()?.join("mihomo")
+    } else {
+        PathBuf::from(path)
+            .parent()
+            .unwrap_or(Path::new("."))
+            .join("mihomo")
+    })
+}
+
+/// The version of a `/version` reply, e.g. `v1.19.0` of `{"meta":true,"version":"v1.19.0"}`
+pub fn core_version(reply: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(reply)
+        .ok()
+        .and_then(|v| v["version"].as_str().map(str::to_owned))
+        .unwrap_or_else(|| reply.trim().trim_matches('"').to_owned())
+}
+
+/// Download the latest mihomo release to `dest` unless `current` is already it
+///
+/// `progress` gets the steps as they happen; the returned message is the outcome.
+pub fn mihomo_from_release(
+    api: &str,
+    ci: bool,
+    current: &str,
+    dest: &Path,
+    mut progress: impl FnMut(&str),
+) -> Result<String> {
+    let release = fetch_latest_release(api, MIHOMO_REPO, ci)?;
+    let latest = release.tag_name.trim_start_matches('v');
+
+    if latest == current.trim_start_matches('v') {
+        return Ok(format!("Already up to date (v{latest})."));
+    }
+
+    progress(&format!(
+        "New version available: v{latest} (current: {current})"
+    ));
+    let asset = find_linux_asset(&release.assets)?;
+    progress(&format!("Downloading {}...", asset.name));
+    download_to_path(&asset.browser_download_url, dest)?;
+    Ok(format!("Updated mihomo to v{latest}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// A release API on localhost answering `requests` requests
+    fn stand_in(requests: usize) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let asset_url = format!("{base}/download/mihomo-linux-amd64");
+        let api = base.clone();
+        let server = std::thread::spawn(move || {
+            let mut seen = Vec::new();
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let path = line
+                    .split_whitespace()
+                    .nth(1)
+                    .unwrap_or_default()
+                    .to_owned();
+                // Drain the headers
+                let mut header = String::new();
+                while reader.read_line(&mut header).unwrap() > 2 {
+                    header.clear();
+                }
+                let body = match path.as_str() {
+                    "/repos/MetaCubeX/mihomo/releases/latest" => format!(
+                        r#"{{"tag_name":"v1.19.2","assets":[
+                            {{"name":"mihomo-darwin-arm64","browser_download_url":"{base}/nope"}},
+                            {{"name":"mihomo-linux-amd64","browser_download_url":"{asset_url}"}}]}}"#
+                    ),
+                    "/download/mihomo-linux-amd64" => "\x7fELF".to_owned(),
+                    _ => String::new(),
+                };
+                let status = if body.is_empty() {
+                    "404 Not Found"
+                } else {
+                    "200 OK"
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+                seen.push(path);
+            }
+            seen
+        });
+        (api, server)
+    }
+
+    #[test]
+    fn core_version_of_reply() {
+        assert_eq!(
+            core_version(r#"{"meta":true,"version":"v1.19.0"}"#),
+            "v1.19.0"
+        );
+        assert_eq!(core_version("\"1.10.0\"\n"), "1.10.0");
+    }
+
+    #[test]
+    fn mihomo_downloads_linux_asset_from_stand_in() {
+        let (api, server) = stand_in(2);
+        let dest = std::env::temp_dir().join(format!("clashtui-update-{}", std::process::id()));
+        let mut steps = Vec::new();
+        let msg = mihomo_from_release(&api, false, "v1.18.0", &dest, |s| steps.push(s.to_owned()))
+            .unwrap();
+        assert_eq!(msg, "Updated mihomo to v1.19.2.");
+        assert_eq!(steps[1], "Downloading mihomo-linux-amd64...");
+        assert_eq!(std::fs::read(&dest).unwrap(), b"\x7fELF");
+        let _ = std::fs::remove_file(&dest);
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "/repos/MetaCubeX/mihomo/releases/latest",
+                "/download/mihomo-linux-amd64"
+            ]
+        );
+    }
+
+    #[test]
+    fn mihomo_up_to_date_skips_download() {
+        let (api, server) = stand_in(1);
+        let dest = std::env::temp_dir().join("clashtui-update-never-written");
+        let msg = mihomo_from_release(&api, false, "1.19.2", &dest, |_| {}).unwrap();
+        assert_eq!(msg, "Already up to date (v1.19.2).");
+        assert!(!dest.exists());
+        server.join().unwrap();
+    }
+}
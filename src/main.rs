@@ -15,6 +15,14 @@ fn main() {
         eprintln!("Failed to load Config\n{e}");
         return;
     }
+    if let Err(e) = config::CONFIG.use_controller(cmd.controller.as_deref()) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+
+    if matches!(cmd.command, Some(cli::ArgCommand::Daemon)) {
+        init_logger("info");
+    }
 
     // Handle CLI subcommands (profile, service, mode, update)
     if cmd.command.is_some() {
@@ -26,15 +34,7 @@ fn main() {
         return;
     }
 
-    let log_path = config::config_dir_path().join("clashtui.log");
-    let log_file = std::fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open(&log_path)
-        .expect("Failed to open log file");
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"))
-        .target(env_logger::Target::Pipe(Box::new(log_file)))
-        .init();
+    init_logger("warn");
 
     tui::init().unwrap();
 
@@ -44,3 +44,16 @@ fn main() {
 
     config::CONFIG.save().unwrap();
 }
+
+/// Log to `clashtui.log`, `RUST_LOG` overrides `default_level`
+fn init_logger(default_level: &str) {
+    let log_path = config::config_dir_path().join("clashtui.log");
+    let log_file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .expect("Failed to open log file");
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(default_level))
+        .target(env_logger::Target::Pipe(Box::new(log_file)))
+        .init();
+}
@@ -1,36 +1,52 @@
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use utils::*;
 
 mod agent;
 mod app;
+mod autoselect;
+mod hub;
 mod key;
+mod notice;
 mod popmsg;
+mod profile_update;
 mod signals;
 mod tab;
 mod term;
 mod theme;
 mod utils;
 mod widget;
+mod ws;
 
 pub use app::App;
 pub use key::Key;
 pub use term::hold;
 pub(crate) use theme::Theme;
 
-pub async fn prompt_sudo_password() -> Option<String> {
-    match popmsg::input::InputMasked::new()
-        .with_title("Sudo Password".to_owned())
-        .with_prompt("Sudo password:".to_owned())
-        .build_and_send()
-        .await
-    {
-        Ok(pw) => Some(pw),
-        Err(_) => None,
+/// Ask for the sudo password, `None` if cancelled
+///
+/// Fails outside [App::serve], e.g. in `clashtui daemon`, where nothing would
+/// ever answer the popup.
+pub async fn prompt_sudo_password() -> anyhow::Result<Option<String>> {
+    if !SERVING.load(Ordering::Relaxed) {
+        anyhow::bail!("sudo needs a password; use NOPASSWD or a user service");
     }
+    Ok(
+        match popmsg::input::InputMasked::new()
+            .with_title("Sudo Password".to_owned())
+            .with_prompt("Sudo password:".to_owned())
+            .build_and_send()
+            .await
+        {
+            Ok(pw) => Some(pw),
+            Err(_) => None,
+        },
+    )
 }
 
 pub static EXT_PROC: AtomicBool = AtomicBool::new(false);
+/// Set while [App::serve] runs, popups sent without it are never shown
+pub static SERVING: AtomicBool = AtomicBool::new(false);
 
 trait TuiWidget {
     fn handle_key_event(&mut self, kv: &Key);
@@ -58,3 +74,18 @@ pub fn suspend_terminal() {
 pub fn resume_terminal() -> anyhow::Result<()> {
     term::resume()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn sudo_prompt_fails_without_the_tui() {
+        // A popup nobody reads would block until the timeout
+        let result =
+            tokio::time::timeout(std::time::Duration::from_secs(1), prompt_sudo_password())
+                .await
+                .expect("the prompt reached the popup channel");
+        assert!(result.unwrap_err().to_string().contains("NOPASSWD"));
+    }
+}
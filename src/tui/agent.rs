@@ -478,6 +478,7 @@ fn test_no_duplicate_keys_in_default_agents() {
     check!("srvctl", crate::tui::tab::srvctl::agent());
     check!("settings", crate::tui::tab::settings::agent());
     check!("logs", crate::tui::tab::logs::agent());
+    check!("rules", crate::tui::tab::rules::agent());
 
     if !violations.is_empty() {
         panic!(
@@ -48,6 +48,7 @@ static GLOBAL_CHORD_SHORTCUTS: LazyLock<Vec<(KeyCombo, &str)>> = LazyLock::new(|
             KeyCombo(vec![ctrl('g'), plain('t')]),
             "Close all connections",
         ),
+        (KeyCombo(vec![ctrl('g'), plain('e')]), "Switch controller"),
     ]
 });
 
@@ -72,6 +73,7 @@ impl App {
                 LogsTab::default().into(),
                 SettingsTab::default().into(),
                 CoreSrvCtlTab::default().into(),
+                RulesTab::default().into(),
             ],
             popup: PopUp::default(),
             chord: ChordHandler::default(),
@@ -139,7 +141,11 @@ impl App {
     fn check_startup_perms(&self) {}
     #[tokio::main]
     pub async fn serve() -> anyhow::Result<()> {
+        crate::tui::SERVING.store(true, Ordering::Relaxed);
         signals::Signals::start()?;
+        hub::start();
+        autoselect::start();
+        profile_update::start();
         let mut app = Self::new();
         let mut events = crossterm::event::EventStream::new();
         let mut invt = tokio::time::interval(TICK_RATE);
@@ -210,6 +216,12 @@ impl App {
                 .global_chord
                 .handle(kv, unsafe { &*shortcuts_ptr }, &mut |seq| {
                     log::debug!("global_chord dispatch: {seq:?}");
+                    let local_op =
+                        matches!(seq.last().and_then(|k| k.plain()), Some('c' | 'm' | 'f'));
+                    if local_op && let Err(e) = crate::config::CONFIG.ensure_local() {
+                        widget::popmsg::Confirm::err(e);
+                        return;
+                    }
                     match seq.last().and_then(|k| k.plain()) {
                         Some('c') => {
                             log::debug!("open_dir: core data dir");
@@ -241,6 +253,7 @@ impl App {
                             let _ =
                                 crate::functions::restful::connection::terminate_all_connections();
                         }
+                        Some('e') => switch_controller(),
                         _ => {}
                     }
                 })
@@ -255,6 +268,10 @@ impl App {
         }
 
         let ti = self.tab_index as usize;
+        if self.tabs[ti].is_local_only() && crate::config::CONFIG.is_remote() {
+            self.handle_global_kv(kv);
+            return;
+        }
         let shortcuts_ptr: *const [(widget::tab::KeyCombo, &str)] =
             { self.tabs[ti].shortcuts() as *const _ };
 
@@ -285,7 +302,11 @@ impl App {
             chunks[0],
         );
 
-        self.tabs[self.tab_index as usize].render(f, chunks[1]);
+        let tab = &mut self.tabs[self.tab_index as usize];
+        match crate::config::CONFIG.ensure_local() {
+            Err(e) if tab.is_local_only() => render_disabled(tab, &e, f, chunks[1]),
+            _ => tab.render(f, chunks[1]),
+        }
 
         if self.chord.is_active() {
             self.render_which(f);
@@ -443,9 +464,9 @@ impl App {
     }
     /// Global layer (4) — last resort: Tab switch, Quit, Help
     fn handle_global_kv(&mut self, kv: &Key) -> bool {
-        const TAB_COUNT: u8 = 7;
+        const TAB_COUNT: u8 = 8;
         match kv.code {
-            KeyCode::Char(c @ '1'..='7') if !kv.ctrl && !kv.alt && !kv.super_ => {
+            KeyCode::Char(c @ '1'..='8') if !kv.ctrl && !kv.alt && !kv.super_ => {
                 let new_index = c as u8 - '1' as u8;
                 if new_index != self.tab_index {
                     self.tabs[self.tab_index as usize].on_leave();
@@ -489,6 +510,41 @@ impl App {
     }
 }
 
+/// Select the next entry of `controllers` and tell what is active now
+fn switch_controller() {
+    use crate::config::CONFIG;
+    let msg = match CONFIG.cycle_controller() {
+        Some(endpoint) => format!("{} ({})", endpoint.name, endpoint.address),
+        None if CONFIG.cfg_file.controllers.is_empty() => {
+            "No `controllers` in config.yaml, using the local core".to_owned()
+        }
+        None => format!("local ({})", CONFIG.controller_for_core()),
+    };
+    log::info!("controller: {msg}");
+    hub::switch_controller();
+    widget::popmsg::Confirm::dismiss_any("Controller".to_owned())
+        .with_prompt(msg)
+        .build_and_send();
+}
+
+/// Placeholder for a local-only tab while a remote controller is active
+fn render_disabled(
+    tab: &Tab,
+    e: &anyhow::Error,
+    f: &mut ratatui::Frame,
+    area: ratatui::layout::Rect,
+) {
+    use ratatui::widgets::{Block, Paragraph};
+    let section = match tab {
+        Tab::FileTab(_) => "file",
+        _ => "srvctl",
+    };
+    let block = Block::bordered()
+        .border_style(Theme::get().section(section).border)
+        .title(tab.title());
+    f.render_widget(Paragraph::new(e.to_string()).block(block), area);
+}
+
 /// each item should represent for one tab
 fn render_tabbar(
     titles: impl IntoIterator<Item = &'static str>,
@@ -502,9 +558,12 @@ fn render_tabbar(
     use ratatui::widgets::{Block, Tabs};
 
     let theme = Theme::get();
-    let block = Block::bordered()
+    let mut block = Block::bordered()
         .title(" Clashtui ")
         .title_bottom(Line::raw(" Tab or num ").right_aligned().reversed());
+    if let Some(msg) = super::notice::current() {
+        block = block.title(Line::raw(format!(" {msg} ")).right_aligned().reversed());
+    }
     let titles = titles
         .into_iter()
         .enumerate()
@@ -575,6 +634,7 @@ mod tests {
                 Tab::from(LogsTab::default()),
                 Tab::from(SettingsTab::default()),
                 Tab::from(CoreSrvCtlTab::default()),
+                Tab::from(RulesTab::default()),
             ],
             popup: PopUp::default(),
             chord: ChordHandler::default(),
@@ -608,8 +668,9 @@ mod tests {
     #[test]
     fn global_chord_shortcuts_have_expected_entries() {
         let shortcuts = &*GLOBAL_CHORD_SHORTCUTS;
-        assert_eq!(shortcuts.len(), 4);
+        assert_eq!(shortcuts.len(), 5);
         assert_eq!(&shortcuts[1].1, &"Open core install dir");
+        assert_eq!(&shortcuts[4].1, &"Switch controller");
     }
 
     #[test]
@@ -659,13 +720,25 @@ mod tests {
     }
 
     #[test]
-    fn tab_switch_out_of_range_ignored() {
+    fn tab_switch_8() {
         let rt = tokio::runtime::Runtime::new().unwrap();
         let _guard = rt.enter();
         let mut app = mk_app();
         QUIT.store(false, Ordering::Relaxed);
         let key = kev(KeyCode::Char('8'));
         let result = app.handle_global_kv(&key);
+        assert!(result);
+        assert_eq!(app.tab_index, 7);
+    }
+
+    #[test]
+    fn tab_switch_out_of_range_ignored() {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let _guard = rt.enter();
+        let mut app = mk_app();
+        QUIT.store(false, Ordering::Relaxed);
+        let key = kev(KeyCode::Char('9'));
+        let result = app.handle_global_kv(&key);
         assert!(!result);
         assert_eq!(app.tab_index, 0);
     }
@@ -0,0 +1,52 @@
+//! Runs the `auto_select` policies of `config.yaml` in the background
+
+use std::time::Duration;
+
+use crate::config::{AutoSelect, CONFIG};
+use crate::functions::autoselect::{self, MIN_INTERVAL};
+use crate::functions::latency;
+
+use super::hub;
+use super::tab::logs;
+
+/// One task per configured group, once
+pub fn start() {
+    for policy in CONFIG.cfg_file.auto_select.iter().cloned() {
+        tokio::spawn(run(policy));
+    }
+}
+
+async fn run(policy: AutoSelect) {
+    let interval = Duration::from_secs(policy.interval.max(MIN_INTERVAL));
+    let mut last_error = None;
+    loop {
+        let p = policy.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let outcome = autoselect::run_once(&p)?;
+            latency::record(outcome.samples);
+            Ok::<_, anyhow::Error>(outcome.switch)
+        })
+        .await
+        .unwrap();
+        match result {
+            Ok(switch) => {
+                last_error = None;
+                if let Some(switch) = switch {
+                    log::info!("{switch}");
+                    logs::push_local("info", switch.to_string());
+                    hub::kick();
+                }
+            }
+            Err(e) => {
+                let msg = format!("auto-select {}: {e}", policy.group);
+                log::warn!("{msg}");
+                // Repeat a failure in the Logs tab only when it changes
+                if last_error.as_ref() != Some(&msg) {
+                    logs::push_local("warning", msg.clone());
+                    last_error = Some(msg);
+                }
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
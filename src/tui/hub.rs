@@ -0,0 +1,514 @@
+//! Controller data hub
+//!
+//! One background task owns polling of `/version`, `/configs`, `/proxies`
+//! and `/connections` and publishes the results through a [watch] channel.
+//! Tabs subscribe instead of calling [restful](crate::functions::restful)
+//! on their own timers, so an unreachable controller costs one timeout per
+//! round (with exponential backoff) and shows up the same way everywhere.
+//!
+//! `/version` doubles as the liveness probe and is always polled; the other
+//! topics are only fetched while some tab holds an [Interest] in them. Only a
+//! failing `/version` marks the controller down, any other topic keeps its
+//! last data and carries the error.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::{Notify, watch};
+
+use crate::config::{CONFIG, CoreType, controller_generation};
+use crate::functions::restful::capability::{self, Capabilities};
+use crate::functions::restful::client::client;
+use crate::functions::restful::config_struct::ClashConfig;
+use crate::functions::restful::connection::{self, ConnInfo};
+use crate::functions::restful::proxies::{self, ProxiesResponse};
+use crate::functions::restful::{config, control, core_detect};
+
+/// Round interval while the controller answers
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+const PROXIES_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Push interval requested from the `/connections` WebSocket
+const STREAM_INTERVAL: Duration = Duration::from_millis(1000);
+/// Without a frame for this long the stream is considered dead and
+/// `/connections` is polled over REST until it comes back
+const STREAM_STALE: Duration = Duration::from_millis(3000);
+
+/// Latest value of one endpoint
+pub struct Topic<T> {
+    /// Bumped on every publish, `0` until the first one
+    pub seq: u64,
+    /// When the data was received
+    pub at: Option<Instant>,
+    pub data: Option<Arc<T>>,
+    /// Why the latest fetch failed, `data` is then from before
+    pub error: Option<String>,
+}
+
+impl<T> Default for Topic<T> {
+    fn default() -> Self {
+        Self {
+            seq: 0,
+            at: None,
+            data: None,
+            error: None,
+        }
+    }
+}
+
+impl<T> Clone for Topic<T> {
+    fn clone(&self) -> Self {
+        Self {
+            seq: self.seq,
+            at: self.at,
+            data: self.data.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+impl<T> Topic<T> {
+    fn publish(&mut self, data: T, at: Instant) {
+        self.seq += 1;
+        self.at = Some(at);
+        self.data = Some(Arc::new(data));
+        self.error = None;
+    }
+
+    /// Keep the data, `seq` is only bumped for new data
+    fn fail(&mut self, error: String) {
+        self.error = Some(error);
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub enum Health {
+    #[default]
+    Unknown,
+    Up,
+    /// The controller answers, but for another core than the configured one
+    Mismatch {
+        detected: CoreType,
+        configured: CoreType,
+    },
+    Down {
+        error: String,
+        retry_in: Duration,
+    },
+}
+
+pub struct VersionInfo {
+    /// Raw `/version` body
+    pub raw: String,
+    pub core: CoreType,
+}
+
+#[derive(Clone, Default)]
+pub struct ControllerState {
+    /// Bumped on every publish of any topic or health change
+    pub seq: u64,
+    pub health: Health,
+    pub version: Topic<VersionInfo>,
+    /// Probed once per controller and core version, see [capability]
+    pub capabilities: Topic<Capabilities>,
+    pub config: Topic<ClashConfig>,
+    pub proxies: Topic<ProxiesResponse>,
+    pub connections: Topic<ConnInfo>,
+}
+
+impl ControllerState {
+    /// Status line for tabs to show instead of their own data, if any
+    pub fn error(&self) -> Option<String> {
+        match &self.health {
+            Health::Up => None,
+            Health::Unknown => Some("Waiting for controller...".to_owned()),
+            Health::Mismatch {
+                detected,
+                configured,
+            } => Some(format!(
+                "API returned {detected} data, but {configured} is configured"
+            )),
+            Health::Down { error, retry_in } => Some(format!(
+                "Controller unreachable: {error} (retry in {}s)",
+                retry_in.as_secs()
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Want {
+    Config,
+    Proxies,
+    Connections,
+}
+
+/// Keeps a topic polled while alive
+pub struct Interest(Want);
+
+impl Drop for Interest {
+    fn drop(&mut self) {
+        HUB.wanted[self.0 as usize].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+struct Hub {
+    tx: watch::Sender<ControllerState>,
+    wanted: [AtomicUsize; 3],
+    kick: Notify,
+    /// Wakes the poller from a backoff, see [switch_controller]
+    switched: Notify,
+    started: AtomicBool,
+    last_frame_at: Mutex<Option<Instant>>,
+}
+
+static HUB: LazyLock<Hub> = LazyLock::new(|| Hub {
+    tx: watch::Sender::new(ControllerState::default()),
+    wanted: Default::default(),
+    kick: Notify::new(),
+    switched: Notify::new(),
+    started: AtomicBool::new(false),
+    last_frame_at: Mutex::new(None),
+});
+
+pub fn subscribe() -> watch::Receiver<ControllerState> {
+    HUB.tx.subscribe()
+}
+
+/// Ask the hub to poll `want` until the returned guard is dropped.
+///
+/// A new interest triggers a round right away so the tab does not wait
+/// for the next tick.
+pub fn interest(want: Want) -> Interest {
+    HUB.wanted[want as usize].fetch_add(1, Ordering::Relaxed);
+    HUB.kick.notify_one();
+    Interest(want)
+}
+
+/// Poll now, e.g. after an action changed the controller state
+pub fn kick() {
+    HUB.kick.notify_one();
+}
+
+/// Forget the data of the previous controller and poll the active one now
+pub fn switch_controller() {
+    *HUB.last_frame_at.lock().unwrap() = None;
+    publish(|s| {
+        *s = ControllerState {
+            seq: s.seq,
+            ..Default::default()
+        }
+    });
+    HUB.switched.notify_one();
+    HUB.kick.notify_one();
+}
+
+/// Wait for a publish newer than `seen` (a [ControllerState::seq]) and
+/// return a copy of the state
+pub async fn changed(seen: u64) -> ControllerState {
+    let mut rx = subscribe();
+    // the sender lives in a static, so this never fails
+    rx.wait_for(|s| s.seq > seen)
+        .await
+        .expect("hub sender dropped")
+        .clone()
+}
+
+/// A tab's position in the publish stream.
+///
+/// `after_sync` runs after every finished task, so without the `waiting`
+/// flag each popup or action would start another wait chain.
+#[derive(Default)]
+pub struct Follower {
+    seen: u64,
+    waiting: Cell<bool>,
+}
+
+impl Follower {
+    /// Next publish, or None when a wait is already in flight
+    pub fn next(&self) -> Option<impl Future<Output = ControllerState> + Send + 'static> {
+        if self.waiting.replace(true) {
+            return None;
+        }
+        Some(changed(self.seen))
+    }
+
+    /// Mark `state` as delivered
+    pub fn deliver(&mut self, state: &ControllerState) {
+        self.seen = state.seq;
+        self.waiting.set(false);
+    }
+}
+
+fn wanted(want: Want) -> bool {
+    HUB.wanted[want as usize].load(Ordering::Relaxed) > 0
+}
+
+fn publish(f: impl FnOnce(&mut ControllerState)) {
+    HUB.tx.send_modify(|s| {
+        f(s);
+        s.seq += 1;
+    });
+}
+
+fn backoff(failures: u32) -> Duration {
+    POLL_INTERVAL
+        .saturating_mul(1 << failures.min(16))
+        .min(MAX_BACKOFF)
+}
+
+/// Start the polling task and the `/connections` stream, once
+pub fn start() {
+    if HUB.started.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    spawn_ws_connections();
+    tokio::spawn(run());
+}
+
+async fn run() {
+    let mut failures = 0u32;
+    let mut proxies_at: Option<Instant> = None;
+    let mut generation = controller_generation();
+    loop {
+        if generation != controller_generation() {
+            generation = controller_generation();
+            failures = 0;
+            proxies_at = None;
+        }
+        let delay = match poll_round(&mut proxies_at).await {
+            Ok(()) => {
+                failures = 0;
+                POLL_INTERVAL
+            }
+            Err(error) => {
+                failures += 1;
+                let retry_in = backoff(failures);
+                log::warn!("Controller poll failed: {error}");
+                publish(|s| s.health = Health::Down { error, retry_in });
+                retry_in
+            }
+        };
+        if failures > 0 {
+            // honor the backoff, new interests wait for the next retry
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = HUB.switched.notified() => {}
+            }
+        } else {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = HUB.kick.notified() => {}
+            }
+        }
+    }
+}
+
+async fn blocking<T: Send + 'static>(
+    f: fn() -> Result<T, minreq::Error>,
+) -> Result<(T, Instant), String> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .unwrap()
+        .map(|v| (v, Instant::now()))
+        .map_err(|e| e.to_string())
+}
+
+async fn poll_round(proxies_at: &mut Option<Instant>) -> Result<(), String> {
+    let (raw, at) = blocking(control::version).await?;
+    let detected = core_detect::parse_core_type(&raw).map_err(|e| e.to_string())?;
+    let configured = CONFIG.core_type();
+    let mismatch = detected != configured;
+    crate::config::set_core_mismatch(mismatch);
+    let probe_due = capability::needs_probe(&raw);
+    publish(|s| {
+        s.version.publish(
+            VersionInfo {
+                raw,
+                core: detected,
+            },
+            at,
+        );
+        s.health = if mismatch {
+            Health::Mismatch {
+                detected,
+                configured,
+            }
+        } else {
+            Health::Up
+        };
+    });
+    if mismatch {
+        return Ok(());
+    }
+    // The controller answers, a failing topic is retried next round
+    if probe_due {
+        match tokio::task::spawn_blocking(|| capability::probe(&*client()))
+            .await
+            .unwrap()
+        {
+            Ok(caps) => {
+                let missing = caps.missing();
+                if !missing.is_empty() {
+                    log::info!("{} lacks: {}", caps.version, missing.join(", "));
+                }
+                capability::set_current(caps.clone());
+                publish(|s| s.capabilities.publish(caps, Instant::now()));
+            }
+            Err(e) => fail("capability probe", e.to_string(), |s| &mut s.capabilities),
+        }
+    }
+
+    if wanted(Want::Config) {
+        match blocking(config::fetch).await {
+            Ok((cfg, at)) => publish(|s| s.config.publish(cfg, at)),
+            Err(e) => fail("/configs", e, |s| &mut s.config),
+        }
+    }
+    if wanted(Want::Proxies) && proxies_at.is_none_or(|t| t.elapsed() >= PROXIES_INTERVAL) {
+        match blocking(proxies::fetch_proxies).await {
+            Ok((response, at)) => {
+                *proxies_at = Some(at);
+                publish(|s| s.proxies.publish(response, at));
+            }
+            Err(e) => fail("/proxies", e, |s| &mut s.proxies),
+        }
+    }
+    if wanted(Want::Connections) && !stream_alive(Instant::now()) {
+        match blocking(connection::get_connections).await {
+            Ok((info, at)) => publish(|s| s.connections.publish(info, at)),
+            Err(e) => fail("/connections", e, |s| &mut s.connections),
+        }
+    }
+    Ok(())
+}
+
+/// Record that fetching one topic failed
+fn fail<T>(what: &str, error: String, topic: fn(&mut ControllerState) -> &mut Topic<T>) {
+    log::warn!("Controller poll of {what} failed: {error}");
+    publish(|s| topic(s).fail(error));
+}
+
+fn stream_alive(now: Instant) -> bool {
+    is_fresh(*HUB.last_frame_at.lock().unwrap(), now)
+}
+
+fn is_fresh(last_frame_at: Option<Instant>, now: Instant) -> bool {
+    last_frame_at.is_some_and(|at| now.saturating_duration_since(at) < STREAM_STALE)
+}
+
+fn spawn_ws_connections() {
+    crate::tui::ws::spawn_ws_stream(
+        "/connections",
+        || format!("interval={}", STREAM_INTERVAL.as_millis()),
+        Arc::new(AtomicBool::new(false)),
+        |text| {
+            let at = Instant::now();
+            *HUB.last_frame_at.lock().unwrap() = Some(at);
+            // Frames are large with many connections, skip parsing while nobody looks
+            if !wanted(Want::Connections) {
+                return;
+            }
+            match serde_json::from_str::<ConnInfo>(text) {
+                Ok(info) => publish(|s| s.connections.publish(info, at)),
+                Err(e) => log::warn!("Bad /connections frame: {e}"),
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        assert_eq!(backoff(1), Duration::from_secs(2));
+        assert_eq!(backoff(2), Duration::from_secs(4));
+        assert_eq!(backoff(4), Duration::from_secs(16));
+        assert_eq!(backoff(5), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn topic_publish_bumps_seq() {
+        let mut t = Topic::default();
+        assert_eq!(t.seq, 0);
+        assert!(t.data.is_none());
+        let at = Instant::now();
+        t.publish(7u32, at);
+        t.publish(8u32, at);
+        assert_eq!(t.seq, 2);
+        assert_eq!(t.at, Some(at));
+        assert_eq!(t.data.as_deref(), Some(&8));
+    }
+
+    #[test]
+    fn topic_failure_keeps_data() {
+        let mut t = Topic::default();
+        let at = Instant::now();
+        t.publish(7u32, at);
+        t.fail("timed out".to_owned());
+        assert_eq!(t.seq, 1);
+        assert_eq!(t.data.as_deref(), Some(&7));
+        assert_eq!(t.error.as_deref(), Some("timed out"));
+        t.publish(8u32, at);
+        assert!(t.error.is_none());
+    }
+
+    #[test]
+    fn stream_goes_stale_without_frames() {
+        let t0 = Instant::now();
+        assert!(!is_fresh(None, t0));
+        assert!(is_fresh(Some(t0), t0 + STREAM_INTERVAL));
+        assert!(!is_fresh(Some(t0), t0 + STREAM_STALE));
+    }
+
+    #[test]
+    fn interest_is_counted_until_dropped() {
+        assert!(!wanted(Want::Proxies));
+        let a = interest(Want::Proxies);
+        let b = interest(Want::Proxies);
+        drop(a);
+        assert!(wanted(Want::Proxies));
+        drop(b);
+        assert!(!wanted(Want::Proxies));
+    }
+
+    #[test]
+    fn follower_allows_one_wait_at_a_time() {
+        let mut f = Follower::default();
+        assert!(f.next().is_some());
+        assert!(f.next().is_none());
+        let state = ControllerState {
+            seq: 3,
+            ..Default::default()
+        };
+        f.deliver(&state);
+        assert_eq!(f.seen, 3);
+        assert!(f.next().is_some());
+    }
+
+    #[test]
+    fn health_error_messages() {
+        let mut s = ControllerState::default();
+        assert!(s.error().is_some());
+        s.health = Health::Up;
+        assert!(s.error().is_none());
+        s.health = Health::Down {
+            error: "refused".to_owned(),
+            retry_in: Duration::from_secs(4),
+        };
+        assert_eq!(
+            s.error().as_deref(),
+            Some("Controller unreachable: refused (retry in 4s)")
+        );
+        s.health = Health::Mismatch {
+            detected: CoreType::Singbox,
+            configured: CoreType::Mihomo,
+        };
+        assert!(s.error().unwrap().ends_with("is configured"));
+    }
+}
@@ -0,0 +1,25 @@
+//! One-line messages shown in the tab bar for a while, without taking the focus
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const SHOWN_FOR: Duration = Duration::from_secs(8);
+
+static NOTICE: Mutex<Option<(String, Instant)>> = Mutex::new(None);
+
+/// Replace the message in the tab bar
+pub fn show(msg: impl Into<String>) {
+    *NOTICE.lock().unwrap() = Some((msg.into(), Instant::now()));
+}
+
+/// The message to show now, if it has not expired
+pub fn current() -> Option<String> {
+    let mut notice = NOTICE.lock().unwrap();
+    if notice
+        .as_ref()
+        .is_some_and(|(_, at)| at.elapsed() >= SHOWN_FOR)
+    {
+        *notice = None;
+    }
+    notice.as_ref().map(|(msg, _)| msg.clone())
+}
@@ -0,0 +1,55 @@
+//! Updates URL profiles in the background once their interval has passed, see
+//! [UpdateSchedule](crate::config::database::UpdateSchedule)
+
+use crate::config::CONFIG;
+use crate::functions::file::profile::{AutoUpdate, AutoUpdater};
+
+use super::notice;
+use super::tab::logs;
+
+pub fn start() {
+    tokio::spawn(run());
+}
+
+async fn run() {
+    let mut updater = AutoUpdater::new(CONFIG.cfg_file.auto_apply_updates);
+    loop {
+        // The first round waits too, so startup is not slowed by downloads
+        tokio::time::sleep(AutoUpdater::CHECK_EVERY).await;
+        // Profiles are local files, nothing to do while a remote controller is active
+        if CONFIG.ensure_local().is_err() {
+            continue;
+        }
+        for (name, result) in updater.update_due().await {
+            match result {
+                AutoUpdate::Updated(_) => {
+                    let msg = format!("Profile {name} updated");
+                    log::info!("{msg}");
+                    logs::push_local("info", msg.clone());
+                    notice::show(msg);
+                }
+                AutoUpdate::Held(diff) => {
+                    log::info!("Scheduled update of profile {name} held:\n{diff}");
+                    logs::push_local(
+                        "info",
+                        format!(
+                            "Scheduled update of profile {name} held, update it in the Profile tab to review: {}",
+                            diff.to_string().replace('\n', "; ")
+                        ),
+                    );
+                    notice::show(format!(
+                        "Profile {name} changed, press u in Profile to review"
+                    ));
+                }
+                AutoUpdate::Failed(e) => {
+                    log::warn!("Scheduled update of profile {name} failed: {e}");
+                    logs::push_local(
+                        "warning",
+                        format!("Scheduled update of profile {name} failed: {e}"),
+                    );
+                    notice::show(format!("Profile {name} update failed, see Logs"));
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,18 @@
+mod analytics;
+mod closed;
+
 use super::dev::*;
-use crate::functions::restful::connection::{self, Conn};
+use crate::functions::export::{self, Format};
+use crate::functions::restful::connection::{self, Conn, ConnInfo};
+use crate::tui::hub;
 use crate::tui::widget::fzffind;
 use ratatui::text::Line;
 use ratatui::widgets::{Cell, Row, Table};
 use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime};
+
+use analytics::{GroupBy, Totals, Tracker};
+use closed::ClosedLog;
 
 newtype_tab!(ConnectionsTab(Tab<Connections>));
 
@@ -65,6 +74,11 @@ mod_agent!(
             Key::SortByUlSpeed,
             "Sort by UL Speed"
         ),
+        (
+            [KeyCode::Char('s'), KeyCode::Char('t')],
+            Key::SortByDuration,
+            "Sort by Duration"
+        ),
         (
             [KeyCode::Char('s'), KeyCode::Char('r')],
             Key::SortReset,
@@ -73,6 +87,18 @@ mod_agent!(
         ([KeyCode::Char('/')], Key::Search, "Search/Filter"),
         ([KeyCode::Char('p')], Key::TogglePause, "Pause/Resume"),
         ([KeyCode::Char('f')], Key::FzfFind, "Find"),
+        (
+            [KeyCode::Char('e'), KeyCode::Char('j')],
+            Key::ExportJsonl,
+            "Export as JSONL"
+        ),
+        (
+            [KeyCode::Char('e'), KeyCode::Char('c')],
+            Key::ExportCsv,
+            "Export as CSV"
+        ),
+        ([KeyCode::Char('v')], Key::CycleView, "Switch view"),
+        ([KeyCode::Char('b')], Key::CycleGroupBy, "Group by"),
     ]
 );
 
@@ -91,10 +117,15 @@ pub enum Key {
     SortByUpload,
     SortByDlSpeed,
     SortByUlSpeed,
+    SortByDuration,
     SortReset,
     Search,
     TogglePause,
     FzfFind,
+    CycleView,
+    CycleGroupBy,
+    ExportJsonl,
+    ExportCsv,
 }
 
 impl TryFrom<&crate::tui::Key> for Key {
@@ -146,6 +177,7 @@ enum SortColumn {
     Upload,
     DlSpeed,
     UlSpeed,
+    Duration,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
@@ -155,6 +187,28 @@ enum SortDirection {
     Ascending,
 }
 
+/// What the table shows
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+enum View {
+    /// Open connections
+    #[default]
+    Live,
+    /// Connections that have closed, newest first
+    Closed,
+    /// Traffic of open and closed connections grouped by [GroupBy]
+    Analytics,
+}
+
+impl View {
+    fn next(self) -> Self {
+        match self {
+            Self::Live => Self::Closed,
+            Self::Closed => Self::Analytics,
+            Self::Analytics => Self::Live,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Default)]
 struct SortState {
     column: Option<SortColumn>,
@@ -170,6 +224,8 @@ struct DisplayRow {
     dl_speed: u64,
     ul_speed: u64,
     id: String,
+    /// How long a closed connection was open, `None` for open ones
+    duration: Option<Duration>,
 }
 
 #[derive(Default)]
@@ -178,92 +234,158 @@ struct Connections {
     display_rows: Vec<DisplayRow>,
     row: Option<usize>,
     error: Option<String>,
+    /// Why the latest refresh failed, the rows are from before
+    stale: Option<String>,
     last_bytes: HashMap<String, (u64, u64)>,
+    /// When the snapshot behind `last_bytes` was taken
+    last_sample_at: Option<Instant>,
+    /// Time between the last two snapshots, the divisor for speeds
+    sample_elapsed: Duration,
     sort_state: SortState,
     filter: Option<String>,
     paused: bool,
-}
-
-fn human_bytes(bytes: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
-    let mut size = bytes as f64;
-    let mut unit_idx = 0;
-    while size >= 1024.0 && unit_idx < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit_idx += 1;
-    }
-    if unit_idx == 0 {
-        format!("{size:.0} {unit}", size = size, unit = UNITS[unit_idx])
-    } else {
-        format!("{size:.1} {unit}", size = size, unit = UNITS[unit_idx])
-    }
+    hub: hub::Follower,
+    conns_seq: u64,
+    /// Held while visible and not paused
+    interest: Option<hub::Interest>,
+    view: View,
+    group_by: GroupBy,
+    tracker: Tracker,
+    /// `tracker` grouped by `group_by`
+    group_rows: Vec<(String, Totals)>,
+    closed: ClosedLog,
+    /// `closed` as table rows, speeds are averages over the duration
+    closed_rows: Vec<DisplayRow>,
+    /// [controller_generation](crate::config::controller_generation) of the
+    /// snapshots so far
+    generation: u64,
 }
 
 fn human_speed(bytes_per_sec: u64) -> String {
     format!("{}/s", human_bytes(bytes_per_sec))
 }
 
+/// Bytes transferred over `elapsed`, scaled to bytes per second
+fn per_second(delta: u64, elapsed: Duration) -> u64 {
+    if elapsed.is_zero() {
+        delta
+    } else {
+        (delta as f64 / elapsed.as_secs_f64()) as u64
+    }
+}
+
+/// Build table rows; speeds are the byte deltas against `last_bytes`
+/// over `elapsed`, and `last_bytes` is replaced by this snapshot so
+/// closed connections do not pile up.
 fn make_display_rows(
     conns: &[Conn],
     last_bytes: &mut HashMap<String, (u64, u64)>,
+    elapsed: Duration,
 ) -> Vec<DisplayRow> {
+    let prev_bytes = std::mem::take(last_bytes);
     conns
         .iter()
         .map(|c| {
-            let host = c.metadata.host.clone();
-            let port = &c.metadata.destination_port;
-            let host_display = if host.is_empty() {
-                if let Some(ref ip) = c.metadata.destination_ip {
-                    if !port.is_empty() && port != "0" {
-                        format!("{ip}:{port}")
-                    } else {
-                        ip.clone()
-                    }
-                } else {
-                    c.metadata.remote_destination.clone()
-                }
-            } else {
-                if !port.is_empty() && port != "0" {
-                    format!("{host}:{port}")
-                } else {
-                    host
-                }
-            };
-
             let rule = c.rule.as_deref().unwrap_or("-");
-            let chains = {
-                let mut rev: Vec<&str> = c.chains.iter().map(|s| s.as_str()).collect();
-                rev.reverse();
-                if rev.is_empty() {
-                    "-".to_owned()
-                } else {
-                    rev.join(" > ")
-                }
-            };
-
-            let prev = last_bytes
+            let prev = prev_bytes
                 .get(&c.id)
                 .copied()
                 .unwrap_or((c.download, c.upload));
-            let dl_speed = c.download.saturating_sub(prev.0);
-            let ul_speed = c.upload.saturating_sub(prev.1);
+            let dl_speed = per_second(c.download.saturating_sub(prev.0), elapsed);
+            let ul_speed = per_second(c.upload.saturating_sub(prev.1), elapsed);
 
             last_bytes.insert(c.id.clone(), (c.download, c.upload));
 
             DisplayRow {
-                host: host_display,
+                host: host_display(c),
                 rule: rule.to_owned(),
-                chains,
+                chains: chains_display(c),
                 download: c.download,
                 upload: c.upload,
                 dl_speed,
                 ul_speed,
                 id: c.id.clone(),
+                duration: None,
+            }
+        })
+        .collect()
+}
+
+/// Rows of closed connections, newest first; the rule includes its payload
+fn make_closed_rows(closed: &ClosedLog) -> Vec<DisplayRow> {
+    closed
+        .iter()
+        .map(|entry| {
+            let c = &entry.conn;
+            let rule = match (c.rule.as_deref(), c.rule_payload.as_deref()) {
+                (Some(rule), Some(payload)) if !payload.is_empty() => format!("{rule}({payload})"),
+                (rule, _) => rule.unwrap_or("-").to_owned(),
+            };
+            let elapsed = entry.duration.unwrap_or_default();
+            DisplayRow {
+                host: host_display(c),
+                rule,
+                chains: chains_display(c),
+                download: c.download,
+                upload: c.upload,
+                dl_speed: per_second(c.download, elapsed),
+                ul_speed: per_second(c.upload, elapsed),
+                id: c.id.clone(),
+                duration: entry.duration,
             }
         })
         .collect()
 }
 
+/// `host:port`, else the destination IP with the port
+fn host_display(c: &Conn) -> String {
+    let host = c.metadata.host.clone();
+    let port = &c.metadata.destination_port;
+    if host.is_empty() {
+        if let Some(ref ip) = c.metadata.destination_ip {
+            if !port.is_empty() && port != "0" {
+                format!("{ip}:{port}")
+            } else {
+                ip.clone()
+            }
+        } else {
+            c.metadata.remote_destination.clone()
+        }
+    } else if !port.is_empty() && port != "0" {
+        format!("{host}:{port}")
+    } else {
+        host
+    }
+}
+
+/// The chain from the group to the node, e.g. `Entry > Sl-pvd0 > vmess-ipdktc33`
+fn chains_display(c: &Conn) -> String {
+    let mut rev: Vec<&str> = c.chains.iter().map(|s| s.as_str()).collect();
+    rev.reverse();
+    if rev.is_empty() {
+        "-".to_owned()
+    } else {
+        rev.join(" > ")
+    }
+}
+
+fn human_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", d.as_secs_f64())
+    } else if secs < 3600 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    }
+}
+
+fn row_matches(r: &DisplayRow, filter: Option<&str>) -> bool {
+    filter.is_none_or(|pat| {
+        r.host.contains(pat) || r.rule.contains(pat) || r.chains.contains(pat) || r.id.contains(pat)
+    })
+}
+
 const HOST_COL: &str = "Host";
 const RULE_COL: &str = "Rule";
 const CHAINS_COL: &str = "Chains";
@@ -271,6 +393,9 @@ const DL_COL: &str = "Download";
 const UL_COL: &str = "Upload";
 const DLSPD_COL: &str = "DL Speed";
 const ULSPD_COL: &str = "UL Speed";
+const DLAVG_COL: &str = "DL Avg";
+const ULAVG_COL: &str = "UL Avg";
+const DURATION_COL: &str = "Duration";
 
 fn sort_header(sort_state: SortState, column: SortColumn, base: &str) -> String {
     if sort_state.column == Some(column) {
@@ -299,60 +424,35 @@ impl BasicTabContent for Connections {
         if self.paused {
             return;
         }
-        if crate::config::is_core_mismatch() {
+        let Some(next) = self.hub.next() else {
             return;
-        }
-        async {
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-            let info = tri!(
-                tokio::task::spawn_blocking(connection::get_connections)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-            wrapper(|content: &mut Self| {
-                let conns = info.connections.unwrap_or_default();
-                content.conns = conns;
-                content.error = None;
-                content.refresh_display_rows();
-            })
+        };
+        async move {
+            let state = next.await;
+            wrapper(move |content: &mut Self| content.apply_hub(state))
         }
         .spawn_at(task_set);
     }
 
     fn on_enter(&mut self, task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
-        self.paused = false;
+        self.set_paused(false);
         if crate::config::is_core_mismatch() {
             self.conns = Vec::new();
             self.display_rows = Vec::new();
             self.error = Some("API data mismatch with configured core".to_owned());
             return;
         }
-        async {
-            let info = tri!(
-                tokio::task::spawn_blocking(connection::get_connections)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-            wrapper(|content: &mut Self| {
-                let conns = info.connections.unwrap_or_default();
-                content.conns = conns;
-                content.error = None;
-                content.refresh_display_rows();
-            })
-        }
-        .spawn_at(task_set);
+        self.after_sync(task_set);
     }
 
     fn on_leave(&mut self, _task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
-        self.paused = true;
+        self.set_paused(true);
     }
 }
 
 impl TabContent for Connections {
     fn init(&mut self, _task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
-        self.paused = true;
+        self.set_paused(true);
         self.error = Some("Loading connections...".to_owned());
     }
 
@@ -368,30 +468,33 @@ impl TabContent for Connections {
                     if r > 0 {
                         self.row = Some(r - 1);
                     }
-                } else if !self.display_rows.is_empty() {
-                    self.row = Some(self.display_rows.len() - 1);
+                } else if self.row_count() > 0 {
+                    self.row = Some(self.row_count() - 1);
                 }
             }
             Key::MoveDown => {
                 if let Some(r) = self.row {
-                    if r + 1 < self.display_rows.len() {
+                    if r + 1 < self.row_count() {
                         self.row = Some(r + 1);
                     }
-                } else if !self.display_rows.is_empty() {
+                } else if self.row_count() > 0 {
                     self.row = Some(0);
                 }
             }
             Key::GoTop => {
-                if !self.display_rows.is_empty() {
+                if self.row_count() > 0 {
                     self.row = Some(0);
                 }
             }
             Key::GoBottom => {
-                if !self.display_rows.is_empty() {
-                    self.row = Some(self.display_rows.len().saturating_sub(1));
+                if self.row_count() > 0 {
+                    self.row = Some(self.row_count().saturating_sub(1));
                 }
             }
             Key::Terminate => {
+                if self.view != View::Live {
+                    return;
+                }
                 let Some(row) = self.row else { return };
                 let Some(display_row) = self.display_rows.get(row) else {
                     return;
@@ -406,9 +509,7 @@ impl TabContent for Connections {
                     .unwrap();
                     let info = tri!(result, or_cancel);
                     wrapper(move |content: &mut Connections| {
-                        content.conns = info.connections.unwrap_or_default();
-                        content.error = None;
-                        content.refresh_display_rows();
+                        content.apply_snapshot(&info, Instant::now());
                         if content.row.unwrap_or(0) >= content.display_rows.len() {
                             content.row = content.display_rows.len().checked_sub(1);
                         }
@@ -417,6 +518,9 @@ impl TabContent for Connections {
                 .spawn_at(task_set);
             }
             Key::TerminateAll => {
+                if self.view != View::Live {
+                    return;
+                }
                 let (use_bulk, ids): (bool, Vec<String>) = if let Some(ref pat) = self.filter {
                     let ids: Vec<String> = self
                         .display_rows
@@ -451,95 +555,648 @@ impl TabContent for Connections {
                         } else {
                             for id in &ids {
                                 let _ = connection::terminate_connection(Some(id.clone()));
+                            }
+                        }
+                        connection::get_connections()
+                    })
+                    .await
+                    .unwrap();
+                    let info = tri!(result, or_cancel);
+                    wrapper(move |content: &mut Connections| {
+                        content.apply_snapshot(&info, Instant::now());
+                        content.row = None;
+                    })
+                }
+                .spawn_at(task_set);
+            }
+            Key::SortByHost => self.toggle_sort(SortColumn::Host),
+            Key::SortByRule => self.toggle_sort(SortColumn::Rule),
+            Key::SortByChains => self.toggle_sort(SortColumn::Chains),
+            Key::SortByDownload => self.toggle_sort(SortColumn::Download),
+            Key::SortByUpload => self.toggle_sort(SortColumn::Upload),
+            Key::SortByDlSpeed => self.toggle_sort(SortColumn::DlSpeed),
+            Key::SortByUlSpeed => self.toggle_sort(SortColumn::UlSpeed),
+            Key::SortByDuration => self.toggle_sort(SortColumn::Duration),
+            Key::SortReset => {
+                self.sort_state = SortState::default();
+                self.apply_sort();
+            }
+            Key::Search => {
+                async move {
+                    let filter = tri!(
+                        Input::new()
+                            .with_title("Filter".to_owned())
+                            .build_and_send()
+                            .await,
+                        or_cancel
+                    );
+                    wrapper(move |content: &mut Connections| {
+                        content.filter = (!filter.is_empty()).then_some(filter);
+                    })
+                }
+                .spawn_at(task_set);
+            }
+            Key::TogglePause => {
+                self.set_paused(!self.paused);
+                // The wait chain stopped while paused, restart it
+                self.after_sync(task_set);
+            }
+            Key::ExportJsonl => self.export(Format::Jsonl, task_set),
+            Key::ExportCsv => self.export(Format::Csv, task_set),
+            Key::CycleView => {
+                self.view = self.view.next();
+                self.row = (self.row_count() > 0).then_some(0);
+            }
+            Key::CycleGroupBy => {
+                self.group_by = self.group_by.next();
+                self.refresh_group_rows();
+            }
+            Key::FzfFind => {
+                self.set_paused(true);
+                let names: Vec<String> = match self.view {
+                    View::Live | View::Closed => self
+                        .rows()
+                        .iter()
+                        .map(|r| format!("{} | {} | {}", r.host, r.rule, r.chains))
+                        .collect(),
+                    View::Analytics => self.group_rows.iter().map(|(k, _)| k.clone()).collect(),
+                };
+                async move {
+                    let selected = tokio::task::spawn_blocking(move || {
+                        fzffind::run_fzf(&names, "Find Connection")
+                    })
+                    .await
+                    .unwrap_or(None);
+                    wrapper(move |content: &mut Connections| {
+                        content.row = selected;
+                    })
+                }
+                .spawn_at(task_set);
+            }
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::functions::restful::connection::{ConnMetaData, Conn};
+    fn render(&self, f: &mut Frame, area: Rect, _state: &mut Self::State) {
+        let theme = Theme::get();
+        let section = theme.section("connections");
+        let block = Block::bordered()
+            .border_style(section.border)
+            .title(Self::TITLE);
 
-    fn mk_key(code: KeyCode) -> crate::tui::Key {
-        crate::tui::Key {
-            code,
-            shift: matches!(code, KeyCode::Char(c) if c.is_ascii_uppercase()),
-            ctrl: false,
-            alt: false,
-            super_: false,
+        let mut title = if let Some(filter) = self.filter.as_ref() {
+            format!(" / {filter} ")
+        } else {
+            String::new()
+        };
+        if self.paused {
+            title.push_str(" [PAUSED]");
         }
-    }
-
-    fn conn(id: &str, host: &str) -> Conn {
-        Conn {
-            id: id.to_owned(),
-            metadata: ConnMetaData {
-                network: "tcp".to_owned(),
-                ctype: "".to_owned(),
-                host: host.to_owned(),
-                process: "".to_owned(),
-                process_path: "".to_owned(),
-                source_ip: "".to_owned(),
-                source_port: "0".to_owned(),
-                remote_destination: "".to_owned(),
-                destination_port: "0".to_owned(),
-                destination_ip: None,
-                sniff_host: None,
-            },
-            upload: 0,
-            download: 0,
-            start: "".to_owned(),
-            chains: vec![],
-            rule: None,
-            rule_payload: None,
+        if let Some(e) = &self.stale {
+            title.push_str(&format!(" [stale: {e}]"));
         }
-    }
-
-    fn mk_conns(conns: &[Conn]) -> Connections {
-        let mut c = Connections {
-            conns: conns.to_vec(),
-            ..Default::default()
+        let block = if title.is_empty() {
+            block
+        } else {
+            block.title_bottom(Line::raw(title).right_aligned().reversed())
         };
-        c.display_rows = make_display_rows(&c.conns, &mut c.last_bytes);
-        c
-    }
 
-    #[test]
-    fn key_agent_contains_single_keys() {
-        let a = agent();
-        assert!(a.contains_key(&mk_key(KeyCode::Char('j'))));
-        assert!(a.contains_key(&mk_key(KeyCode::Char('k'))));
-        assert!(a.contains_key(&mk_key(KeyCode::Char('G'))));
-        assert!(a.contains_key(&mk_key(KeyCode::Up)));
-        assert!(a.contains_key(&mk_key(KeyCode::Down)));
-    }
+        if !self.error.as_deref().unwrap_or("").is_empty() && self.display_rows.is_empty() {
+            let widget =
+                ratatui::widgets::Paragraph::new(self.error.as_deref().unwrap_or("")).block(block);
+            f.render_widget(widget, area);
+            return;
+        }
 
-    #[test]
-    fn key_try_from_returns_correct_actions() {
-        assert!(matches!(Key::try_from(&mk_key(KeyCode::Char('j'))), Ok(Key::MoveDown)));
-        assert!(matches!(Key::try_from(&mk_key(KeyCode::Char('k'))), Ok(Key::MoveUp)));
-        assert!(matches!(Key::try_from(&mk_key(KeyCode::Char('G'))), Ok(Key::GoBottom)));
-        assert!(matches!(Key::try_from(&mk_key(KeyCode::Char('/'))), Ok(Key::Search)));
-        assert!(matches!(Key::try_from(&mk_key(KeyCode::Char('p'))), Ok(Key::TogglePause)));
-        assert!(matches!(Key::try_from(&mk_key(KeyCode::Char('f'))), Ok(Key::FzfFind)));
-    }
+        if self.view == View::Analytics {
+            self.render_analytics(f, area, block);
+            return;
+        }
 
-    #[test]
-    fn chord_keys_not_in_try_from() {
-        assert!(Key::try_from(&mk_key(KeyCode::Char('s'))).is_err());
-        assert!(Key::try_from(&mk_key(KeyCode::Char('d'))).is_err());
-        assert!(Key::try_from(&mk_key(KeyCode::Char('a'))).is_err());
-    }
+        let sort_indicator = if let Some(col) = self.sort_state.column {
+            let dir = if self.sort_state.direction == SortDirection::Descending {
+                "▼"
+            } else {
+                "▲"
+            };
+            let name = match col {
+                SortColumn::Host => "Host",
+                SortColumn::Rule => "Rule",
+                SortColumn::Chains => "Chains",
+                SortColumn::Download => "Dn",
+                SortColumn::Upload => "Up",
+                SortColumn::DlSpeed => "DL",
+                SortColumn::UlSpeed => "UL",
+                SortColumn::Duration => "Dur",
+            };
+            format!(" ({name} {dir})")
+        } else {
+            String::new()
+        };
 
-    #[test]
-    fn human_bytes_formats_correctly() {
-        assert_eq!(human_bytes(0), "0 B");
-        assert_eq!(human_bytes(500), "500 B");
-        assert_eq!(human_bytes(1024), "1.0 KB");
-        assert_eq!(human_bytes(2_048), "2.0 KB");
-        assert_eq!(human_bytes(1_048_576), "1.0 MB");
-        assert_eq!(human_bytes(1_073_741_824), "1.0 GB");
-        assert_eq!(human_bytes(1_099_511_627_776), "1.0 TB");
-    }
+        let closed = self.view == View::Closed;
+        let all_rows = self.rows();
+        let filtered_count: usize = all_rows
+            .iter()
+            .filter(|r| row_matches(r, self.filter.as_deref()))
+            .count();
+
+        let noun = if closed { "closed" } else { "conns" };
+        let count_text = if self.filter.is_some() {
+            format!(
+                "{}/{} {noun}{}",
+                filtered_count,
+                all_rows.len(),
+                sort_indicator
+            )
+        } else {
+            format!("{} {noun}{}", all_rows.len(), sort_indicator)
+        };
+
+        let header_style = section.border;
+        let (dl_col, ul_col) = if closed {
+            (DLAVG_COL, ULAVG_COL)
+        } else {
+            (DLSPD_COL, ULSPD_COL)
+        };
+        let mut header_cells = vec![
+            sort_header(self.sort_state, SortColumn::Host, HOST_COL),
+            sort_header(self.sort_state, SortColumn::Rule, RULE_COL),
+            sort_header(self.sort_state, SortColumn::Chains, CHAINS_COL),
+            sort_header(self.sort_state, SortColumn::Download, DL_COL),
+            sort_header(self.sort_state, SortColumn::Upload, UL_COL),
+            sort_header(self.sort_state, SortColumn::DlSpeed, dl_col),
+            sort_header(self.sort_state, SortColumn::UlSpeed, ul_col),
+        ];
+        if closed {
+            header_cells.push(sort_header(
+                self.sort_state,
+                SortColumn::Duration,
+                DURATION_COL,
+            ));
+        }
+        let header_cells = header_cells
+            .into_iter()
+            .map(|h| Cell::from(h).style(header_style));
+
+        let header = Row::new(header_cells).height(1);
+
+        let mut widths = vec![
+            ratatui::prelude::Constraint::Min(30),
+            ratatui::prelude::Constraint::Max(15),
+            ratatui::prelude::Constraint::Min(15),
+            ratatui::prelude::Constraint::Max(10),
+            ratatui::prelude::Constraint::Max(10),
+            ratatui::prelude::Constraint::Max(10),
+            ratatui::prelude::Constraint::Max(10),
+        ];
+        if closed {
+            widths.push(ratatui::prelude::Constraint::Max(10));
+        }
+
+        let rows: Vec<Row> = all_rows
+            .iter()
+            .filter(|r| row_matches(r, self.filter.as_deref()))
+            .map(|r| {
+                let mut cells = vec![
+                    Cell::from(r.host.as_str()),
+                    Cell::from(r.rule.as_str()),
+                    Cell::from(r.chains.as_str()),
+                    Cell::from(human_bytes(r.download)),
+                    Cell::from(human_bytes(r.upload)),
+                    Cell::from(human_speed(r.dl_speed)),
+                    Cell::from(human_speed(r.ul_speed)),
+                ];
+                if closed {
+                    cells.push(Cell::from(
+                        r.duration.map_or("-".to_owned(), human_duration),
+                    ));
+                }
+                Row::new(cells).height(1)
+            })
+            .collect();
+
+        let highlight_style = section.highlight;
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block.title_bottom(Line::raw(count_text).right_aligned()))
+            .row_highlight_style(highlight_style);
+
+        if let Some(selected) = self.row {
+            f.render_stateful_widget(
+                table,
+                area,
+                &mut ratatui::widgets::TableState::new()
+                    .with_selected(Some(selected))
+                    .with_offset(0),
+            );
+        } else {
+            f.render_stateful_widget(
+                table,
+                area,
+                &mut ratatui::widgets::TableState::default().with_offset(0),
+            );
+        }
+    }
+}
+
+impl Connections {
+    fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.interest = (!paused).then(|| hub::interest(hub::Want::Connections));
+    }
+
+    fn apply_hub(&mut self, state: hub::ControllerState) {
+        self.hub.deliver(&state);
+        if self.paused {
+            return;
+        }
+        if let Some(e) = state.error() {
+            self.conns.clear();
+            self.display_rows.clear();
+            self.row = None;
+            self.error = Some(e);
+            return;
+        }
+        let topic = state.connections;
+        self.stale = topic.error.clone();
+        if topic.seq == self.conns_seq {
+            return;
+        }
+        self.conns_seq = topic.seq;
+        if let (Some(info), Some(at)) = (topic.data, topic.at) {
+            self.apply_snapshot(&info, at);
+        }
+    }
+
+    fn apply_snapshot(&mut self, info: &ConnInfo, at: Instant) {
+        let generation = crate::config::controller_generation();
+        if generation != self.generation {
+            // The connections of the previous controller did not close
+            self.generation = generation;
+            self.tracker = Tracker::default();
+            self.closed = ClosedLog::default();
+            self.closed_rows.clear();
+            self.last_bytes.clear();
+            self.last_sample_at = None;
+        }
+        self.sample_elapsed = self
+            .last_sample_at
+            .map(|prev| at.saturating_duration_since(prev))
+            .unwrap_or_default();
+        self.last_sample_at = Some(at);
+        self.conns = info.connections.clone().unwrap_or_default();
+        self.error = None;
+        let gone = self.tracker.update(&self.conns);
+        if !gone.is_empty() {
+            self.closed.push(gone, SystemTime::now());
+            self.closed_rows = make_closed_rows(&self.closed);
+        }
+        self.group_rows = self.tracker.groups(self.group_by);
+        self.refresh_display_rows();
+    }
+
+    /// The connections of the Live or Closed view as filtered and sorted
+    fn visible_conns(&self) -> Vec<Conn> {
+        let by_id: HashMap<&str, &Conn> = match self.view {
+            View::Live => self.conns.iter().map(|c| (c.id.as_str(), c)).collect(),
+            View::Closed => self
+                .closed
+                .iter()
+                .map(|c| (c.conn.id.as_str(), &c.conn))
+                .collect(),
+            View::Analytics => return Vec::new(),
+        };
+        self.rows()
+            .iter()
+            .filter(|r| row_matches(r, self.filter.as_deref()))
+            .filter_map(|r| by_id.get(r.id.as_str()).map(|c| (*c).clone()))
+            .collect()
+    }
+
+    /// Write [Self::visible_conns] to a file in the export dir
+    fn export(&self, format: Format, task_set: &mut FutureSet<Self>) {
+        let conns = self.visible_conns();
+        if conns.is_empty() {
+            return;
+        }
+        let kind = match self.view {
+            View::Closed => "closed-connections",
+            _ => "connections",
+        };
+        async move {
+            let dir = crate::config::export_dir();
+            let count = conns.len();
+            let path = tri!(
+                tokio::task::spawn_blocking(move || {
+                    export::save(&dir, kind, format, |out| {
+                        export::write_conns(out, format, &conns)
+                    })
+                })
+                .await
+                .unwrap()
+            );
+            let _ = crate::tui::widget::popmsg::Confirm::dismiss_any("Exported".to_owned())
+                .with_prompt(format!("{count} connections -> {}", path.display()))
+                .build_and_send()
+                .await;
+            do_nothing()
+        }
+        .spawn_at(task_set);
+    }
+
+    /// Table rows of the Live or Closed view
+    fn rows(&self) -> &[DisplayRow] {
+        match self.view {
+            View::Closed => &self.closed_rows,
+            _ => &self.display_rows,
+        }
+    }
+
+    /// Rows of the current view
+    fn row_count(&self) -> usize {
+        match self.view {
+            View::Live | View::Closed => self.rows().len(),
+            View::Analytics => self.group_rows.len(),
+        }
+    }
+
+    fn refresh_group_rows(&mut self) {
+        self.group_rows = self.tracker.groups(self.group_by);
+        self.clamp_row();
+    }
+
+    fn refresh_display_rows(&mut self) {
+        self.display_rows =
+            make_display_rows(&self.conns, &mut self.last_bytes, self.sample_elapsed);
+        // Store original order index in a separate field would be ideal,
+        // but we can rebuild from conns on SortReset since conns retains API order
+        self.apply_sort();
+        self.clamp_row();
+    }
+
+    /// Clamp cursor to valid range
+    fn clamp_row(&mut self) {
+        let len = self.row_count();
+        if len == 0 {
+            self.row = None;
+        } else if let Some(r) = self.row {
+            if r >= len {
+                self.row = Some(len.saturating_sub(1));
+            }
+        } else {
+            self.row = Some(0);
+        }
+    }
+
+    fn render_analytics(&self, f: &mut Frame, area: Rect, block: Block) {
+        let theme = Theme::get();
+        let section = theme.section("connections");
+        let matches = |key: &str| self.filter.as_deref().is_none_or(|pat| key.contains(pat));
+        let rows: Vec<Row> = self
+            .group_rows
+            .iter()
+            .filter(|(key, _)| matches(key))
+            .map(|(key, t)| {
+                Row::new(vec![
+                    Cell::from(key.as_str()),
+                    Cell::from(format!("{}/{}", t.live, t.conns)),
+                    Cell::from(human_bytes(t.download)),
+                    Cell::from(human_bytes(t.upload)),
+                    Cell::from(human_bytes(t.bytes())),
+                ])
+            })
+            .collect();
+        let count_text = if self.filter.is_some() {
+            format!(
+                "{}/{} by {}",
+                rows.len(),
+                self.group_rows.len(),
+                self.group_by.label()
+            )
+        } else {
+            format!("{} by {}", rows.len(), self.group_by.label())
+        };
+        let header = Row::new(
+            [self.group_by.label(), "Open/All", DL_COL, UL_COL, "Total"]
+                .map(|h| Cell::from(h).style(section.border)),
+        );
+        let widths = [
+            ratatui::prelude::Constraint::Min(30),
+            ratatui::prelude::Constraint::Max(10),
+            ratatui::prelude::Constraint::Max(10),
+            ratatui::prelude::Constraint::Max(10),
+            ratatui::prelude::Constraint::Max(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block.title_bottom(Line::raw(count_text).right_aligned()))
+            .row_highlight_style(section.highlight);
+        f.render_stateful_widget(
+            table,
+            area,
+            &mut ratatui::widgets::TableState::new().with_selected(self.row),
+        );
+    }
+
+    fn toggle_sort(&mut self, column: SortColumn) {
+        if self.sort_state.column == Some(column) {
+            match self.sort_state.direction {
+                SortDirection::Descending => self.sort_state.direction = SortDirection::Ascending,
+                SortDirection::Ascending => self.sort_state = SortState::default(),
+            }
+        } else {
+            self.sort_state = SortState {
+                column: Some(column),
+                direction: SortDirection::Descending,
+            };
+        }
+        self.apply_sort();
+    }
+
+    fn apply_sort(&mut self) {
+        let Some(column) = self.sort_state.column else {
+            let orig_pos: HashMap<&str, usize> = self
+                .conns
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (c.id.as_str(), i))
+                .collect();
+            self.display_rows
+                .sort_by_key(|r| orig_pos.get(r.id.as_str()).copied().unwrap_or(usize::MAX));
+            self.closed_rows = make_closed_rows(&self.closed);
+            return;
+        };
+        let descending = self.sort_state.direction == SortDirection::Descending;
+        sort_rows(&mut self.display_rows, column, descending);
+        sort_rows(&mut self.closed_rows, column, descending);
+    }
+}
+
+fn sort_rows(rows: &mut [DisplayRow], column: SortColumn, descending: bool) {
+    match column {
+        SortColumn::Host => {
+            if descending {
+                rows.sort_by(|a, b| b.host.cmp(&a.host));
+            } else {
+                rows.sort_by(|a, b| a.host.cmp(&b.host));
+            }
+        }
+        SortColumn::Rule => {
+            if descending {
+                rows.sort_by(|a, b| b.rule.cmp(&a.rule));
+            } else {
+                rows.sort_by(|a, b| a.rule.cmp(&b.rule));
+            }
+        }
+        SortColumn::Chains => {
+            if descending {
+                rows.sort_by(|a, b| b.chains.cmp(&a.chains));
+            } else {
+                rows.sort_by(|a, b| a.chains.cmp(&b.chains));
+            }
+        }
+        SortColumn::Download => {
+            if descending {
+                rows.sort_by(|a, b| b.download.cmp(&a.download));
+            } else {
+                rows.sort_by(|a, b| a.download.cmp(&b.download));
+            }
+        }
+        SortColumn::Upload => {
+            if descending {
+                rows.sort_by(|a, b| b.upload.cmp(&a.upload));
+            } else {
+                rows.sort_by(|a, b| a.upload.cmp(&b.upload));
+            }
+        }
+        SortColumn::DlSpeed => {
+            if descending {
+                rows.sort_by(|a, b| b.dl_speed.cmp(&a.dl_speed));
+            } else {
+                rows.sort_by(|a, b| a.dl_speed.cmp(&b.dl_speed));
+            }
+        }
+        SortColumn::UlSpeed => {
+            if descending {
+                rows.sort_by(|a, b| b.ul_speed.cmp(&a.ul_speed));
+            } else {
+                rows.sort_by(|a, b| a.ul_speed.cmp(&b.ul_speed));
+            }
+        }
+        SortColumn::Duration => {
+            if descending {
+                rows.sort_by_key(|r| std::cmp::Reverse(r.duration));
+            } else {
+                rows.sort_by_key(|r| r.duration);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::restful::connection::{Conn, ConnMetaData};
+
+    fn mk_key(code: KeyCode) -> crate::tui::Key {
+        crate::tui::Key {
+            code,
+            shift: matches!(code, KeyCode::Char(c) if c.is_ascii_uppercase()),
+            ctrl: false,
+            alt: false,
+            super_: false,
+        }
+    }
+
+    fn conn(id: &str, host: &str) -> Conn {
+        Conn {
+            id: id.to_owned(),
+            metadata: ConnMetaData {
+                network: "tcp".to_owned(),
+                ctype: "".to_owned(),
+                host: host.to_owned(),
+                process: "".to_owned(),
+                process_path: "".to_owned(),
+                source_ip: "".to_owned(),
+                source_port: "0".to_owned(),
+                remote_destination: "".to_owned(),
+                destination_port: "0".to_owned(),
+                destination_ip: None,
+                sniff_host: None,
+            },
+            upload: 0,
+            download: 0,
+            start: "".to_owned(),
+            chains: vec![],
+            rule: None,
+            rule_payload: None,
+        }
+    }
+
+    fn mk_conns(conns: &[Conn]) -> Connections {
+        let mut c = Connections {
+            conns: conns.to_vec(),
+            ..Default::default()
+        };
+        c.display_rows = make_display_rows(&c.conns, &mut c.last_bytes, Duration::ZERO);
+        c
+    }
+
+    #[test]
+    fn key_agent_contains_single_keys() {
+        let a = agent();
+        assert!(a.contains_key(&mk_key(KeyCode::Char('j'))));
+        assert!(a.contains_key(&mk_key(KeyCode::Char('k'))));
+        assert!(a.contains_key(&mk_key(KeyCode::Char('G'))));
+        assert!(a.contains_key(&mk_key(KeyCode::Up)));
+        assert!(a.contains_key(&mk_key(KeyCode::Down)));
+    }
+
+    #[test]
+    fn key_try_from_returns_correct_actions() {
+        assert!(matches!(
+            Key::try_from(&mk_key(KeyCode::Char('j'))),
+            Ok(Key::MoveDown)
+        ));
+        assert!(matches!(
+            Key::try_from(&mk_key(KeyCode::Char('k'))),
+            Ok(Key::MoveUp)
+        ));
+        assert!(matches!(
+            Key::try_from(&mk_key(KeyCode::Char('G'))),
+            Ok(Key::GoBottom)
+        ));
+        assert!(matches!(
+            Key::try_from(&mk_key(KeyCode::Char('/'))),
+            Ok(Key::Search)
+        ));
+        assert!(matches!(
+            Key::try_from(&mk_key(KeyCode::Char('p'))),
+            Ok(Key::TogglePause)
+        ));
+        assert!(matches!(
+            Key::try_from(&mk_key(KeyCode::Char('f'))),
+            Ok(Key::FzfFind)
+        ));
+    }
+
+    #[test]
+    fn chord_keys_not_in_try_from() {
+        assert!(Key::try_from(&mk_key(KeyCode::Char('s'))).is_err());
+        assert!(Key::try_from(&mk_key(KeyCode::Char('d'))).is_err());
+        assert!(Key::try_from(&mk_key(KeyCode::Char('a'))).is_err());
+    }
+
+    #[test]
+    fn human_bytes_formats_correctly() {
+        assert_eq!(human_bytes(0), "0 B");
+        assert_eq!(human_bytes(500), "500 B");
+        assert_eq!(human_bytes(1024), "1.0 KB");
+        assert_eq!(human_bytes(2_048), "2.0 KB");
+        assert_eq!(human_bytes(1_048_576), "1.0 MB");
+        assert_eq!(human_bytes(1_073_741_824), "1.0 GB");
+        assert_eq!(human_bytes(1_099_511_627_776), "1.0 TB");
+    }
 
     #[test]
     fn human_speed_appends_per_second() {
@@ -650,8 +1307,28 @@ mod tests {
     fn apply_sort_by_download_descending() {
         let mut c = Connections {
             display_rows: vec![
-                DisplayRow { host: "a".into(), rule: "".into(), chains: "".into(), download: 100, upload: 0, dl_speed: 0, ul_speed: 0, id: "1".into() },
-                DisplayRow { host: "b".into(), rule: "".into(), chains: "".into(), download: 500, upload: 0, dl_speed: 0, ul_speed: 0, id: "2".into() },
+                DisplayRow {
+                    host: "a".into(),
+                    rule: "".into(),
+                    chains: "".into(),
+                    download: 100,
+                    upload: 0,
+                    dl_speed: 0,
+                    ul_speed: 0,
+                    id: "1".into(),
+                    duration: None,
+                },
+                DisplayRow {
+                    host: "b".into(),
+                    rule: "".into(),
+                    chains: "".into(),
+                    download: 500,
+                    upload: 0,
+                    dl_speed: 0,
+                    ul_speed: 0,
+                    id: "2".into(),
+                    duration: None,
+                },
             ],
             ..Default::default()
         };
@@ -773,322 +1450,216 @@ mod tests {
         let shortcuts = agent::all_shortcuts();
         let single_key_count = shortcuts.iter().filter(|(c, _, _)| c.len() == 1).count();
         let chord_count = shortcuts.iter().filter(|(c, _, _)| c.len() > 1).count();
-        assert!(single_key_count >= 6, "should have at least 6 single-key shortcuts");
+        assert!(
+            single_key_count >= 6,
+            "should have at least 6 single-key shortcuts"
+        );
         assert!(chord_count >= 7, "should have at least 7 chord shortcuts");
     }
-}
-                        connection::get_connections()
-                    })
-                    .await
-                    .unwrap();
-                    let info = tri!(result, or_cancel);
-                    wrapper(move |content: &mut Connections| {
-                        content.conns = info.connections.unwrap_or_default();
-                        content.error = None;
-                        content.refresh_display_rows();
-                        content.row = None;
-                    })
-                }
-                .spawn_at(task_set);
-            }
-            Key::SortByHost => self.toggle_sort(SortColumn::Host),
-            Key::SortByRule => self.toggle_sort(SortColumn::Rule),
-            Key::SortByChains => self.toggle_sort(SortColumn::Chains),
-            Key::SortByDownload => self.toggle_sort(SortColumn::Download),
-            Key::SortByUpload => self.toggle_sort(SortColumn::Upload),
-            Key::SortByDlSpeed => self.toggle_sort(SortColumn::DlSpeed),
-            Key::SortByUlSpeed => self.toggle_sort(SortColumn::UlSpeed),
-            Key::SortReset => {
-                self.sort_state = SortState::default();
-                self.apply_sort();
-            }
-            Key::Search => {
-                async move {
-                    let filter = tri!(
-                        Input::new()
-                            .with_title("Filter".to_owned())
-                            .build_and_send()
-                            .await,
-                        or_cancel
-                    );
-                    wrapper(move |content: &mut Connections| {
-                        content.filter = (!filter.is_empty()).then_some(filter);
-                    })
-                }
-                .spawn_at(task_set);
-            }
-            Key::TogglePause => {
-                self.paused = !self.paused;
-            }
-            Key::FzfFind => {
-                self.paused = true;
-                let names: Vec<String> = self
-                    .display_rows
-                    .iter()
-                    .map(|r| format!("{} | {} | {}", r.host, r.rule, r.chains))
-                    .collect();
-                async move {
-                    let selected = tokio::task::spawn_blocking(move || {
-                        fzffind::run_fzf(&names, "Find Connection")
-                    })
-                    .await
-                    .unwrap_or(None);
-                    wrapper(move |content: &mut Connections| {
-                        content.row = selected;
-                    })
-                }
-                .spawn_at(task_set);
-            }
-        }
-    }
 
-    fn render(&self, f: &mut Frame, area: Rect, _state: &mut Self::State) {
-        let theme = Theme::get();
-        let section = theme.section("connections");
-        let block = Block::bordered()
-            .border_style(section.border)
-            .title(Self::TITLE);
+    #[test]
+    fn speeds_are_scaled_by_elapsed_time() {
+        let mut a = conn("1", "a.com");
+        let mut last_bytes = HashMap::new();
+        make_display_rows(std::slice::from_ref(&a), &mut last_bytes, Duration::ZERO);
+        a.download = 4096;
+        a.upload = 1024;
+        let rows = make_display_rows(&[a], &mut last_bytes, Duration::from_millis(2000));
+        assert_eq!(rows[0].dl_speed, 2048);
+        assert_eq!(rows[0].ul_speed, 512);
+    }
 
-        let mut title = if let Some(filter) = self.filter.as_ref() {
-            format!(" / {filter} ")
-        } else {
-            String::new()
-        };
-        if self.paused {
-            title.push_str(" [PAUSED]");
-        }
-        let block = if title.is_empty() {
-            block
-        } else {
-            block.title_bottom(Line::raw(title).right_aligned().reversed())
-        };
+    #[test]
+    fn closed_connections_leave_last_bytes() {
+        let mut last_bytes = HashMap::new();
+        make_display_rows(
+            &[conn("1", "a.com"), conn("2", "b.com")],
+            &mut last_bytes,
+            Duration::ZERO,
+        );
+        make_display_rows(&[conn("2", "b.com")], &mut last_bytes, Duration::ZERO);
+        assert_eq!(last_bytes.len(), 1);
+        assert!(last_bytes.contains_key("2"));
+    }
 
-        if !self.error.as_deref().unwrap_or("").is_empty() && self.display_rows.is_empty() {
-            let widget =
-                ratatui::widgets::Paragraph::new(self.error.as_deref().unwrap_or("")).block(block);
-            f.render_widget(widget, area);
-            return;
-        }
+    #[test]
+    fn apply_snapshot_uses_frame_timestamps() {
+        let t0 = Instant::now();
+        let mut c = Connections::default();
+        c.apply_snapshot(
+            &ConnInfo {
+                connections: Some(vec![conn("1", "a.com")]),
+                ..Default::default()
+            },
+            t0,
+        );
+        let mut grown = conn("1", "a.com");
+        grown.download = 1000;
+        c.apply_snapshot(
+            &ConnInfo {
+                connections: Some(vec![grown]),
+                ..Default::default()
+            },
+            t0 + Duration::from_millis(500),
+        );
+        assert_eq!(c.display_rows[0].dl_speed, 2000);
+        assert_eq!(c.row, Some(0));
+    }
 
-        let sort_indicator = if let Some(col) = self.sort_state.column {
-            let dir = if self.sort_state.direction == SortDirection::Descending {
-                "▼"
-            } else {
-                "▲"
-            };
-            let name = match col {
-                SortColumn::Host => "Host",
-                SortColumn::Rule => "Rule",
-                SortColumn::Chains => "Chains",
-                SortColumn::Download => "Dn",
-                SortColumn::Upload => "Up",
-                SortColumn::DlSpeed => "DL",
-                SortColumn::UlSpeed => "UL",
-            };
-            format!(" ({name} {dir})")
-        } else {
-            String::new()
-        };
+    #[test]
+    fn analytics_keep_closed_connections() {
+        let t0 = Instant::now();
+        let mut c = Connections::default();
+        let mut a = conn("1", "a.com");
+        a.download = 1000;
+        let mut b = conn("2", "b.com");
+        b.download = 10;
+        c.apply_snapshot(
+            &ConnInfo {
+                connections: Some(vec![a, b.clone()]),
+                ..Default::default()
+            },
+            t0,
+        );
+        c.apply_snapshot(
+            &ConnInfo {
+                connections: Some(vec![b]),
+                ..Default::default()
+            },
+            t0 + Duration::from_secs(1),
+        );
+        assert_eq!(c.row_count(), 1);
+        c.view = View::Analytics;
+        c.refresh_group_rows();
+        assert_eq!(c.row_count(), 2);
+        let (host, totals) = &c.group_rows[0];
+        assert_eq!((host.as_str(), totals.download), ("a.com", 1000));
+        assert_eq!((totals.conns, totals.live), (1, 0));
+    }
 
-        let filtered_count: usize = self
-            .display_rows
-            .iter()
-            .filter(|r| {
-                self.filter.as_deref().is_none_or(|pat| {
-                    r.host.contains(pat)
-                        || r.rule.contains(pat)
-                        || r.chains.contains(pat)
-                        || r.id.contains(pat)
-                })
-            })
-            .count();
+    #[test]
+    fn closed_view_keeps_final_bytes() {
+        let t0 = Instant::now();
+        let mut c = Connections::default();
+        let mut a = conn("1", "a.com");
+        a.rule = Some("DomainSuffix".to_owned());
+        a.rule_payload = Some("a.com".to_owned());
+        let mut b = conn("2", "b.com");
+        c.apply_snapshot(
+            &ConnInfo {
+                connections: Some(vec![a.clone(), b.clone()]),
+                ..Default::default()
+            },
+            t0,
+        );
+        a.download = 300;
+        b.download = 100;
+        c.apply_snapshot(
+            &ConnInfo {
+                connections: Some(vec![a, b]),
+                ..Default::default()
+            },
+            t0 + Duration::from_secs(1),
+        );
+        c.apply_snapshot(&ConnInfo::default(), t0 + Duration::from_secs(2));
+
+        c.view = View::Closed;
+        assert_eq!(c.row_count(), 2);
+        c.toggle_sort(SortColumn::Download);
+        let rows = c.rows();
+        assert_eq!((rows[0].id.as_str(), rows[0].download), ("1", 300));
+        assert_eq!(rows[0].rule, "DomainSuffix(a.com)");
+        // no parsable `start`
+        assert_eq!(rows[0].duration, None);
+        assert_eq!(
+            rows.iter()
+                .filter(|r| row_matches(r, Some("b.com")))
+                .count(),
+            1
+        );
+    }
 
-        let count_text = if self.filter.is_some() {
-            format!(
-                "{}/{} conns{}",
-                filtered_count,
-                self.display_rows.len(),
-                sort_indicator
-            )
-        } else {
-            format!("{} conns{}", self.display_rows.len(), sort_indicator)
+    #[test]
+    fn controller_switch_forgets_connections() {
+        let t0 = Instant::now();
+        let mut c = Connections::default();
+        let info = ConnInfo {
+            connections: Some(vec![conn("1", "a.com")]),
+            ..Default::default()
         };
-
-        let header_style = section.border;
-        let header_cells = [
-            sort_header(self.sort_state, SortColumn::Host, HOST_COL),
-            sort_header(self.sort_state, SortColumn::Rule, RULE_COL),
-            sort_header(self.sort_state, SortColumn::Chains, CHAINS_COL),
-            sort_header(self.sort_state, SortColumn::Download, DL_COL),
-            sort_header(self.sort_state, SortColumn::Upload, UL_COL),
-            sort_header(self.sort_state, SortColumn::DlSpeed, DLSPD_COL),
-            sort_header(self.sort_state, SortColumn::UlSpeed, ULSPD_COL),
-        ]
-        .into_iter()
-        .map(|h| Cell::from(h).style(header_style));
-
-        let header = Row::new(header_cells).height(1);
-
-        let widths = [
-            ratatui::prelude::Constraint::Min(30),
-            ratatui::prelude::Constraint::Max(15),
-            ratatui::prelude::Constraint::Min(15),
-            ratatui::prelude::Constraint::Max(10),
-            ratatui::prelude::Constraint::Max(10),
-            ratatui::prelude::Constraint::Max(10),
-            ratatui::prelude::Constraint::Max(10),
-        ];
-
-        let rows: Vec<Row> = self
-            .display_rows
-            .iter()
-            .filter(|r| {
-                self.filter.as_deref().is_none_or(|pat| {
-                    r.host.contains(pat)
-                        || r.rule.contains(pat)
-                        || r.chains.contains(pat)
-                        || r.id.contains(pat)
-                })
-            })
-            .map(|r| {
-                Row::new(vec![
-                    Cell::from(r.host.as_str()),
-                    Cell::from(r.rule.as_str()),
-                    Cell::from(r.chains.as_str()),
-                    Cell::from(human_bytes(r.download)),
-                    Cell::from(human_bytes(r.upload)),
-                    Cell::from(human_speed(r.dl_speed)),
-                    Cell::from(human_speed(r.ul_speed)),
-                ])
-                .height(1)
-            })
-            .collect();
-
-        let highlight_style = section.highlight;
-        let table = Table::new(rows, widths)
-            .header(header)
-            .block(block.title_bottom(Line::raw(count_text).right_aligned()))
-            .row_highlight_style(highlight_style);
-
-        if let Some(selected) = self.row {
-            f.render_stateful_widget(
-                table,
-                area,
-                &mut ratatui::widgets::TableState::new()
-                    .with_selected(Some(selected))
-                    .with_offset(0),
-            );
-        } else {
-            f.render_stateful_widget(
-                table,
-                area,
-                &mut ratatui::widgets::TableState::default().with_offset(0),
-            );
-        }
+        c.apply_snapshot(&info, t0);
+        c.apply_snapshot(&ConnInfo::default(), t0 + Duration::from_secs(1));
+        assert_eq!(c.closed_rows.len(), 1);
+        c.apply_snapshot(&info, t0 + Duration::from_secs(2));
+
+        // As if the snapshots so far came from another controller
+        c.generation = u64::MAX;
+        let info = ConnInfo {
+            connections: Some(vec![conn("2", "b.com")]),
+            ..Default::default()
+        };
+        c.apply_snapshot(&info, t0 + Duration::from_secs(3));
+        assert!(c.closed_rows.is_empty());
+        assert_eq!(c.sample_elapsed, Duration::ZERO);
+        assert_eq!(c.last_bytes.keys().collect::<Vec<_>>(), ["2"]);
+        assert_eq!(c.tracker.groups(GroupBy::Host).len(), 1);
     }
-}
 
-impl Connections {
-    fn refresh_display_rows(&mut self) {
-        self.display_rows = make_display_rows(&self.conns, &mut self.last_bytes);
-        // Store original order index in a separate field would be ideal,
-        // but we can rebuild from conns on SortReset since conns retains API order
-        self.apply_sort();
-        // Clamp cursor to valid range
-        if self.display_rows.is_empty() {
-            self.row = None;
-        } else if let Some(r) = self.row {
-            if r >= self.display_rows.len() {
-                self.row = Some(self.display_rows.len().saturating_sub(1));
-            }
-        } else {
-            self.row = Some(0);
-        }
+    #[test]
+    fn visible_conns_follow_view_and_filter() {
+        let mut c = Connections::default();
+        c.apply_snapshot(
+            &ConnInfo {
+                connections: Some(vec![conn("1", "a.com"), conn("2", "b.com")]),
+                ..Default::default()
+            },
+            Instant::now(),
+        );
+        c.filter = Some("b.com".to_owned());
+        let ids: Vec<String> = c.visible_conns().into_iter().map(|c| c.id).collect();
+        assert_eq!(ids, ["2"]);
+
+        c.apply_snapshot(&ConnInfo::default(), Instant::now());
+        assert!(c.visible_conns().is_empty());
+        c.view = View::Closed;
+        c.filter = None;
+        assert_eq!(c.visible_conns().len(), 2);
+        c.view = View::Analytics;
+        assert!(c.visible_conns().is_empty());
     }
 
-    fn toggle_sort(&mut self, column: SortColumn) {
-        if self.sort_state.column == Some(column) {
-            match self.sort_state.direction {
-                SortDirection::Descending => self.sort_state.direction = SortDirection::Ascending,
-                SortDirection::Ascending => self.sort_state = SortState::default(),
-            }
-        } else {
-            self.sort_state = SortState {
-                column: Some(column),
-                direction: SortDirection::Descending,
-            };
-        }
-        self.apply_sort();
+    #[test]
+    fn parse_ws_frame() {
+        let text = r#"{"downloadTotal":10,"uploadTotal":20,"connections":[
+            {"id":"c1","metadata":{"network":"tcp","type":"HTTP","host":"a.com","sourceIP":"10.0.0.2",
+             "sourcePort":"5000","destinationPort":"443"},"upload":1,"download":2,
+             "start":"2026-01-01T00:00:00Z","chains":["node","Proxy"],"rule":"Match"}],"memory":0}"#;
+        let info: ConnInfo = serde_json::from_str(text).unwrap();
+        assert_eq!(info.download_total, 10);
+        let conns = info.connections.unwrap();
+        assert_eq!(conns[0].id, "c1");
+        assert_eq!(conns[0].chains, vec!["node", "Proxy"]);
     }
 
-    fn apply_sort(&mut self) {
-        let Some(column) = self.sort_state.column else {
-            let orig_ids: Vec<String> = self.conns.iter().map(|c| c.id.clone()).collect();
-            self.display_rows.sort_by_key(|r| {
-                orig_ids
-                    .iter()
-                    .position(|id| *id == r.id)
-                    .unwrap_or(usize::MAX)
-            });
-            return;
+    #[test]
+    fn sort_recorded_connections() {
+        crate::functions::restful::client::use_fixtures();
+        let info = connection::get_connections().unwrap();
+        let mut c = Connections::default();
+        c.apply_snapshot(&info, Instant::now());
+        let ids = |c: &Connections| -> Vec<String> {
+            c.display_rows
+                .iter()
+                .map(|r| r.id[..4].to_owned())
+                .collect()
         };
-        let descending = self.sort_state.direction == SortDirection::Descending;
-        match column {
-            SortColumn::Host => {
-                if descending {
-                    self.display_rows.sort_by(|a, b| b.host.cmp(&a.host));
-                } else {
-                    self.display_rows.sort_by(|a, b| a.host.cmp(&b.host));
-                }
-            }
-            SortColumn::Rule => {
-                if descending {
-                    self.display_rows.sort_by(|a, b| b.rule.cmp(&a.rule));
-                } else {
-                    self.display_rows.sort_by(|a, b| a.rule.cmp(&b.rule));
-                }
-            }
-            SortColumn::Chains => {
-                if descending {
-                    self.display_rows.sort_by(|a, b| b.chains.cmp(&a.chains));
-                } else {
-                    self.display_rows.sort_by(|a, b| a.chains.cmp(&b.chains));
-                }
-            }
-            SortColumn::Download => {
-                if descending {
-                    self.display_rows
-                        .sort_by(|a, b| b.download.cmp(&a.download));
-                } else {
-                    self.display_rows
-                        .sort_by(|a, b| a.download.cmp(&b.download));
-                }
-            }
-            SortColumn::Upload => {
-                if descending {
-                    self.display_rows.sort_by(|a, b| b.upload.cmp(&a.upload));
-                } else {
-                    self.display_rows.sort_by(|a, b| a.upload.cmp(&b.upload));
-                }
-            }
-            SortColumn::DlSpeed => {
-                if descending {
-                    self.display_rows
-                        .sort_by(|a, b| b.dl_speed.cmp(&a.dl_speed));
-                } else {
-                    self.display_rows
-                        .sort_by(|a, b| a.dl_speed.cmp(&b.dl_speed));
-                }
-            }
-            SortColumn::UlSpeed => {
-                if descending {
-                    self.display_rows
-                        .sort_by(|a, b| b.ul_speed.cmp(&a.ul_speed));
-                } else {
-                    self.display_rows
-                        .sort_by(|a, b| a.ul_speed.cmp(&b.ul_speed));
-                }
-            }
-        }
+        assert_eq!(ids(&c), ["5f0c", "0a9b", "c3d4"]);
+
+        c.toggle_sort(SortColumn::Download);
+        assert_eq!(ids(&c), ["c3d4", "5f0c", "0a9b"]);
+        c.toggle_sort(SortColumn::Download);
+        assert_eq!(ids(&c), ["0a9b", "5f0c", "c3d4"]);
+        c.toggle_sort(SortColumn::Download);
+        assert_eq!(ids(&c), ["5f0c", "0a9b", "c3d4"]);
     }
 }
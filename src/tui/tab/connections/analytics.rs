@@ -0,0 +1,180 @@
+//! Traffic totals that outlive the connections they came from
+
+use crate::functions::restful::connection::Conn;
+use std::collections::HashMap;
+
+/// What the analytics view groups connections by
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum GroupBy {
+    #[default]
+    Host,
+    Process,
+    Rule,
+    /// The first element of `chains`, the outbound that carried the traffic
+    Chain,
+}
+
+impl GroupBy {
+    const ALL: [GroupBy; 4] = [Self::Host, Self::Process, Self::Rule, Self::Chain];
+
+    pub fn next(self) -> Self {
+        Self::ALL[(self as usize + 1) % Self::ALL.len()]
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Host => "Host",
+            Self::Process => "Process",
+            Self::Rule => "Rule",
+            Self::Chain => "Chain",
+        }
+    }
+
+    pub fn key_of(self, c: &Conn) -> String {
+        let key = match self {
+            Self::Host => host_of(c),
+            Self::Process => c.metadata.process.clone(),
+            Self::Rule => match (c.rule.as_deref(), c.rule_payload.as_deref()) {
+                (Some(rule), Some(payload)) if !payload.is_empty() => format!("{rule}({payload})"),
+                (Some(rule), _) => rule.to_owned(),
+                (None, _) => String::new(),
+            },
+            Self::Chain => c.chains.first().cloned().unwrap_or_default(),
+        };
+        if key.is_empty() { "-".to_owned() } else { key }
+    }
+}
+
+/// `host`, else the destination IP, without the port
+pub fn host_of(c: &Conn) -> String {
+    if !c.metadata.host.is_empty() {
+        c.metadata.host.clone()
+    } else if let Some(ip) = c
+        .metadata
+        .destination_ip
+        .as_ref()
+        .filter(|ip| !ip.is_empty())
+    {
+        ip.clone()
+    } else {
+        c.metadata.remote_destination.clone()
+    }
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Totals {
+    pub download: u64,
+    pub upload: u64,
+    /// Connections seen, open or closed
+    pub conns: usize,
+    /// Connections still open
+    pub live: usize,
+}
+
+impl Totals {
+    pub fn bytes(&self) -> u64 {
+        self.download + self.upload
+    }
+
+    fn add(&mut self, c: &Conn, live: bool) {
+        self.download += c.download;
+        self.upload += c.upload;
+        self.conns += 1;
+        self.live += live as usize;
+    }
+}
+
+/// Follows connections across snapshots; a connection whose ID is gone from
+/// the next snapshot is closed and its last seen bytes are kept in the totals.
+#[derive(Default)]
+pub struct Tracker {
+    live: HashMap<String, Conn>,
+    /// Totals of closed connections, indexed by `GroupBy as usize`
+    closed: [HashMap<String, Totals>; 4],
+}
+
+impl Tracker {
+    /// Take a new snapshot, returning the connections it closed
+    pub fn update(&mut self, conns: &[Conn]) -> Vec<Conn> {
+        let mut next: HashMap<String, Conn> =
+            conns.iter().map(|c| (c.id.clone(), c.clone())).collect();
+        let closed: Vec<Conn> = self
+            .live
+            .drain()
+            .filter_map(|(id, c)| (!next.contains_key(&id)).then_some(c))
+            .collect();
+        for c in &closed {
+            for by in GroupBy::ALL {
+                self.closed[by as usize]
+                    .entry(by.key_of(c))
+                    .or_default()
+                    .add(c, false);
+            }
+        }
+        std::mem::swap(&mut self.live, &mut next);
+        closed
+    }
+
+    /// Totals by `by`, largest traffic first
+    pub fn groups(&self, by: GroupBy) -> Vec<(String, Totals)> {
+        let mut groups = self.closed[by as usize].clone();
+        for c in self.live.values() {
+            groups.entry(by.key_of(c)).or_default().add(c, true);
+        }
+        let mut groups: Vec<(String, Totals)> = groups.into_iter().collect();
+        groups.sort_by(|(ka, a), (kb, b)| b.bytes().cmp(&a.bytes()).then_with(|| ka.cmp(kb)));
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::restful::connection::ConnInfo;
+
+    fn recorded() -> Vec<Conn> {
+        let info: ConnInfo = serde_json::from_str(include_str!(
+            "../../../functions/restful/tests/fixtures/connections.json"
+        ))
+        .unwrap();
+        info.connections.unwrap()
+    }
+
+    #[test]
+    fn closed_connections_keep_their_bytes() {
+        let conns = recorded();
+        let mut t = Tracker::default();
+        assert!(t.update(&conns).is_empty());
+        let before = t.groups(GroupBy::Chain);
+
+        let closed = t.update(&conns[1..]);
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].id, conns[0].id);
+        let after = t.groups(GroupBy::Chain);
+        let sum = |g: &[(String, Totals)]| g.iter().map(|(_, t)| t.bytes()).sum::<u64>();
+        assert_eq!(sum(&before), sum(&after));
+        assert_eq!(after.iter().map(|(_, t)| t.conns).sum::<usize>(), 3);
+        assert_eq!(after.iter().map(|(_, t)| t.live).sum::<usize>(), 2);
+
+        // Everything closed: totals stay
+        t.update(&[]);
+        assert_eq!(sum(&t.groups(GroupBy::Host)), sum(&before));
+    }
+
+    #[test]
+    fn groups_sorted_by_traffic() {
+        let mut t = Tracker::default();
+        t.update(&recorded());
+        let groups = t.groups(GroupBy::Process);
+        assert!(groups.windows(2).all(|w| w[0].1.bytes() >= w[1].1.bytes()));
+    }
+
+    #[test]
+    fn group_keys() {
+        let c = &recorded()[0];
+        assert_eq!(GroupBy::Chain.key_of(c), c.chains[0]);
+        assert_eq!(GroupBy::Host.key_of(c), host_of(c));
+        assert_eq!(GroupBy::Host.next(), GroupBy::Process);
+        assert_eq!(GroupBy::Chain.next(), GroupBy::Host);
+    }
+}
@@ -0,0 +1,127 @@
+//! Connections that disappeared between snapshots
+
+use crate::functions::restful::connection::Conn;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Closed connections kept, the oldest are dropped first
+pub const CLOSED_BUFFER_SIZE: usize = 1000;
+
+pub struct ClosedConn {
+    /// The last snapshot the connection was in, so its final bytes
+    pub conn: Conn,
+    /// From `start` to the snapshot without the connection, so at most one
+    /// refresh interval too long
+    pub duration: Option<Duration>,
+}
+
+#[derive(Default)]
+pub struct ClosedLog {
+    entries: VecDeque<ClosedConn>,
+}
+
+impl ClosedLog {
+    pub fn push(&mut self, closed: Vec<Conn>, closed_at: SystemTime) {
+        for conn in closed {
+            let duration =
+                parse_rfc3339(&conn.start).and_then(|start| closed_at.duration_since(start).ok());
+            if self.entries.len() == CLOSED_BUFFER_SIZE {
+                self.entries.pop_front();
+            }
+            self.entries.push_back(ClosedConn { conn, duration });
+        }
+    }
+
+    /// Newest first
+    pub fn iter(&self) -> impl Iterator<Item = &ClosedConn> {
+        self.entries.iter().rev()
+    }
+}
+
+/// Time of an RFC 3339 timestamp such as `2026-10-18T09:12:03.123456789+08:00`
+pub fn parse_rfc3339(s: &str) -> Option<SystemTime> {
+    let (date, time) = s.split_once(['T', ' '])?;
+    let mut ymd = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (y, m, d) = (ymd.next()??, ymd.next()??, ymd.next()??);
+
+    let (clock, offset_secs) = if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else {
+        let at = time.rfind(['+', '-'])?;
+        let (clock, offset) = time.split_at(at);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let (oh, om) = offset[1..].split_once(':')?;
+        (
+            clock,
+            sign * (oh.parse::<i64>().ok()? * 3600 + om.parse::<i64>().ok()? * 60),
+        )
+    };
+    let (hms, frac) = clock.split_once('.').unwrap_or((clock, ""));
+    let mut hms = hms.splitn(3, ':').map(|p| p.parse::<i64>().ok());
+    let (h, min, sec) = (hms.next()??, hms.next()??, hms.next()??);
+    let nanos: u32 = if frac.is_empty() {
+        0
+    } else {
+        format!("{:0<9}", &frac[..frac.len().min(9)]).parse().ok()?
+    };
+
+    let secs = days_from_civil(y, m, d) * 86400 + h * 3600 + min * 60 + sec - offset_secs;
+    let secs = u64::try_from(secs).ok()?;
+    Some(UNIX_EPOCH + Duration::new(secs, nanos))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::restful::connection::ConnInfo;
+
+    fn recorded() -> Vec<Conn> {
+        let info: ConnInfo = serde_json::from_str(include_str!(
+            "../../../functions/restful/tests/fixtures/connections.json"
+        ))
+        .unwrap();
+        info.connections.unwrap()
+    }
+
+    #[test]
+    fn parse_rfc3339_offsets() {
+        let utc = parse_rfc3339("2026-10-18T01:12:03Z").unwrap();
+        assert_eq!(
+            utc.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            1_792_285_923
+        );
+        assert_eq!(
+            parse_rfc3339("2026-10-18T09:12:03.5+08:00").unwrap(),
+            utc + Duration::from_millis(500)
+        );
+        assert!(parse_rfc3339("").is_none());
+        assert!(parse_rfc3339("yesterday").is_none());
+    }
+
+    #[test]
+    fn closed_log_is_bounded_and_newest_first() {
+        let conns = recorded();
+        let closed_at = parse_rfc3339("2026-10-18T09:12:41+08:00").unwrap();
+        let mut log = ClosedLog::default();
+        log.push(conns.clone(), closed_at);
+        let newest = log.iter().next().unwrap();
+        assert_eq!(newest.conn.id, conns[2].id);
+        assert_eq!(newest.duration, Some(Duration::from_millis(42_500)));
+
+        for _ in 0..CLOSED_BUFFER_SIZE {
+            log.push(conns[..1].to_vec(), closed_at);
+        }
+        assert_eq!(log.iter().count(), CLOSED_BUFFER_SIZE);
+        assert!(log.iter().all(|c| c.conn.id == conns[0].id));
+    }
+}
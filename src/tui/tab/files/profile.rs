@@ -55,21 +55,6 @@ pub fn fetch_traffic_for_url(url: &str, with_proxy: bool) {
     );
 }
 
-fn human_bytes(bytes: u64) -> String {
-    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
-    let mut size = bytes as f64;
-    let mut unit_idx = 0;
-    while size >= 1024.0 && unit_idx < UNITS.len() - 1 {
-        size /= 1024.0;
-        unit_idx += 1;
-    }
-    if unit_idx == 0 {
-        format!("{size:.0} {unit}", size = size, unit = UNITS[unit_idx])
-    } else {
-        format!("{size:.1} {unit}", size = size, unit = UNITS[unit_idx])
-    }
-}
-
 fn traffic_percentage(used: u64, total: u64) -> f64 {
     if total == 0 {
         0.0
@@ -148,6 +133,16 @@ mod_agent!(
             Key::Action(Action::ToggleUpdateWithProxy),
             "Toggle update with proxy"
         ),
+        (
+            key("I"),
+            Key::Action(Action::SetUpdateInterval),
+            "Set auto-update interval"
+        ),
+        (
+            key("R"),
+            Key::Action(Action::Rollback),
+            "Rollback to an earlier version"
+        ),
         (key("n"), Key::Action(Action::Traffic), "Show traffic"),
     ]
 );
@@ -232,6 +227,8 @@ impl<'de> serde::Deserialize<'de> for Key {
                         "GoEnd" => Ok(Key::Action(Action::GoEnd)),
                         "ToggleNoPp" => Ok(Key::Action(Action::ToggleNoPp)),
                         "ToggleUpdateWithProxy" => Ok(Key::Action(Action::ToggleUpdateWithProxy)),
+                        "SetUpdateInterval" => Ok(Key::Action(Action::SetUpdateInterval)),
+                        "Rollback" => Ok(Key::Action(Action::Rollback)),
                         "Traffic" => Ok(Key::Action(Action::Traffic)),
                         s => Err(de::Error::unknown_variant(
                             s,
@@ -252,6 +249,8 @@ impl<'de> serde::Deserialize<'de> for Key {
                                 "GoEnd",
                                 "ToggleNoPp",
                                 "ToggleUpdateWithProxy",
+                                "SetUpdateInterval",
+                                "Rollback",
                                 "Traffic",
                             ],
                         )),
@@ -284,6 +283,8 @@ pub enum Action {
     GoEnd,
     ToggleNoPp,
     ToggleUpdateWithProxy,
+    SetUpdateInterval,
+    Rollback,
     Traffic,
 }
 
@@ -423,9 +424,7 @@ impl DualTabContent for Profile {
         };
 
         let current = &crate::config::CONFIG
-            .data
-            .lock()
-            .unwrap()
+            .data()
             .get_current()
             .map(|pf| pf.name)
             .unwrap_or_default();
@@ -482,6 +481,8 @@ mod actions {
                 Self::CopyUrl => copy_url(name).await,
                 Self::ToggleNoPp => toggle_no_pp(name).await,
                 Self::ToggleUpdateWithProxy => toggle_update_with_proxy(name).await,
+                Self::SetUpdateInterval => set_update_interval(name).await,
+                Self::Rollback => rollback(name).await,
                 Self::Traffic => {
                     unreachable!("traffic handled in handle_key_event directly")
                 }
@@ -557,11 +558,11 @@ mod actions {
                 tri!(std::fs::create_dir_all(
                     &*crate::functions::file::PROFILE_JSONS_PATH
                 ));
-                let file = tri!(std::fs::File::create(&path));
-                tri!(serde_json::to_writer(file, &content));
+                let content = tri!(serde_json::to_vec(&content));
+                tri!(crate::config::write_atomic(&path, content));
             }
             {
-                let mut pm = crate::config::CONFIG.data.lock().unwrap();
+                let mut pm = crate::config::CONFIG.data_mut();
                 let dtype = if is_url {
                     crate::config::database::ProfileType::Url(source.clone())
                 } else {
@@ -579,8 +580,8 @@ mod actions {
                 if let Some(parent) = path.parent() {
                     tri!(std::fs::create_dir_all(parent));
                 }
-                let file = tri!(std::fs::File::create(&path));
-                tri!(serde_yml::to_writer(file, &content));
+                let content = tri!(serde_yml::to_string(&content));
+                tri!(crate::config::write_atomic(&path, content));
             }
             tri!(db::create(name, source));
         } else {
@@ -603,11 +604,7 @@ mod actions {
         {
             let pf = tri!(db::get(&name).ok_or_else(|| anyhow::anyhow!("Profile not found")));
             if pf.dtype == crate::config::database::ProfileType::Singbox
-                || crate::config::CONFIG
-                    .data
-                    .lock()
-                    .unwrap()
-                    .contains_in_singbox(&pf.name)
+                || crate::config::CONFIG.data().contains_in_singbox(&pf.name)
             {
                 Confirm::err(anyhow::anyhow!(
                     "no_pp is not applicable for sing-box profiles (proxy-provider not supported)"
@@ -632,6 +629,85 @@ mod actions {
         })
     }
 
+    async fn set_update_interval(name: String) -> CB {
+        let pf = tri!(db::get(&name).ok_or_else(|| anyhow::anyhow!("Profile not found")));
+        if !matches!(pf.dtype, crate::config::database::ProfileType::Url(_)) {
+            Confirm::err("Only URL profiles are updated in the background");
+            return do_nothing();
+        }
+        let current = match pf.schedule.interval {
+            Some(hours) => hours.to_string(),
+            None => "auto".to_owned(),
+        };
+        let input = tri!(
+            Input::new()
+                .with_title(format!("Update interval in hours (now {current})"))
+                .with_prompt("Hours, 0 to disable, auto to follow the provider".to_owned())
+                .build_and_send()
+                .await,
+            or_cancel
+        );
+        let hours = tri!(crate::functions::file::profile::parse_update_interval(
+            &input
+        ));
+        tri!(db::set_update_interval(&name, hours));
+
+        let (names, atime) = get_profiles_with_readable_atime();
+        wrapper(move |(content, _): &mut C| {
+            sync_helper(content, names, atime);
+        })
+    }
+
+    async fn rollback(name: String) -> CB {
+        use crate::functions::file::history;
+        let pf = tri!(db::get(&name).ok_or_else(|| anyhow::anyhow!("Profile not found")));
+        let versions = tri!(history::versions(&history::profile_dir(&name)));
+        if versions.is_empty() {
+            Confirm::err(format!("No earlier versions of {name} are kept"));
+            return do_nothing();
+        }
+        let listed: Vec<String> = versions
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                format!(
+                    "{:>2}  {}  {}  {}",
+                    i + 1,
+                    v.stamp,
+                    v.hash,
+                    human_bytes(v.size)
+                )
+            })
+            .collect();
+        let input = tri!(
+            Input::new()
+                .with_title(format!("Rollback {name} to version (empty for 1)"))
+                .with_prompt(listed.join("\n"))
+                .build_and_send()
+                .await,
+            or_cancel
+        );
+        let to = match input.trim() {
+            "" => 1,
+            s => tri!(
+                s.parse::<usize>()
+                    .ok()
+                    .filter(|n| (1..=versions.len()).contains(n))
+                    .ok_or_else(|| anyhow::anyhow!("Pick a version from 1 to {}", versions.len()))
+            ),
+        };
+        let version = tri!(crate::functions::file::profile::rollback(pf, to).await);
+        crate::tui::notice::show(format!(
+            "{name} rolled back to {} ({})",
+            version.stamp, version.hash
+        ));
+
+        let (names, atime) = get_profiles_with_readable_atime();
+        wrapper(move |(content, _): &mut C| {
+            sync_helper(content, names, atime);
+        })
+    }
+
     async fn delete(name: String) -> CB {
         let rx = Confirm::title(format!("Delete profile?"))
             .with_prompt(format!("Delete {name}?\nEnter to confirm, Esc to cancel"))
@@ -680,7 +756,10 @@ mod actions {
                 fetch_traffic_for_url(url, with_proxy);
             }
         }
-        let result = update_profile(db::get(&name).unwrap(), with_proxy).await;
+        let result = update_profile(db::get(&name).unwrap(), with_proxy, async |diff| {
+            review_update(&name, diff).await
+        })
+        .await;
 
         let (names, atime) = get_profiles_with_readable_atime();
         wrapper(move |(content, _): &mut C| {
@@ -704,13 +783,30 @@ mod actions {
         })
     }
 
+    /// Show what the downloaded profile changes and wait for the user's call
+    async fn review_update(
+        name: &str,
+        diff: crate::functions::file::profile::diff::ProfileDiff,
+    ) -> bool {
+        Confirm::title(format!("Apply update of {name}?"))
+            .with_prompt(format!(
+                "{diff}\n\nEnter to apply, Esc to keep the current version"
+            ))
+            .build_and_send()
+            .await
+            .is_ok()
+    }
+
     pub(super) async fn update_all(names: Vec<String>) -> CB {
         let mut results = Vec::with_capacity(names.len());
         for name in &names {
             let with_proxy = db::get(name)
                 .map(|pf| pf.update_with_proxy)
                 .unwrap_or(false);
-            let result = update_profile(db::get(name).unwrap(), with_proxy).await;
+            let result = update_profile(db::get(name).unwrap(), with_proxy, async |diff| {
+                review_update(name, diff).await
+            })
+            .await;
             results.push((name.clone(), result));
         }
 
@@ -963,17 +1059,19 @@ pub(super) fn get_profiles_with_readable_atime() -> (Vec<String>, Vec<String>) {
             let no_pp = pf.no_pp;
             let update_with_proxy = pf.update_with_proxy;
             let is_singbox = pf.dtype == ProfileType::Singbox
-                || crate::config::CONFIG
-                    .data
-                    .lock()
-                    .unwrap()
-                    .contains_in_singbox(&pf.name);
+                || crate::config::CONFIG.data().contains_in_singbox(&pf.name);
             let domain = match &pf.dtype {
                 ProfileType::File => "local import".to_owned(),
                 ProfileType::Url(url) => extract_domain(url).unwrap_or("unknown").to_owned(),
                 ProfileType::Singbox => "singbox profile".to_owned(),
                 ProfileType::Template { .. } => "template".to_owned(),
             };
+            let every = pf
+                .schedule
+                .interval()
+                .filter(|_| matches!(pf.dtype, ProfileType::Url(_)))
+                .map(|hours| format!("|every {hours}h"))
+                .unwrap_or_default();
             let atime = pf
                 .load_local_profile()
                 .ok()
@@ -988,7 +1086,10 @@ pub(super) fn get_profiles_with_readable_atime() -> (Vec<String>, Vec<String>) {
                 ""
             };
             let pxy_str = if update_with_proxy { "proxy" } else { "" };
-            (name, format!("{domain}|{atime}|{no_pp_str}|{pxy_str}"))
+            (
+                name,
+                format!("{domain}|{atime}|{no_pp_str}|{pxy_str}{every}"),
+            )
         })
         .collect();
     composed.sort_unstable();
@@ -1,12 +1,13 @@
 use super::dev::*;
-use crate::config::CONFIG;
-use crate::functions::restful::api_log::{self, LogEntry};
+use crate::functions::export::{self, Format};
+use crate::functions::log_archive;
+use crate::functions::restful::api_log::LogEntry;
 use crate::functions::restful::config;
+use ratatui::layout::Constraint;
 use ratatui::text::Line;
-use ratatui::widgets::{List, ListItem};
+use ratatui::widgets::{Cell, List, ListItem, Row, Table};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
 
 newtype_tab!(LogsTab(Tab<Logs>));
 
@@ -27,6 +28,19 @@ mod_agent!(
         ([KeyCode::Char('p')], Key::TogglePause, "Pause/Resume"),
         ([KeyCode::Char('f')], Key::FzfFind, "Find"),
         ([KeyCode::Char('c')], Key::Clear, "Clear logs"),
+        ([KeyCode::Char('v')], Key::ToggleRaw, "Table/raw view"),
+        ([KeyCode::Char('r')], Key::Replay, "Replay archived log"),
+        ([KeyCode::Char('l')], Key::Live, "Back to live logs"),
+        (
+            [KeyCode::Char('e'), KeyCode::Char('j')],
+            Key::ExportJsonl,
+            "Export as JSONL"
+        ),
+        (
+            [KeyCode::Char('e'), KeyCode::Char('c')],
+            Key::ExportCsv,
+            "Export as CSV"
+        ),
         (
             [KeyCode::Char('t'), KeyCode::Char('d')],
             Key::ToggleDebug,
@@ -65,6 +79,11 @@ pub enum Key {
     TogglePause,
     FzfFind,
     Clear,
+    ToggleRaw,
+    Replay,
+    Live,
+    ExportJsonl,
+    ExportCsv,
     ToggleDebug,
     ToggleInfo,
     ToggleWarning,
@@ -86,6 +105,20 @@ impl TryFrom<&crate::tui::Key> for Key {
 
 const LOG_BUFFER_SIZE: usize = 300;
 
+/// Entries clashtui itself adds, shown along with the core's
+static LOCAL_ENTRIES: Mutex<Vec<LogEntry>> = Mutex::new(Vec::new());
+
+/// Show `payload` in the Logs tab as if the core had logged it
+pub fn push_local(type_: &str, payload: String) {
+    let mut local = LOCAL_ENTRIES.lock().unwrap();
+    if local.len() >= LOG_BUFFER_SIZE {
+        local.remove(0);
+    }
+    let entry = LogEntry::new(type_.to_owned(), payload);
+    log_archive::record(&entry);
+    local.push(entry);
+}
+
 struct LogBuffer {
     entries: [Option<LogEntry>; LOG_BUFFER_SIZE],
     tail: isize,
@@ -144,6 +177,8 @@ impl Default for Logs {
             scroll: 0,
             error: None,
             filter: None,
+            raw: false,
+            replay: None,
             paused: true,
             current_log_level: String::new(),
             ws_pending: None,
@@ -153,11 +188,33 @@ impl Default for Logs {
     }
 }
 
+struct Replay {
+    name: String,
+    entries: Vec<LogEntry>,
+    /// Lowest level shown, set by the level toggles while replaying
+    level: String,
+}
+
+/// Order of the core's log levels, unknown types count as debug
+fn level_rank(level: &str) -> u8 {
+    match level {
+        "info" => 1,
+        "warning" | "warn" => 2,
+        "error" => 3,
+        "silent" => 4,
+        _ => 0,
+    }
+}
+
 struct Logs {
     buffer: LogBuffer,
     scroll: usize,
     error: Option<String>,
     filter: Option<String>,
+    /// One line of text per entry instead of the table
+    raw: bool,
+    /// An archive file shown instead of the live buffer
+    replay: Option<Replay>,
     paused: bool,
     current_log_level: String,
     ws_pending: Option<Arc<Mutex<Vec<LogEntry>>>>,
@@ -166,88 +223,32 @@ struct Logs {
 }
 
 fn spawn_ws_logs(
-    controller: String,
-    secret: Option<String>,
     pending: Arc<Mutex<Vec<LogEntry>>>,
     level: Arc<Mutex<String>>,
     reconnect: Arc<AtomicBool>,
 ) {
-    std::thread::spawn(move || {
-        let ws_scheme = if controller.starts_with("https") {
-            "wss"
-        } else {
-            "ws"
-        };
-        // Strip http(s):// prefix and trailing slash if any
-        let addr = controller
-            .strip_prefix("http://")
-            .or_else(|| controller.strip_prefix("https://"))
-            .unwrap_or(&controller)
-            .trim_end_matches('/');
-
-        loop {
-            let current_level = level.lock().unwrap().clone();
-            reconnect.store(false, Ordering::Relaxed);
-
-            let url_str = if let Some(ref s) = secret {
-                format!("{ws_scheme}://{addr}/logs?token={s}&level={current_level}")
-            } else {
-                format!("{ws_scheme}://{addr}/logs?level={current_level}")
-            };
-
-            match tungstenite::connect(&url_str) {
-                Ok((mut ws, _)) => {
-                    // Set read timeout on inner TcpStream for periodic reconnect checks
-                    if let tungstenite::stream::MaybeTlsStream::Plain(stream) = ws.get_mut() {
-                        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
-                    }
-
-                    loop {
-                        match ws.read() {
-                            Ok(tungstenite::Message::Text(text)) => {
-                                if let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) {
-                                    let type_ = v
-                                        .get("type")
-                                        .and_then(|t| t.as_str())
-                                        .unwrap_or("unknown")
-                                        .to_owned();
-                                    let payload = v
-                                        .get("payload")
-                                        .and_then(|p| p.as_str())
-                                        .unwrap_or("")
-                                        .to_owned();
-                                    pending.lock().unwrap().push(LogEntry {
-                                        type_,
-                                        payload,
-                                        time: api_log::timestamp(),
-                                    });
-                                }
-                            }
-                            Ok(tungstenite::Message::Close(_)) => break,
-                            Err(tungstenite::Error::Io(ref e))
-                                if e.kind() == std::io::ErrorKind::WouldBlock
-                                    || e.kind() == std::io::ErrorKind::TimedOut =>
-                            {
-                                if reconnect.load(Ordering::Relaxed) {
-                                    break;
-                                }
-                                continue;
-                            }
-                            Err(e) => {
-                                log::warn!("WebSocket read error: {e}");
-                                break;
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                Err(e) => {
-                    log::warn!("WebSocket connect error: {e}");
-                }
+    crate::tui::ws::spawn_ws_stream(
+        "/logs",
+        move || format!("level={}", level.lock().unwrap()),
+        reconnect,
+        move |text| {
+            if let Ok(v) = serde_json::from_str::<serde_json::Value>(text) {
+                let type_ = v
+                    .get("type")
+                    .and_then(|t| t.as_str())
+                    .unwrap_or("unknown")
+                    .to_owned();
+                let payload = v
+                    .get("payload")
+                    .and_then(|p| p.as_str())
+                    .unwrap_or("")
+                    .to_owned();
+                let entry = LogEntry::new(type_, payload);
+                log_archive::record(&entry);
+                pending.lock().unwrap().push(entry);
             }
-            std::thread::sleep(Duration::from_secs(2));
-        }
-    });
+        },
+    );
 }
 
 impl BasicTabContent for Logs {
@@ -300,12 +301,14 @@ impl BasicTabContent for Logs {
             let pending = Arc::clone(pending);
             async move {
                 tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                let entries: Vec<LogEntry> = pending.lock().unwrap().drain(..).collect();
+                let mut entries: Vec<LogEntry> = pending.lock().unwrap().drain(..).collect();
+                entries.extend(LOCAL_ENTRIES.lock().unwrap().drain(..));
                 wrapper(move |content: &mut Self| {
                     for entry in entries {
                         content.buffer.push(entry);
                     }
-                    if content.buffer.count() > 0
+                    if content.replay.is_none()
+                        && content.buffer.count() > 0
                         && content.scroll + 1 >= content.buffer.count().saturating_sub(1)
                     {
                         content.scroll = content.buffer.count().saturating_sub(1);
@@ -321,11 +324,9 @@ impl TabContent for Logs {
     fn init(&mut self, task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
         let pending = Arc::new(Mutex::new(Vec::new()));
         self.ws_pending = Some(Arc::clone(&pending));
-        let controller = CONFIG.controller_for_core().to_owned();
-        let secret = CONFIG.secret_for_core().map(|s| s.to_owned());
         let level = Arc::clone(&self.ws_level);
         let reconnect = Arc::clone(&self.ws_reconnect);
-        spawn_ws_logs(controller, secret, pending, level, reconnect);
+        spawn_ws_logs(pending, level, reconnect);
 
         self.error = Some("Press p to start capturing logs".to_owned());
         // Fetch initial log level
@@ -362,7 +363,7 @@ impl TabContent for Logs {
                 self.scroll = self.scroll.saturating_sub(1);
             }
             Key::MoveDown => {
-                if self.scroll + 1 < self.buffer.count() {
+                if self.scroll + 1 < self.visible().count() {
                     self.scroll += 1;
                 }
             }
@@ -370,15 +371,15 @@ impl TabContent for Logs {
                 self.scroll = 0;
             }
             Key::GoBottom => {
-                if !self.buffer.is_empty() {
-                    self.scroll = self.buffer.count().saturating_sub(1);
-                }
+                self.scroll = self.visible().count().saturating_sub(1);
             }
             Key::Search => {
                 async move {
                     let filter = tri!(
                         Input::new()
-                            .with_title("Filter".to_owned())
+                            .with_title(
+                                "Filter (text or level:/src:/dst:/rule:/proxy:/msg:)".to_owned()
+                            )
                             .build_and_send()
                             .await,
                         or_cancel
@@ -399,8 +400,7 @@ impl TabContent for Logs {
             Key::FzfFind => {
                 self.paused = true;
                 let names: Vec<String> = self
-                    .buffer
-                    .iter_from_head()
+                    .visible()
                     .map(|e| format!("{} {} {}", e.time, e.type_, e.payload))
                     .collect();
                 async move {
@@ -422,6 +422,15 @@ impl TabContent for Logs {
                 self.scroll = 0;
                 self.filter = None;
             }
+            Key::ToggleRaw => self.raw = !self.raw,
+            Key::Replay => self.pick_replay(task_set),
+            Key::Live => {
+                if self.replay.take().is_some() {
+                    self.scroll = self.buffer.count().saturating_sub(1);
+                }
+            }
+            Key::ExportJsonl => self.export(Format::Jsonl, task_set),
+            Key::ExportCsv => self.export(Format::Csv, task_set),
             Key::ToggleDebug => self.toggle_log_level("debug", task_set),
             Key::ToggleInfo => self.toggle_log_level("info", task_set),
             Key::ToggleWarning => self.toggle_log_level("warning", task_set),
@@ -436,11 +445,16 @@ impl TabContent for Logs {
             .title(Self::TITLE);
 
         let mut title_parts = Vec::new();
-        title_parts.push(self.current_log_level.clone());
+        if let Some(ref replay) = self.replay {
+            title_parts.push(format!(" replay {} ", replay.name));
+            title_parts.push(replay.level.clone());
+        } else {
+            title_parts.push(self.current_log_level.clone());
+        }
         if let Some(ref filter) = self.filter {
             title_parts.push(format!(" / {filter} "));
         }
-        if self.paused {
+        if self.paused && self.replay.is_none() {
             title_parts.push(" [PAUSED]".to_owned());
         }
         let block = if title_parts.len() > 1 {
@@ -449,34 +463,291 @@ impl TabContent for Logs {
             block
         };
 
-        if !self.error.as_deref().unwrap_or("").is_empty() && self.buffer.is_empty() {
+        if !self.error.as_deref().unwrap_or("").is_empty()
+            && self.buffer.is_empty()
+            && self.replay.is_none()
+        {
             let widget =
                 ratatui::widgets::Paragraph::new(self.error.as_deref().unwrap_or("")).block(block);
             f.render_widget(widget, area);
             return;
         }
 
-        let visible_lines: Vec<ListItem> = self
-            .buffer
-            .iter_from_head()
-            .map(|e| format!("{} {} {}", e.time, e.type_, e.payload))
-            .filter(|line| self.filter.as_deref().is_none_or(|pat| line.contains(pat)))
-            .map(|line| ListItem::new(Line::raw(line)))
-            .collect();
-
         let highlight_style = Theme::get().section("logs").highlight;
-        let list = List::new(visible_lines)
+        if self.raw {
+            let visible_lines: Vec<ListItem> = self
+                .visible()
+                .map(|e| ListItem::new(Line::raw(format!("{} {} {}", e.time, e.type_, e.payload))))
+                .collect();
+            let list = List::new(visible_lines)
+                .block(block)
+                .highlight_style(highlight_style);
+            let mut list_state =
+                ratatui::widgets::ListState::default().with_selected(Some(self.scroll));
+            f.render_stateful_widget(list, area, &mut list_state);
+            return;
+        }
+
+        let header = Row::new(
+            [
+                "Time",
+                "Level",
+                "Net",
+                "Source",
+                "Destination",
+                "Rule",
+                "Proxy",
+                "Message",
+            ]
+            .map(|h| Cell::from(h).style(Theme::get().section("logs").border)),
+        );
+        let rows: Vec<Row> = self.visible().map(table_row).collect();
+        let widths = [
+            Constraint::Length(17),
+            Constraint::Length(7),
+            Constraint::Length(3),
+            Constraint::Fill(2),
+            Constraint::Fill(3),
+            Constraint::Fill(3),
+            Constraint::Fill(3),
+            Constraint::Fill(4),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
             .block(block)
-            .highlight_style(highlight_style);
+            .row_highlight_style(highlight_style);
+        f.render_stateful_widget(
+            table,
+            area,
+            &mut ratatui::widgets::TableState::new().with_selected(Some(self.scroll)),
+        );
+    }
+}
+
+/// Columns of the table; entries of an unknown format only fill Message,
+/// with the raw payload
+fn table_row(e: &LogEntry) -> Row<'_> {
+    let Some(f) = e.fields.as_ref() else {
+        return Row::new([
+            Cell::from(e.time.as_str()),
+            Cell::from(e.type_.as_str()),
+            Cell::default(),
+            Cell::default(),
+            Cell::default(),
+            Cell::default(),
+            Cell::default(),
+            Cell::from(e.payload.as_str()),
+        ]);
+    };
+    let cell = |v: &Option<String>| Cell::from(v.clone().unwrap_or_default());
+    let source = match (&f.source, &f.process) {
+        (Some(src), Some(process)) => format!("{src} ({process})"),
+        (src, _) => src.clone().unwrap_or_default(),
+    };
+    Row::new([
+        Cell::from(e.time.as_str()),
+        Cell::from(e.type_.as_str()),
+        cell(&f.network),
+        Cell::from(source),
+        cell(&f.destination),
+        cell(&f.rule),
+        cell(&f.proxy),
+        cell(&f.message),
+    ])
+}
+
+/// A column a `column:value` filter term can name
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Column {
+    Level,
+    Network,
+    /// The source address and process
+    Source,
+    Destination,
+    Rule,
+    Proxy,
+    /// Whatever is left of a known payload, the whole payload otherwise
+    Message,
+}
+
+impl Column {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "level" | "type" => Self::Level,
+            "net" | "network" => Self::Network,
+            "src" | "source" | "process" => Self::Source,
+            "dst" | "dest" | "destination" => Self::Destination,
+            "rule" => Self::Rule,
+            "proxy" | "chain" | "via" => Self::Proxy,
+            "msg" | "message" => Self::Message,
+            _ => return None,
+        })
+    }
+
+    fn values(self, e: &LogEntry) -> [Option<&str>; 2] {
+        let Some(f) = e.fields.as_ref() else {
+            return match self {
+                Self::Level => [Some(&e.type_), None],
+                Self::Message => [Some(&e.payload), None],
+                _ => [None, None],
+            };
+        };
+        match self {
+            Self::Level => [Some(&e.type_), None],
+            Self::Network => [f.network.as_deref(), None],
+            Self::Source => [f.source.as_deref(), f.process.as_deref()],
+            Self::Destination => [f.destination.as_deref(), None],
+            Self::Rule => [f.rule.as_deref(), None],
+            Self::Proxy => [f.proxy.as_deref(), None],
+            Self::Message => [f.message.as_deref(), None],
+        }
+    }
+}
+
+/// One whitespace separated word of the filter
+#[derive(Debug, PartialEq, Eq)]
+enum Term {
+    /// Contained in the column, ignoring ASCII case
+    Column(Column, String),
+    /// Contained in the `time type payload` line
+    Text(String),
+}
 
-        let mut list_state =
-            ratatui::widgets::ListState::default().with_selected(Some(self.scroll));
-        f.render_stateful_widget(list, area, &mut list_state);
+impl Term {
+    fn parse_all(filter: &str) -> Vec<Term> {
+        filter
+            .split_whitespace()
+            .map(|word| match word.split_once(':') {
+                Some((name, value)) if !value.is_empty() => match Column::from_name(name) {
+                    Some(column) => Term::Column(column, value.to_ascii_lowercase()),
+                    None => Term::Text(word.to_owned()),
+                },
+                _ => Term::Text(word.to_owned()),
+            })
+            .collect()
+    }
+
+    fn matches(&self, e: &LogEntry, line: &str) -> bool {
+        match self {
+            Term::Column(column, pat) => column
+                .values(e)
+                .into_iter()
+                .flatten()
+                .any(|v| v.to_ascii_lowercase().contains(pat.as_str())),
+            Term::Text(pat) => line.contains(pat.as_str()),
+        }
     }
 }
 
 impl Logs {
+    /// Entries of the live buffer or the replay that pass every term of the
+    /// filter, oldest first
+    fn visible(&self) -> impl Iterator<Item = &LogEntry> {
+        let terms = self
+            .filter
+            .as_deref()
+            .map(Term::parse_all)
+            .unwrap_or_default();
+        let (entries, min_level): (Box<dyn Iterator<Item = &LogEntry>>, u8) = match &self.replay {
+            Some(replay) => (Box::new(replay.entries.iter()), level_rank(&replay.level)),
+            None => (Box::new(self.buffer.iter_from_head()), 0),
+        };
+        entries.filter(move |e| {
+            if level_rank(&e.type_) < min_level {
+                return false;
+            }
+            if terms.is_empty() {
+                return true;
+            }
+            let line = format!("{} {} {}", e.time, e.type_, e.payload);
+            terms.iter().all(|t| t.matches(e, &line))
+        })
+    }
+
+    /// Choose an archive file with fzf and show it instead of the live buffer
+    fn pick_replay(&self, task_set: &mut FutureSet<Self>) {
+        async {
+            let dir = crate::config::log_archive_dir();
+            let files = {
+                let dir = dir.clone();
+                tri!(
+                    tokio::task::spawn_blocking(move || log_archive::list(&dir))
+                        .await
+                        .unwrap()
+                )
+            };
+            if files.is_empty() {
+                let _ = crate::tui::widget::popmsg::Confirm::dismiss_any("Replay".to_owned())
+                    .with_prompt(format!("No archived logs in {}", dir.display()))
+                    .build_and_send()
+                    .await;
+                return do_nothing();
+            }
+            let names: Vec<String> = files
+                .iter()
+                .map(|f| format!("{}  {} KiB", f.name, f.size.div_ceil(1024)))
+                .collect();
+            let picked = tokio::task::spawn_blocking(move || {
+                crate::tui::widget::fzffind::run_fzf(&names, "Replay")
+            })
+            .await
+            .unwrap_or(None);
+            let Some(file) = picked.and_then(|i| files.into_iter().nth(i)) else {
+                return do_nothing();
+            };
+            let path = file.path.clone();
+            let entries = tri!(
+                tokio::task::spawn_blocking(move || log_archive::read(&path))
+                    .await
+                    .unwrap()
+            );
+            wrapper(move |content: &mut Logs| {
+                content.replay = Some(Replay {
+                    name: file.name,
+                    entries,
+                    level: "debug".to_owned(),
+                });
+                content.scroll = 0;
+                content.paused = true;
+            })
+        }
+        .spawn_at(task_set);
+    }
+
+    /// Write [Self::visible] to a file in the export dir
+    fn export(&self, format: Format, task_set: &mut FutureSet<Self>) {
+        let entries: Vec<LogEntry> = self.visible().cloned().collect();
+        if entries.is_empty() {
+            return;
+        }
+        async move {
+            let dir = crate::config::export_dir();
+            let count = entries.len();
+            let path = tri!(
+                tokio::task::spawn_blocking(move || {
+                    export::save(&dir, "logs", format, |out| {
+                        export::write_logs(out, format, &entries)
+                    })
+                })
+                .await
+                .unwrap()
+            );
+            let _ = crate::tui::widget::popmsg::Confirm::dismiss_any("Exported".to_owned())
+                .with_prompt(format!("{count} log entries -> {}", path.display()))
+                .build_and_send()
+                .await;
+            do_nothing()
+        }
+        .spawn_at(task_set);
+    }
+
     fn toggle_log_level(&mut self, level: &str, _task_set: &mut FutureSet<Self>) {
+        // A replay filters what it shows, the core keeps its level
+        if let Some(replay) = self.replay.as_mut() {
+            replay.level = level.to_owned();
+            self.scroll = 0;
+            return;
+        }
         if crate::config::is_core_mismatch() {
             return;
         }
@@ -490,6 +761,7 @@ impl Logs {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::functions::restful::api_log;
     use crossterm::event::KeyCode;
 
     fn kev(code: KeyCode, shift: bool) -> crate::tui::Key {
@@ -504,9 +776,8 @@ mod tests {
 
     fn make_entry(type_: &str, payload: &str, time: &str) -> LogEntry {
         LogEntry {
-            type_: type_.to_owned(),
-            payload: payload.to_owned(),
             time: time.to_owned(),
+            ..LogEntry::new(type_.to_owned(), payload.to_owned())
         }
     }
 
@@ -589,6 +860,93 @@ mod tests {
         assert!(logs.filter.is_none());
     }
 
+    #[test]
+    fn visible_follows_filter() {
+        let mut logs = Logs::default();
+        logs.buffer.push(make_entry(
+            "info",
+            "match DomainSuffix",
+            "00-01-01 00:00:00",
+        ));
+        logs.buffer
+            .push(make_entry("warning", "dial failed", "00-01-01 00:00:01"));
+        assert_eq!(logs.visible().count(), 2);
+        logs.filter = Some("warning".to_owned());
+        let visible: Vec<&LogEntry> = logs.visible().collect();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].time, "00-01-01 00:00:01");
+    }
+
+    #[test]
+    fn column_filters_use_parsed_fields() {
+        let mut logs = Logs::default();
+        for payload in [
+            "[TCP] 127.0.0.1:51234(firefox) --> ads.example.com:443 match DomainSuffix(example.com) using REJECT",
+            "[TCP] 127.0.0.1:51240(git) --> github.com:443 match DomainSuffix(github.com) using Entry[vmess-ipdktc33]",
+            "[UDP] dial DIRECT (match Match/) 127.0.0.1:40011 --> 1.1.1.1:53 error: i/o timeout",
+            "Start initial configuration in progress",
+        ] {
+            logs.buffer
+                .push(make_entry("info", payload, "00-01-01 00:00:00"));
+        }
+        let mut hits = |filter: &str| {
+            logs.filter = Some(filter.to_owned());
+            logs.visible().count()
+        };
+        assert_eq!(hits("proxy:reject"), 1);
+        assert_eq!(hits("via:vmess"), 1);
+        assert_eq!(hits("src:git dst:github"), 1);
+        assert_eq!(hits("rule:domainsuffix"), 2);
+        assert_eq!(hits("msg:timeout"), 1);
+        // Unknown formats only have a message
+        assert_eq!(hits("msg:initial"), 1);
+        assert_eq!(hits("proxy:initial"), 0);
+        // Not a column: plain text, case-sensitive as before
+        assert_eq!(hits("127.0.0.1:53"), 0);
+        assert_eq!(hits("1.1.1.1:53"), 1);
+
+        logs.filter = Some("proxy:REJECT".to_owned());
+        let visible: Vec<&LogEntry> = logs.visible().collect();
+        assert_eq!(visible.len(), 1);
+        assert_eq!(
+            visible[0].fields.as_ref().unwrap().process.as_deref(),
+            Some("firefox")
+        );
+    }
+
+    #[test]
+    fn replay_replaces_buffer_and_filters_level() {
+        let mut logs = Logs::default();
+        logs.buffer
+            .push(make_entry("info", "live", "00-01-01 00:00:00"));
+        logs.replay = Some(Replay {
+            name: "core.jsonl".to_owned(),
+            entries: vec![
+                make_entry("debug", "resolving", "26-10-18 12:30:00"),
+                make_entry(
+                    "info",
+                    "[TCP] 127.0.0.1:1 --> a.com:443 match Match using REJECT",
+                    "26-10-18 12:30:01",
+                ),
+                make_entry("error", "dial failed", "26-10-18 12:30:02"),
+            ],
+            level: "debug".to_owned(),
+        });
+        assert_eq!(logs.visible().count(), 3);
+        assert!(logs.visible().all(|e| e.payload != "live"));
+
+        logs.toggle_log_level("info", &mut FutureSet::default());
+        assert_eq!(logs.visible().count(), 2);
+        logs.filter = Some("proxy:reject".to_owned());
+        assert_eq!(logs.visible().count(), 1);
+        // The live level is untouched
+        assert!(logs.current_log_level.is_empty());
+
+        logs.replay = None;
+        logs.filter = None;
+        assert_eq!(logs.visible().next().unwrap().payload, "live");
+    }
+
     #[test]
     fn toggle_pause_flips_state() {
         let mut logs = Logs::default();
@@ -9,6 +9,7 @@ mod dev {
 
     pub use crate::tui::popmsg::prelude::*;
     pub(crate) use crate::tui::theme::Theme;
+    pub(crate) use crate::tui::utils::human_bytes;
 }
 
 use crate::tui::widget::tab::KeyCombo;
@@ -257,6 +258,7 @@ pub(crate) mod connections;
 pub(crate) mod files;
 pub(crate) mod logs;
 pub(crate) mod proxies;
+pub(crate) mod rules;
 pub(crate) mod settings;
 pub(crate) mod srvctl;
 mod status;
@@ -336,6 +338,7 @@ pub mod prelude {
     pub use super::files::FileTab;
     pub use super::logs::LogsTab;
     pub use super::proxies::ProxiesTab;
+    pub use super::rules::RulesTab;
     pub use super::settings::SettingsTab;
     pub use super::srvctl::CoreSrvCtlTab;
     pub use super::status::StatusTab;
@@ -378,6 +381,7 @@ pub mod prelude {
         init_section!(keymap, "srvctl", srvctl);
         init_section!(keymap, "settings", settings);
         init_section!(keymap, "logs", logs);
+        init_section!(keymap, "rules", rules);
 
         // FileTab has nested sections — delegate to files::agent_init
         if let Some(section_val) = crate::tui::agent::take_section(keymap, "file") {
@@ -405,6 +409,15 @@ pub mod prelude {
             CoreSrvCtlTab,
             StatusTab,
             LogsTab,
+            RulesTab,
         }
     );
+
+    impl Tab {
+        /// Works on the local core's service or files, see
+        /// [Config::is_remote](crate::config::Config::is_remote)
+        pub fn is_local_only(&self) -> bool {
+            matches!(self, Self::FileTab(_) | Self::CoreSrvCtlTab(_))
+        }
+    }
 }
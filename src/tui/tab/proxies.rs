@@ -1,5 +1,6 @@
 pub mod content;
 pub mod handlers;
+pub mod providers;
 pub mod render;
 pub mod tree;
 
@@ -74,6 +75,17 @@ mod_agent!(
         ([KeyCode::Char('r')], Key::Refresh, "Refresh"),
         ([KeyCode::Char('f')], Key::GroupSelect, "Group select"),
         ([KeyCode::Char('F')], Key::FzfFind, "Find proxy"),
+        (
+            [KeyCode::Char('P')],
+            Key::ToggleProviders,
+            "Toggle providers pane"
+        ),
+        ([KeyCode::Char('u')], Key::UpdateProvider, "Update provider"),
+        (
+            [KeyCode::Char('U')],
+            Key::UpdateAllProviders,
+            "Update all providers"
+        ),
     ]
 );
 
@@ -100,6 +112,9 @@ pub enum Key {
     Search,
     FzfFind,
     GroupSelect,
+    ToggleProviders,
+    UpdateProvider,
+    UpdateAllProviders,
 }
 
 impl TryFrom<&crate::tui::Key> for Key {
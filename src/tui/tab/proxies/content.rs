@@ -1,9 +1,11 @@
 use super::super::dev::*;
-use crate::functions::restful::proxies::{self};
 use indexmap::IndexMap;
 use std::cell::Cell;
 use std::time::Instant;
 
+use crate::tui::hub;
+
+use super::providers::ProviderPane;
 use super::tree::{NodeItem, NodeType, ProxyTree, SortMode};
 
 #[derive(Default)]
@@ -11,10 +13,18 @@ pub struct Proxies {
     pub tree: ProxyTree,
     pub proxies: IndexMap<String, crate::functions::restful::proxies::Proxy>,
     pub error: Option<String>,
+    /// Why the latest refresh failed, the tree is from before
+    pub stale: Option<String>,
     pub testing_since: Option<Instant>,
     pub jump_target: Cell<Option<usize>>,
     pub filter: Option<String>,
     pub paused: bool,
+    pub provider_pane: ProviderPane,
+    pub hub: hub::Follower,
+    pub proxies_seq: u64,
+    pub interest: Option<hub::Interest>,
+    /// Persisted delay test stats by node name
+    pub latency: std::collections::HashMap<String, crate::functions::latency::NodeStats>,
 }
 
 type SelectionKey = (String, Option<String>, NodeType);
@@ -71,6 +81,9 @@ impl Proxies {
         task_set: &mut FutureSet<Self>,
         state: &mut ListState,
     ) {
+        if self.provider_pane.visible && self.dispatch_provider_key(key, task_set) {
+            return;
+        }
         let current = state.selected().unwrap_or(0);
 
         match key {
@@ -292,6 +305,9 @@ impl Proxies {
                     self.fzf_find_with_prompt(items, &prompt, task_set);
                 }
             }
+            super::Key::ToggleProviders => self.toggle_providers(task_set),
+            super::Key::UpdateProvider => self.update_selected_provider(state, task_set),
+            super::Key::UpdateAllProviders => self.update_all_providers(task_set),
         }
     }
 }
@@ -310,51 +326,52 @@ impl BasicTabContent for Proxies {
         if self.paused {
             return;
         }
-        if crate::config::is_core_mismatch() {
+        let Some(next) = self.hub.next() else {
             return;
-        }
-        async {
-            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            let response = tri!(
-                tokio::task::spawn_blocking(proxies::fetch_proxies)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-            wrapper(|content: &mut Self| {
-                content.proxies = response.proxies;
-                content.tree.rebuild_from_proxies(&content.proxies);
-                content.error = None;
-            })
+        };
+        async move {
+            let state = next.await;
+            wrapper(move |content: &mut Self| content.apply_hub(state))
         }
         .spawn_at(task_set);
     }
 
     fn on_enter(&mut self, task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
         self.paused = false;
+        self.interest = Some(hub::interest(hub::Want::Proxies));
         if crate::config::is_core_mismatch() {
             self.proxies = IndexMap::new();
             self.tree = ProxyTree::default();
             self.error = Some("API data mismatch with configured core".to_owned());
             return;
         }
-        async {
-            let response = tri!(
-                tokio::task::spawn_blocking(proxies::fetch_proxies)
-                    .await
-                    .unwrap()
-            );
-            wrapper(|content: &mut Self| {
-                content.proxies = response.proxies.clone();
-                content.tree = ProxyTree::build(response);
-                content.error = None;
-            })
-        }
-        .spawn_at(task_set);
+        self.after_sync(task_set);
     }
 
     fn on_leave(&mut self, _task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
         self.paused = true;
+        self.interest = None;
+    }
+}
+
+impl Proxies {
+    fn apply_hub(&mut self, state: hub::ControllerState) {
+        self.hub.deliver(&state);
+        if let Some(e) = state.error() {
+            self.error = Some(e);
+            return;
+        }
+        let topic = state.proxies;
+        self.stale = topic.error.clone();
+        if topic.seq == self.proxies_seq {
+            return;
+        }
+        self.proxies_seq = topic.seq;
+        if let Some(response) = topic.data {
+            self.proxies = response.proxies.clone();
+            self.tree.rebuild_from_proxies(&self.proxies);
+            self.error = None;
+        }
     }
 }
 
@@ -379,7 +396,14 @@ impl TabContent for Proxies {
                 state.select(Some(idx));
             }
         }
-        super::render::render(self, f, area, state);
+        if self.provider_pane.visible {
+            use ratatui::layout::{Constraint::Ratio, Layout};
+            let hori = Layout::horizontal([Ratio(3, 5), Ratio(2, 5)]).split(area);
+            super::render::render(self, f, hori[0], state);
+            super::providers::render(&self.provider_pane, f, hori[1]);
+        } else {
+            super::render::render(self, f, area, state);
+        }
     }
 }
 
@@ -559,4 +583,23 @@ mod tests {
         assert!(!display.contains("TCP"));
         assert!(!display.contains("UDP"));
     }
+
+    #[tokio::test]
+    async fn group_delay_test_records_history() {
+        crate::functions::restful::client::use_fixtures();
+        let (mut content, _) = load_fixture();
+        let before = content.proxies["vmess-ipdktc33"].history.len();
+
+        let mut task_set = FutureSet::new();
+        content.test_delay("Sl-pvd0".to_owned(), NodeType::Folder, &mut task_set);
+        assert!(content.testing_since.is_some());
+        let callback = task_set.join_next().await.unwrap().unwrap();
+        callback(&mut content);
+
+        assert!(content.testing_since.is_none());
+        assert!(content.error.is_none());
+        let history = &content.proxies["vmess-ipdktc33"].history;
+        assert_eq!(history.len(), before + 1);
+        assert_eq!(history.last().unwrap().delay, 120);
+    }
 }
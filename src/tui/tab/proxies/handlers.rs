@@ -1,4 +1,6 @@
 use super::super::dev::*;
+use crate::functions::latency;
+use crate::functions::restful::client::client;
 use crate::functions::restful::proxies::{self};
 use std::collections::HashMap;
 use std::time::{Duration, Instant};
@@ -37,7 +39,7 @@ impl Proxies {
 
 impl Proxies {
     pub fn select_inline(&mut self, group: String, node: String, task_set: &mut FutureSet<Self>) {
-        let t_secs = crate::config::CONFIG.cfg_file.timeout.unwrap_or(5).max(1) + 3;
+        let t_secs = client().timeout().max(1) + 3;
         self.error = Some(format!("Switching to {node}..."));
         self.testing_since = Some(Instant::now());
         async move {
@@ -61,9 +63,11 @@ impl Proxies {
                     });
                 }
             };
+            let stats = latency::stats();
             wrapper(move |content: &mut Self| {
                 content.proxies = response.proxies;
                 content.tree.rebuild_from_proxies(&content.proxies);
+                content.latency = stats;
                 content.error = None;
                 content.testing_since = None;
             })
@@ -72,9 +76,14 @@ impl Proxies {
     }
 
     pub fn test_delay(&mut self, name: String, ntype: NodeType, task_set: &mut FutureSet<Self>) {
-        let timeout = crate::config::CONFIG.cfg_file.timeout.unwrap_or(5) * 1000;
+        let timeout = client().timeout() * 1000;
         let test_url = self.proxies.get(&name).and_then(|p| p.test_url.clone());
-        let t_secs = crate::config::CONFIG.cfg_file.timeout.unwrap_or(5).max(1) + 3;
+        let members = self
+            .proxies
+            .get(&name)
+            .and_then(|p| p.all.clone())
+            .unwrap_or_default();
+        let t_secs = client().timeout().max(1) + 3;
 
         match ntype {
             NodeType::Folder => {
@@ -85,7 +94,11 @@ impl Proxies {
                     let delays = match tokio::time::timeout(
                         Duration::from_secs(t_secs),
                         tokio::task::spawn_blocking(move || {
-                            proxies::test_group_delay(&n, test_url.as_deref(), timeout)
+                            let url = test_url.as_deref();
+                            let delays = proxies::test_group_delay(&n, url, timeout)?;
+                            let url = url.unwrap_or(proxies::DEFAULT_TEST_URL);
+                            latency::record(latency::group_samples(&n, &members, url, &delays));
+                            Ok::<_, minreq::Error>(delays)
                         }),
                     )
                     .await
@@ -126,9 +139,11 @@ impl Proxies {
                             }
                         }
                     }
+                    let stats = latency::stats();
                     wrapper(move |content: &mut Self| {
                         content.proxies = response.proxies;
                         content.tree.rebuild_from_proxies(&content.proxies);
+                        content.latency = stats;
                         content.error = None;
                         content.testing_since = None;
                     })
@@ -143,7 +158,11 @@ impl Proxies {
                     let delay = match tokio::time::timeout(
                         Duration::from_secs(t_secs),
                         tokio::task::spawn_blocking(move || {
-                            proxies::test_proxy_delay(&n, test_url.as_deref(), timeout)
+                            let url = test_url.as_deref();
+                            let delay = proxies::test_proxy_delay(&n, url, timeout)?;
+                            let url = url.unwrap_or(proxies::DEFAULT_TEST_URL);
+                            latency::record(vec![latency::Sample::new(&n, None, url, delay)]);
+                            Ok::<_, minreq::Error>(delay)
                         }),
                     )
                     .await
@@ -183,9 +202,11 @@ impl Proxies {
                             proxy.history.push(proxies::DelayRecord { delay: d });
                         }
                     }
+                    let stats = latency::stats();
                     wrapper(move |content: &mut Self| {
                         content.proxies = response.proxies;
                         content.tree.rebuild_from_proxies(&content.proxies);
+                        content.latency = stats;
                         content.error = None;
                         content.testing_since = None;
                     })
@@ -215,21 +236,29 @@ impl Proxies {
             return;
         }
         let proxies_map = self.proxies.clone();
-        let timeout = crate::config::CONFIG.cfg_file.timeout.unwrap_or(5) * 1000;
+        let timeout = client().timeout() * 1000;
         self.error = Some(format!("Testing all ({total} groups/nodes)..."));
         self.testing_since = Some(Instant::now());
         async move {
-            let t_secs = crate::config::CONFIG.cfg_file.timeout.unwrap_or(5).max(1) + 3;
+            let t_secs = client().timeout().max(1) + 3;
             let mut all_delays: HashMap<String, u64> = HashMap::new();
             for name in &folders {
                 let url = proxies_map
                     .get(name.as_str())
                     .and_then(|p| p.test_url.clone());
+                let members = proxies_map
+                    .get(name.as_str())
+                    .and_then(|p| p.all.clone())
+                    .unwrap_or_default();
                 let n = name.clone();
                 match tokio::time::timeout(
                     Duration::from_secs(t_secs),
                     tokio::task::spawn_blocking(move || {
-                        proxies::test_group_delay(&n, url.as_deref(), timeout)
+                        let url = url.as_deref();
+                        let delays = proxies::test_group_delay(&n, url, timeout)?;
+                        let url = url.unwrap_or(proxies::DEFAULT_TEST_URL);
+                        latency::record(latency::group_samples(&n, &members, url, &delays));
+                        Ok::<_, minreq::Error>(delays)
                     }),
                 )
                 .await
@@ -246,7 +275,11 @@ impl Proxies {
                 match tokio::time::timeout(
                     Duration::from_secs(t_secs),
                     tokio::task::spawn_blocking(move || {
-                        proxies::test_proxy_delay(&n, url.as_deref(), timeout)
+                        let url = url.as_deref();
+                        let delay = proxies::test_proxy_delay(&n, url, timeout)?;
+                        let url = url.unwrap_or(proxies::DEFAULT_TEST_URL);
+                        latency::record(vec![latency::Sample::new(&n, None, url, delay)]);
+                        Ok::<_, minreq::Error>(delay)
                     }),
                 )
                 .await
@@ -296,9 +329,11 @@ impl Proxies {
                     .unwrap(),
                 or_set
             );
+            let stats = tokio::task::spawn_blocking(latency::stats).await.unwrap();
             wrapper(move |content: &mut Self| {
                 content.proxies = response.proxies;
                 content.tree.rebuild_from_proxies(&content.proxies);
+                content.latency = stats;
                 content.error = None;
             })
         }
@@ -0,0 +1,349 @@
+use super::super::dev::*;
+use super::content::Proxies;
+use crate::functions::restful::capability::{self, Capability};
+use crate::functions::restful::providers::{self, ProxyProvider};
+use crate::functions::restful::proxies;
+use ratatui::text::Line;
+use ratatui::widgets::{Cell, Row, Table};
+
+/// Proxy-provider pane shown to the right of the proxy tree
+#[derive(Default)]
+pub struct ProviderPane {
+    pub providers: Vec<ProxyProvider>,
+    pub selected: usize,
+    pub visible: bool,
+    pub status: Option<String>,
+}
+
+impl ProviderPane {
+    fn set_providers(&mut self, providers: impl IntoIterator<Item = ProxyProvider>) {
+        self.providers = providers
+            .into_iter()
+            .filter(|p| !p.is_compatible())
+            .collect();
+        if self.selected >= self.providers.len() {
+            self.selected = self.providers.len().saturating_sub(1);
+        }
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        self.providers.get(self.selected).map(|p| p.name.clone())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ProviderOp {
+    Update,
+    HealthCheck,
+}
+
+impl ProviderOp {
+    fn verb(self) -> &'static str {
+        match self {
+            ProviderOp::Update => "Updating",
+            ProviderOp::HealthCheck => "Checking",
+        }
+    }
+
+    fn run(self, name: &str) -> Result<(), minreq::Error> {
+        match self {
+            ProviderOp::Update => providers::update_proxy_provider(name),
+            ProviderOp::HealthCheck => providers::healthcheck_proxy_provider(name),
+        }
+    }
+}
+
+impl Proxies {
+    /// Handle keys while the provider pane has focus.
+    ///
+    /// Returns false for keys that should reach the proxy tree instead.
+    pub(super) fn dispatch_provider_key(
+        &mut self,
+        key: super::Key,
+        task_set: &mut FutureSet<Self>,
+    ) -> bool {
+        let pane = &mut self.provider_pane;
+        match key {
+            super::Key::MoveUp => pane.selected = pane.selected.saturating_sub(1),
+            super::Key::MoveDown => {
+                if pane.selected + 1 < pane.providers.len() {
+                    pane.selected += 1;
+                }
+            }
+            super::Key::GoTop => pane.selected = 0,
+            super::Key::GoBottom => pane.selected = pane.providers.len().saturating_sub(1),
+            super::Key::Refresh => self.refresh_providers(task_set),
+            super::Key::TestDelay => {
+                if let Some(name) = pane.selected_name() {
+                    self.run_provider_op(ProviderOp::HealthCheck, Some(vec![name]), task_set);
+                }
+            }
+            super::Key::TestAllDelay => {
+                self.run_provider_op(ProviderOp::HealthCheck, None, task_set);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    pub(super) fn toggle_providers(&mut self, task_set: &mut FutureSet<Self>) {
+        if !self.provider_pane.visible && !capability::supports(Capability::ProxyProviders) {
+            return crate::tui::widget::popmsg::Confirm::err(
+                "The core's controller has no proxy providers API",
+            );
+        }
+        self.provider_pane.visible = !self.provider_pane.visible;
+        if self.provider_pane.visible {
+            self.refresh_providers(task_set);
+        }
+    }
+
+    /// Update the selected provider: the pane's cursor when it is shown,
+    /// otherwise the provider owning the node under the tree cursor
+    pub(super) fn update_selected_provider(
+        &mut self,
+        state: &ListState,
+        task_set: &mut FutureSet<Self>,
+    ) {
+        let name = if self.provider_pane.visible {
+            self.provider_pane.selected_name()
+        } else {
+            state
+                .selected()
+                .and_then(|i| self.tree.node_at(i))
+                .and_then(|n| self.proxies.get(&n.name))
+                .and_then(|p| p.provider_name.clone())
+        };
+        match name {
+            Some(name) => self.run_provider_op(ProviderOp::Update, Some(vec![name]), task_set),
+            None => crate::tui::widget::popmsg::Confirm::err(
+                "Selected node does not belong to a proxy provider",
+            ),
+        }
+    }
+
+    pub(super) fn update_all_providers(&mut self, task_set: &mut FutureSet<Self>) {
+        self.run_provider_op(ProviderOp::Update, None, task_set);
+    }
+
+    fn refresh_providers(&mut self, task_set: &mut FutureSet<Self>) {
+        async {
+            let all = match tokio::task::spawn_blocking(providers::fetch_proxy_providers)
+                .await
+                .unwrap()
+            {
+                Ok(v) => v,
+                Err(e) => {
+                    return wrapper(move |content: &mut Self| {
+                        content.provider_pane.status = Some(e.to_string());
+                    });
+                }
+            };
+            wrapper(move |content: &mut Self| {
+                content.provider_pane.set_providers(all.into_values());
+                content.provider_pane.status = None;
+            })
+        }
+        .spawn_at(task_set);
+    }
+
+    /// Run `op` on the named providers, or on every provider when `names` is None
+    fn run_provider_op(
+        &mut self,
+        op: ProviderOp,
+        names: Option<Vec<String>>,
+        task_set: &mut FutureSet<Self>,
+    ) {
+        let label = match names.as_deref() {
+            Some([name]) => name.clone(),
+            Some(names) => format!("{} providers", names.len()),
+            None => "all providers".to_owned(),
+        };
+        self.provider_pane.status = Some(format!("{} {label}...", op.verb()));
+        async move {
+            let (failed, all, response) = tokio::task::spawn_blocking(move || {
+                let names = names.unwrap_or_else(|| {
+                    providers::fetch_proxy_providers()
+                        .map(|all| {
+                            all.into_values()
+                                .filter(|p| !p.is_compatible())
+                                .map(|p| p.name)
+                                .collect()
+                        })
+                        .unwrap_or_default()
+                });
+                let failed: Vec<String> = names
+                    .iter()
+                    .filter_map(|n| op.run(n).err().map(|e| format!("{n}: {e}")))
+                    .collect();
+                (
+                    failed,
+                    providers::fetch_proxy_providers(),
+                    proxies::fetch_proxies(),
+                )
+            })
+            .await
+            .unwrap();
+            wrapper(move |content: &mut Self| {
+                if let Ok(all) = all {
+                    content.provider_pane.set_providers(all.into_values());
+                }
+                if let Ok(response) = response {
+                    content.proxies = response.proxies;
+                    content.tree.rebuild_from_proxies(&content.proxies);
+                }
+                content.provider_pane.status = Some(if failed.is_empty() {
+                    format!("{label} done")
+                } else {
+                    format!("Failed: {}", failed.join(", "))
+                });
+            })
+        }
+        .spawn_at(task_set);
+    }
+}
+
+/// `2026-01-02T03:04:05.678+08:00` -> `2026-01-02 03:04`
+fn short_time(rfc3339: &str) -> String {
+    rfc3339.get(..16).unwrap_or(rfc3339).replacen('T', " ", 1)
+}
+
+fn traffic_text(p: &ProxyProvider) -> String {
+    let Some(info) = p.subscription_info.as_ref() else {
+        return "-".to_owned();
+    };
+    let used = human_bytes(info.upload + info.download);
+    if info.total == 0 {
+        used
+    } else {
+        format!("{used}/{}", human_bytes(info.total))
+    }
+}
+
+fn expire_text(p: &ProxyProvider, now_secs: u64) -> String {
+    match p.subscription_info.as_ref().map(|i| i.expire) {
+        None | Some(0) => "-".to_owned(),
+        Some(exp) if exp <= now_secs => "expired".to_owned(),
+        Some(exp) => format!("{}d", (exp - now_secs) / 86400),
+    }
+}
+
+pub fn render(pane: &ProviderPane, f: &mut Frame, area: Rect) {
+    let theme = Theme::get();
+    let section = theme.section("proxies");
+
+    let mut block = Block::bordered()
+        .border_style(section.border)
+        .title("Providers");
+    if let Some(status) = pane.status.as_deref() {
+        block = block.title_bottom(Line::raw(format!(" {status} ")).right_aligned().reversed());
+    }
+
+    if pane.providers.is_empty() {
+        let widget = ratatui::widgets::Paragraph::new("No proxy providers").block(block);
+        f.render_widget(widget, area);
+        return;
+    }
+
+    let now_secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let header = Row::new(
+        ["Name", "Vehicle", "Nodes", "Updated", "Traffic", "Expire"]
+            .into_iter()
+            .map(|h| Cell::from(h).style(section.border)),
+    )
+    .height(1);
+
+    let rows: Vec<Row> = pane
+        .providers
+        .iter()
+        .map(|p| {
+            Row::new(vec![
+                Cell::from(p.name.as_str()),
+                Cell::from(p.vehicle_type.as_str()),
+                Cell::from(p.proxies.len().to_string()),
+                Cell::from(p.updated_at.as_deref().map(short_time).unwrap_or_default()),
+                Cell::from(traffic_text(p)),
+                Cell::from(expire_text(p, now_secs)),
+            ])
+        })
+        .collect();
+
+    let widths = [
+        ratatui::prelude::Constraint::Min(10),
+        ratatui::prelude::Constraint::Max(8),
+        ratatui::prelude::Constraint::Max(6),
+        ratatui::prelude::Constraint::Max(17),
+        ratatui::prelude::Constraint::Max(20),
+        ratatui::prelude::Constraint::Max(8),
+    ];
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(block)
+        .row_highlight_style(section.highlight);
+
+    f.render_stateful_widget(
+        table,
+        area,
+        &mut ratatui::widgets::TableState::default().with_selected(Some(pane.selected)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::functions::restful::providers::SubscriptionInfo;
+
+    fn provider(name: &str, vehicle: &str) -> ProxyProvider {
+        ProxyProvider {
+            name: name.to_owned(),
+            vehicle_type: vehicle.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn set_providers_skips_compatible_and_clamps() {
+        let mut pane = ProviderPane {
+            selected: 5,
+            ..Default::default()
+        };
+        pane.set_providers(vec![
+            provider("default", "Compatible"),
+            provider("a", "HTTP"),
+            provider("b", "File"),
+        ]);
+        let names: Vec<&str> = pane.providers.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        assert_eq!(pane.selected, 1);
+        assert_eq!(pane.selected_name().as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn short_time_trims_rfc3339() {
+        assert_eq!(
+            short_time("2026-01-02T03:04:05.678+08:00"),
+            "2026-01-02 03:04"
+        );
+        assert_eq!(short_time("bogus"), "bogus");
+    }
+
+    #[test]
+    fn traffic_and_expire_text() {
+        let mut p = provider("a", "HTTP");
+        assert_eq!(traffic_text(&p), "-");
+        assert_eq!(expire_text(&p, 0), "-");
+        p.subscription_info = Some(SubscriptionInfo {
+            upload: 512,
+            download: 512,
+            total: 2048,
+            expire: 86400 * 3,
+        });
+        assert_eq!(traffic_text(&p), "1.0 KB/2.0 KB");
+        assert_eq!(expire_text(&p, 0), "3d");
+        assert_eq!(expire_text(&p, 86400 * 4), "expired");
+    }
+}
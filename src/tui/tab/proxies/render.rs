@@ -5,6 +5,19 @@ use crate::tui::theme::Theme;
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, List, ListItem};
 
+/// Sparkline, success rate and p50/p95 of the persisted delay tests
+fn history_summary(stats: &crate::functions::latency::NodeStats) -> String {
+    let mut s = format!(
+        "{} {}%",
+        stats.sparkline(),
+        stats.success_rate().unwrap_or(0)
+    );
+    if let (Some(p50), Some(p95)) = (stats.p50, stats.p95) {
+        s.push_str(&format!(" p50 {p50}ms p95 {p95}ms"));
+    }
+    s
+}
+
 pub fn render(content: &Proxies, f: &mut Frame, area: Rect, state: &mut ListState) {
     let theme = Theme::get();
     let section = theme.section("proxies");
@@ -101,6 +114,9 @@ pub fn render(content: &Proxies, f: &mut Frame, area: Rect, state: &mut ListStat
     if let Some(ref f) = content.filter {
         footer_parts.push(format!("/ {f} "));
     }
+    if let Some(ref e) = content.stale {
+        footer_parts.push(format!("stale: {e} "));
+    }
 
     let footer = footer_parts.join("");
 
@@ -195,6 +211,19 @@ pub fn render(content: &Proxies, f: &mut Frame, area: Rect, state: &mut ListStat
                 spans.push(Span::styled(delay_str, style));
             }
 
+            if node.node_type != NodeType::Folder
+                && let Some(stats) = content.latency.get(&node.name)
+            {
+                spans.push(Span::styled(
+                    format!("  {}", history_summary(stats)),
+                    section
+                        .extra
+                        .get("node_history")
+                        .copied()
+                        .unwrap_or(section.text),
+                ));
+            }
+
             ListItem::new(Line::from(spans))
         })
         .collect();
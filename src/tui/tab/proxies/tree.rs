@@ -46,6 +46,7 @@ impl Default for ProxyTree {
 }
 
 impl ProxyTree {
+    #[cfg(test)]
     pub fn build(response: crate::functions::restful::proxies::ProxiesResponse) -> Self {
         let proxies = response.proxies;
         let mut tree = ProxyTree::default();
@@ -0,0 +1,520 @@
+use super::dev::*;
+use crate::functions::restful::capability::{self, Capability};
+use crate::functions::restful::rules::{self, Rule, RuleProvider};
+use crate::tui::widget::fzffind;
+use indexmap::IndexMap;
+use ratatui::text::Line;
+use ratatui::widgets::{Cell, Row, Table};
+
+newtype_tab!(RulesTab(Tab<Rules>));
+
+mod_agent!(
+    Key,
+    [
+        ([KeyCode::Up], Key::MoveUp, "Move up"),
+        ([KeyCode::Down], Key::MoveDown, "Move down"),
+        ([KeyCode::Char('k')], Key::MoveUp, "Move up"),
+        ([KeyCode::Char('j')], Key::MoveDown, "Move down"),
+        ([KeyCode::Char('G')], Key::GoBottom, "Go to bottom"),
+        (
+            [KeyCode::Char('g'), KeyCode::Char('g')],
+            Key::GoTop,
+            "Go to top"
+        ),
+        ([KeyCode::Char('/')], Key::Search, "Search/Filter"),
+        ([KeyCode::Char('f')], Key::FzfFind, "Find"),
+        ([KeyCode::Char('r')], Key::Refresh, "Refresh"),
+        (
+            [KeyCode::Char('u')],
+            Key::UpdateProvider,
+            "Update rule provider"
+        ),
+        (
+            [KeyCode::Char('U')],
+            Key::UpdateAllProviders,
+            "Update all rule providers"
+        ),
+    ]
+);
+
+#[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub enum Key {
+    MoveUp,
+    MoveDown,
+    GoTop,
+    GoBottom,
+    Search,
+    FzfFind,
+    Refresh,
+    UpdateProvider,
+    UpdateAllProviders,
+}
+
+impl TryFrom<&crate::tui::Key> for Key {
+    type Error = ();
+
+    fn try_from(ev: &crate::tui::Key) -> Result<Self, Self::Error> {
+        let agent = agent();
+        if !agent.is_empty() {
+            return agent.get(ev).copied().ok_or(());
+        }
+        Err(())
+    }
+}
+
+#[derive(Default)]
+struct Rules {
+    rules: Vec<Rule>,
+    providers: IndexMap<String, RuleProvider>,
+    /// index into the filtered view
+    row: Option<usize>,
+    error: Option<String>,
+    filter: Option<String>,
+}
+
+fn rule_matches(rule: &Rule, pat: &str) -> bool {
+    rule.rule_type.contains(pat) || rule.payload.contains(pat) || rule.proxy.contains(pat)
+}
+
+/// `RuleSet` payloads carry the provider size on mihomo
+fn payload_text(rule: &Rule) -> String {
+    match rule.size {
+        Some(size) if size >= 0 => format!("{} ({size})", rule.payload),
+        _ => rule.payload.clone(),
+    }
+}
+
+fn fetch_all() -> Result<(Vec<Rule>, IndexMap<String, RuleProvider>), minreq::Error> {
+    let rules = rules::fetch_rules()?;
+    if !capability::supports(Capability::RuleProviders) {
+        return Ok((rules, IndexMap::new()));
+    }
+    // Provider metadata is optional: keep the rules even if this fails
+    let providers = rules::fetch_rule_providers().unwrap_or_else(|e| {
+        log::warn!("fetch rule providers: {e}");
+        IndexMap::new()
+    });
+    Ok((rules, providers))
+}
+
+impl BasicTabContent for Rules {
+    type Key = Key;
+    type State = ();
+
+    const TITLE: &str = "Rules";
+
+    fn all_shortcuts() -> &'static [(KeyCombo, Self::Key, &'static str)] {
+        agent::all_shortcuts()
+    }
+
+    fn on_enter(&mut self, task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
+        if crate::config::is_core_mismatch() {
+            self.rules = Vec::new();
+            self.providers = IndexMap::new();
+            self.row = None;
+            self.error = Some("API data mismatch with configured core".to_owned());
+            return;
+        }
+        self.refresh(task_set);
+    }
+}
+
+impl TabContent for Rules {
+    fn init(&mut self, _task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
+        self.error = Some("Loading rules...".to_owned());
+    }
+
+    fn handle_key_event(
+        &mut self,
+        key: Key,
+        task_set: &mut FutureSet<Self>,
+        _state: &mut Self::State,
+    ) {
+        let len = self.visible_rules().len();
+        match key {
+            Key::MoveUp => {
+                if let Some(r) = self.row {
+                    self.row = Some(r.saturating_sub(1));
+                } else if len > 0 {
+                    self.row = Some(len - 1);
+                }
+            }
+            Key::MoveDown => {
+                if let Some(r) = self.row {
+                    if r + 1 < len {
+                        self.row = Some(r + 1);
+                    }
+                } else if len > 0 {
+                    self.row = Some(0);
+                }
+            }
+            Key::GoTop => {
+                if len > 0 {
+                    self.row = Some(0);
+                }
+            }
+            Key::GoBottom => {
+                if len > 0 {
+                    self.row = Some(len - 1);
+                }
+            }
+            Key::Search => {
+                async move {
+                    let filter = tri!(
+                        Input::new()
+                            .with_title("Filter".to_owned())
+                            .build_and_send()
+                            .await,
+                        or_cancel
+                    );
+                    wrapper(move |content: &mut Rules| {
+                        content.filter = (!filter.is_empty()).then_some(filter);
+                        content.clamp_row();
+                    })
+                }
+                .spawn_at(task_set);
+            }
+            Key::FzfFind => {
+                let names: Vec<String> = self
+                    .visible_rules()
+                    .iter()
+                    .map(|(_, r)| format!("{} | {} | {}", r.rule_type, r.payload, r.proxy))
+                    .collect();
+                async move {
+                    let selected =
+                        tokio::task::spawn_blocking(move || fzffind::run_fzf(&names, "Find Rule"))
+                            .await
+                            .unwrap_or(None);
+                    wrapper(move |content: &mut Rules| {
+                        if selected.is_some() {
+                            content.row = selected;
+                        }
+                    })
+                }
+                .spawn_at(task_set);
+            }
+            Key::Refresh => self.refresh(task_set),
+            Key::UpdateProvider => match self.selected_provider_name() {
+                Some(name) => self.update_providers(vec![name], task_set),
+                None => crate::tui::widget::popmsg::Confirm::err(
+                    "Selected rule is not backed by a rule provider",
+                ),
+            },
+            Key::UpdateAllProviders => {
+                let names: Vec<String> = self.providers.keys().cloned().collect();
+                if names.is_empty() {
+                    crate::tui::widget::popmsg::Confirm::err("No rule providers");
+                } else {
+                    self.update_providers(names, task_set);
+                }
+            }
+        }
+    }
+
+    fn render(&self, f: &mut Frame, area: Rect, _state: &mut Self::State) {
+        let theme = Theme::get();
+        let section = theme.section("rules");
+        let block = Block::bordered()
+            .border_style(section.border)
+            .title(Self::TITLE);
+
+        if !self.error.as_deref().unwrap_or("").is_empty() && self.rules.is_empty() {
+            let widget =
+                ratatui::widgets::Paragraph::new(self.error.as_deref().unwrap_or("")).block(block);
+            f.render_widget(widget, area);
+            return;
+        }
+
+        let visible = self.visible_rules();
+
+        let count_text = if let Some(filter) = self.filter.as_ref() {
+            format!(" / {filter}  {}/{} rules ", visible.len(), self.rules.len())
+        } else {
+            format!(" {} rules ", self.rules.len())
+        };
+        let mut block = block.title_bottom(Line::raw(count_text).right_aligned().reversed());
+        if let Some(detail) = self.selected_provider_detail(&visible) {
+            block = block.title_bottom(Line::raw(detail).left_aligned());
+        }
+
+        // Hit counts are only reported by mihomo
+        let with_hits = self.rules.iter().any(|r| r.extra.is_some());
+
+        let mut header = vec!["#", "Type", "Payload", "Proxy"];
+        let mut widths = vec![
+            ratatui::prelude::Constraint::Max(6),
+            ratatui::prelude::Constraint::Max(16),
+            ratatui::prelude::Constraint::Min(30),
+            ratatui::prelude::Constraint::Min(15),
+        ];
+        if with_hits {
+            header.push("Hits");
+            widths.push(ratatui::prelude::Constraint::Max(10));
+        }
+        let header = Row::new(
+            header
+                .into_iter()
+                .map(|h| Cell::from(h).style(section.border)),
+        )
+        .height(1);
+
+        let rows: Vec<Row> = visible
+            .iter()
+            .map(|(idx, r)| {
+                let mut cells = vec![
+                    Cell::from(idx.to_string()),
+                    Cell::from(r.rule_type.as_str()),
+                    Cell::from(payload_text(r)),
+                    Cell::from(r.proxy.as_str()),
+                ];
+                if with_hits {
+                    let hits = r
+                        .extra
+                        .as_ref()
+                        .map(|e| e.hit_count.to_string())
+                        .unwrap_or_else(|| "-".to_owned());
+                    cells.push(Cell::from(hits));
+                }
+                let row = Row::new(cells).height(1);
+                if r.extra.as_ref().is_some_and(|e| e.disabled) {
+                    row.style(section.muted)
+                } else {
+                    row
+                }
+            })
+            .collect();
+
+        let table = Table::new(rows, widths)
+            .header(header)
+            .block(block)
+            .row_highlight_style(section.highlight);
+
+        f.render_stateful_widget(
+            table,
+            area,
+            &mut ratatui::widgets::TableState::default().with_selected(self.row),
+        );
+    }
+}
+
+impl Rules {
+    fn refresh(&mut self, task_set: &mut FutureSet<Self>) {
+        async {
+            let (rules, providers) = tri!(
+                tokio::task::spawn_blocking(fetch_all).await.unwrap(),
+                or_set
+            );
+            wrapper(|content: &mut Self| {
+                content.rules = rules;
+                content.providers = providers;
+                content.error = None;
+                content.clamp_row();
+            })
+        }
+        .spawn_at(task_set);
+    }
+
+    fn update_providers(&mut self, names: Vec<String>, task_set: &mut FutureSet<Self>) {
+        async move {
+            let (msg, fetched) = tokio::task::spawn_blocking(move || {
+                let failed: Vec<String> = names
+                    .iter()
+                    .filter_map(|n| {
+                        rules::update_rule_provider(n)
+                            .err()
+                            .map(|e| format!("{n}: {e}"))
+                    })
+                    .collect();
+                let msg = if failed.is_empty() {
+                    format!("Updated {} rule provider(s)", names.len())
+                } else {
+                    format!("Failed to update:\n{}", failed.join("\n"))
+                };
+                (msg, fetch_all())
+            })
+            .await
+            .unwrap();
+            crate::tui::widget::popmsg::Confirm::title("Rule Providers".to_owned())
+                .with_prompt(msg)
+                .build_and_send();
+            let (rules, providers) = tri!(fetched, or_set);
+            wrapper(|content: &mut Self| {
+                content.rules = rules;
+                content.providers = providers;
+                content.error = None;
+                content.clamp_row();
+            })
+        }
+        .spawn_at(task_set);
+    }
+
+    fn selected_provider_name(&self) -> Option<String> {
+        let visible = self.visible_rules();
+        let (_, rule) = visible.get(self.row?)?;
+        self.providers
+            .contains_key(&rule.payload)
+            .then(|| rule.payload.clone())
+    }
+
+    /// Rules passing the current filter, paired with their match order
+    fn visible_rules(&self) -> Vec<(usize, &Rule)> {
+        self.rules
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| {
+                self.filter
+                    .as_deref()
+                    .is_none_or(|pat| rule_matches(r, pat))
+            })
+            .collect()
+    }
+
+    fn clamp_row(&mut self) {
+        let len = self.visible_rules().len();
+        self.row = match self.row {
+            _ if len == 0 => None,
+            Some(r) if r >= len => Some(len - 1),
+            None => Some(0),
+            keep => keep,
+        };
+    }
+
+    /// Summary of the rule provider behind the selected `RuleSet` rule
+    fn selected_provider_detail(&self, visible: &[(usize, &Rule)]) -> Option<String> {
+        let (_, rule) = visible.get(self.row?)?;
+        let provider = self.providers.get(&rule.payload)?;
+        Some(format!(
+            " {}: {} {} {} rules, updated {} ",
+            provider.name,
+            provider.behavior,
+            provider.vehicle_type,
+            provider.rule_count,
+            provider.updated_at.as_deref().unwrap_or("-"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kev(code: KeyCode) -> crate::tui::Key {
+        crate::tui::Key {
+            code,
+            shift: false,
+            ctrl: false,
+            alt: false,
+            super_: false,
+        }
+    }
+
+    fn make_rule(rule_type: &str, payload: &str, proxy: &str) -> Rule {
+        Rule {
+            rule_type: rule_type.to_owned(),
+            payload: payload.to_owned(),
+            proxy: proxy.to_owned(),
+            size: None,
+            extra: None,
+        }
+    }
+
+    fn make_rules() -> Rules {
+        Rules {
+            rules: vec![
+                make_rule("DomainSuffix", "google.com", "Proxy"),
+                make_rule("RuleSet", "cn", "DIRECT"),
+                make_rule("Match", "", "Proxy"),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn key_r_maps_to_refresh() {
+        assert!(matches!(
+            Key::try_from(&kev(KeyCode::Char('r'))),
+            Ok(Key::Refresh)
+        ));
+    }
+
+    #[test]
+    fn key_slash_maps_to_search() {
+        assert!(matches!(
+            Key::try_from(&kev(KeyCode::Char('/'))),
+            Ok(Key::Search)
+        ));
+    }
+
+    #[test]
+    fn filter_matches_type_payload_and_proxy() {
+        let mut r = make_rules();
+        r.filter = Some("Proxy".to_owned());
+        let idx: Vec<usize> = r.visible_rules().iter().map(|(i, _)| *i).collect();
+        assert_eq!(idx, vec![0, 2], "original match order is preserved");
+
+        r.filter = Some("RuleSet".to_owned());
+        assert_eq!(r.visible_rules().len(), 1);
+
+        r.filter = Some("google".to_owned());
+        assert_eq!(r.visible_rules()[0].1.payload, "google.com");
+    }
+
+    #[test]
+    fn clamp_row_follows_filter() {
+        let mut r = make_rules();
+        r.row = Some(2);
+        r.filter = Some("cn".to_owned());
+        r.clamp_row();
+        assert_eq!(r.row, Some(0));
+
+        r.filter = Some("nothing-matches".to_owned());
+        r.clamp_row();
+        assert_eq!(r.row, None);
+
+        r.filter = None;
+        r.clamp_row();
+        assert_eq!(r.row, Some(0));
+    }
+
+    #[test]
+    fn selected_provider_name_requires_known_provider() {
+        let mut r = make_rules();
+        r.providers.insert("cn".to_owned(), RuleProvider::default());
+        r.row = Some(1);
+        assert_eq!(r.selected_provider_name().as_deref(), Some("cn"));
+        r.row = Some(0);
+        assert!(r.selected_provider_name().is_none());
+        r.row = None;
+        assert!(r.selected_provider_name().is_none());
+    }
+
+    #[test]
+    fn payload_text_shows_ruleset_size() {
+        let mut rule = make_rule("RuleSet", "cn", "DIRECT");
+        assert_eq!(payload_text(&rule), "cn");
+        rule.size = Some(-1);
+        assert_eq!(payload_text(&rule), "cn");
+        rule.size = Some(4200);
+        assert_eq!(payload_text(&rule), "cn (4200)");
+    }
+
+    #[test]
+    fn provider_detail_only_for_known_provider() {
+        let mut r = make_rules();
+        r.providers.insert(
+            "cn".to_owned(),
+            RuleProvider {
+                name: "cn".to_owned(),
+                behavior: "Domain".to_owned(),
+                rule_count: 42,
+                vehicle_type: "HTTP".to_owned(),
+                ..Default::default()
+            },
+        );
+        r.row = Some(0);
+        assert!(r.selected_provider_detail(&r.visible_rules()).is_none());
+        r.row = Some(1);
+        let detail = r.selected_provider_detail(&r.visible_rules()).unwrap();
+        assert!(detail.contains("42 rules"));
+    }
+}
@@ -1,5 +1,5 @@
 use super::dev::*;
-use crate::config::CONFIG;
+use crate::tui::hub;
 use ratatui::{
     layout::{Constraint, Layout},
     text::{Line, Span},
@@ -47,7 +47,7 @@ impl TryFrom<&crate::tui::Key> for SettingsKey {
     }
 }
 
-use crate::config::CoreType;
+use crate::functions::restful::capability::{self, Capabilities, Capability};
 use crate::functions::restful::config_struct::{Mode, TunStack};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -58,20 +58,40 @@ enum SettingsOp {
     TunStackOp,
     FlushFakeIP,
     FlushDNSCache,
+    DnsQuery,
     UpdateGeo,
 }
 
 impl SettingsOp {
-    fn all() -> Vec<Self> {
-        let mut ops = vec![Self::SwitchMode, Self::AllowLan];
-        if CONFIG.core_type() == CoreType::Mihomo {
-            ops.push(Self::TunEnable);
-            ops.push(Self::TunStackOp);
-            ops.push(Self::FlushFakeIP);
-            ops.push(Self::FlushDNSCache);
-            ops.push(Self::UpdateGeo);
+    const ALL: [Self; 8] = [
+        Self::SwitchMode,
+        Self::AllowLan,
+        Self::TunEnable,
+        Self::TunStackOp,
+        Self::FlushFakeIP,
+        Self::FlushDNSCache,
+        Self::DnsQuery,
+        Self::UpdateGeo,
+    ];
+
+    /// The controller feature the op needs, `None` for the common ones
+    fn requires(self) -> Option<Capability> {
+        match self {
+            Self::SwitchMode | Self::AllowLan => None,
+            Self::TunEnable | Self::TunStackOp => Some(Capability::Tun),
+            Self::FlushFakeIP => Some(Capability::FlushFakeIp),
+            Self::FlushDNSCache => Some(Capability::FlushDns),
+            Self::DnsQuery => Some(Capability::DnsQuery),
+            Self::UpdateGeo => Some(Capability::UpdateGeo),
         }
-        ops
+    }
+
+    /// Ops the controller described by `caps` can run
+    fn available(caps: &Capabilities) -> Vec<Self> {
+        Self::ALL
+            .into_iter()
+            .filter(|op| op.requires().is_none_or(|cap| caps.supports(cap)))
+            .collect()
     }
 }
 
@@ -88,6 +108,11 @@ struct SettingsContent {
     tun_selector_visible: bool,
     modes: Vec<Mode>,
     tun_stacks: Vec<TunStack>,
+    hub: hub::Follower,
+    config_seq: u64,
+    capabilities_seq: u64,
+    /// Held while the tab is shown
+    interest: Option<hub::Interest>,
 }
 
 impl BasicTabContent for SettingsContent {
@@ -101,19 +126,66 @@ impl BasicTabContent for SettingsContent {
         agent::all_shortcuts()
     }
 
-    fn on_enter(&mut self, _task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
+    fn after_sync(&self, task_set: &mut FutureSet<Self>) {
+        if self.interest.is_none() {
+            return;
+        }
+        let Some(next) = self.hub.next() else {
+            return;
+        };
+        async move {
+            let state = next.await;
+            wrapper(move |c: &mut SettingsContent| c.apply_hub(state))
+        }
+        .spawn_at(task_set);
+    }
+
+    fn on_enter(&mut self, task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
         if crate::config::is_core_mismatch() {
             self.current_mode = "core mismatch".to_owned();
             self.allow_lan = false;
             self.tun_enable = false;
             self.tun_stack = "core mismatch".to_owned();
         }
+        self.ops = SettingsOp::available(&capability::current());
+        self.interest = Some(hub::interest(hub::Want::Config));
+        self.after_sync(task_set);
+    }
+
+    fn on_leave(&mut self, _task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
+        self.interest = None;
+    }
+}
+
+impl SettingsContent {
+    fn apply_hub(&mut self, state: hub::ControllerState) {
+        self.hub.deliver(&state);
+        if state.capabilities.seq != self.capabilities_seq {
+            self.capabilities_seq = state.capabilities.seq;
+            if let Some(caps) = state.capabilities.data.as_deref() {
+                self.ops = SettingsOp::available(caps);
+            }
+        }
+        let topic = state.config;
+        if topic.seq == self.config_seq {
+            return;
+        }
+        self.config_seq = topic.seq;
+        let Some(config) = topic.data else { return };
+        self.current_mode = config.mode.to_string();
+        self.allow_lan = config.allow_lan.unwrap_or(false);
+        self.tun_enable = config.tun.as_ref().map(|t| t.enable).unwrap_or(false);
+        self.tun_stack = config
+            .tun
+            .as_ref()
+            .map(|t| t.stack.to_string())
+            .unwrap_or_else(|| "Mixed".to_owned());
     }
 }
 
 impl TabContent for SettingsContent {
-    fn init(&mut self, task_set: &mut FutureSet<Self>, state: &mut Self::State) {
-        self.ops = SettingsOp::all();
+    fn init(&mut self, _task_set: &mut FutureSet<Self>, state: &mut Self::State) {
+        self.ops = SettingsOp::available(&capability::current());
         self.modes = Mode::VARIANTS.to_vec();
         self.tun_stacks = TunStack::VARIANTS.to_vec();
         self.mode_selector_state.select(Some(0));
@@ -126,37 +198,7 @@ impl TabContent for SettingsContent {
             self.allow_lan = false;
             self.tun_enable = false;
             self.tun_stack = "core mismatch".to_owned();
-            return;
         }
-
-        async move {
-            let result = tokio::task::spawn_blocking(|| crate::functions::restful::config::fetch())
-                .await
-                .unwrap();
-            match result {
-                Ok(config) => {
-                    let mode = config.mode.to_string();
-                    let allow_lan = config.allow_lan.unwrap_or(false);
-                    let tun_enable = config.tun.as_ref().map(|t| t.enable).unwrap_or(false);
-                    let tun_stack = config
-                        .tun
-                        .as_ref()
-                        .map(|t| t.stack.to_string())
-                        .unwrap_or_else(|| "Mixed".to_owned());
-                    wrapper(move |c: &mut SettingsContent| {
-                        c.current_mode = mode;
-                        c.allow_lan = allow_lan;
-                        c.tun_enable = tun_enable;
-                        c.tun_stack = tun_stack;
-                    })
-                }
-                Err(e) => {
-                    crate::tui::widget::popmsg::Confirm::err(e);
-                    do_nothing()
-                }
-            }
-        }
-        .spawn_at(task_set);
     }
 
     fn handle_key_event(
@@ -195,6 +237,7 @@ impl TabContent for SettingsContent {
                             })
                             .await
                             .unwrap();
+                            hub::kick();
                             match result {
                                 Ok(_) => {
                                     let new_val = mode.to_string();
@@ -246,6 +289,7 @@ impl TabContent for SettingsContent {
                             })
                             .await
                             .unwrap();
+                            hub::kick();
                             match result {
                                 Ok(_) => {
                                     let new_val = stack.to_string();
@@ -297,6 +341,7 @@ impl TabContent for SettingsContent {
                             })
                             .await
                             .unwrap();
+                            hub::kick();
                             match result {
                                 Ok(_) => wrapper(move |c: &mut SettingsContent| {
                                     c.allow_lan = new_val;
@@ -325,6 +370,7 @@ impl TabContent for SettingsContent {
                             })
                             .await
                             .unwrap();
+                            hub::kick();
                             match result {
                                 Ok(_) => wrapper(move |c: &mut SettingsContent| {
                                     c.tun_enable = new_val;
@@ -382,6 +428,39 @@ impl TabContent for SettingsContent {
                         }
                         .spawn_at(task_set);
                     }
+                    SettingsOp::DnsQuery => {
+                        async move {
+                            let input = tri!(
+                                Input::new()
+                                    .with_title("DNS query: name [type]".to_owned())
+                                    .build_and_send()
+                                    .await,
+                                or_cancel
+                            );
+                            let mut words = input.split_whitespace();
+                            let Some(name) = words.next().map(str::to_owned) else {
+                                return do_nothing();
+                            };
+                            let qtype = words.next().unwrap_or("A").to_owned();
+                            let result = tokio::task::spawn_blocking(move || {
+                                crate::functions::restful::dns::query(&name, &qtype).map(|r| {
+                                    r.build(&crate::functions::restful::dns::FakeIpRange::current())
+                                })
+                            })
+                            .await
+                            .unwrap();
+                            match result {
+                                Ok(lines) => {
+                                    crate::tui::widget::popmsg::Confirm::title("DNS".to_owned())
+                                        .with_prompt(lines.join("\n"))
+                                        .build_and_send();
+                                }
+                                Err(e) => crate::tui::widget::popmsg::Confirm::err(e),
+                            }
+                            do_nothing()
+                        }
+                        .spawn_at(task_set);
+                    }
                     SettingsOp::UpdateGeo => {
                         async move {
                             if crate::config::is_core_mismatch() {
@@ -438,6 +517,7 @@ impl TabContent for SettingsContent {
                     SettingsOp::TunStackOp => ("TUN Stack", self.tun_stack.as_str()),
                     SettingsOp::FlushFakeIP => ("Flush Fake-IP", ""),
                     SettingsOp::FlushDNSCache => ("Flush DNS Cache", ""),
+                    SettingsOp::DnsQuery => ("DNS Query", ""),
                     SettingsOp::UpdateGeo => ("Update GEO", ""),
                 };
                 ListItem::new(Line::from(vec![
@@ -499,6 +579,7 @@ impl TabContent for SettingsContent {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::CoreType;
 
     fn mk_key(code: KeyCode) -> crate::tui::Key {
         crate::tui::Key {
@@ -557,10 +638,33 @@ mod tests {
 
     #[test]
     fn settings_op_all_is_non_empty() {
-        let ops = SettingsOp::all();
+        let ops = SettingsOp::available(&Capabilities::assumed(CoreType::Mihomo));
         assert!(!ops.is_empty());
         assert!(ops.contains(&SettingsOp::SwitchMode));
         assert!(ops.contains(&SettingsOp::AllowLan));
+        assert!(ops.contains(&SettingsOp::TunEnable));
+    }
+
+    #[test]
+    fn settings_ops_follow_capabilities() {
+        let singbox = SettingsOp::available(&Capabilities::assumed(CoreType::Singbox));
+        assert!(singbox == [SettingsOp::SwitchMode, SettingsOp::AllowLan]);
+
+        let probed = crate::functions::restful::capability::probe(
+            &crate::functions::restful::client::FixtureClient::empty()
+                .route(
+                    minreq::Method::Get,
+                    "/version",
+                    200,
+                    r#"{"version":"sing-box 1.13.11"}"#,
+                )
+                .route(minreq::Method::Post, "/cache/fakeip/flush", 204, ""),
+        )
+        .unwrap();
+        let ops = SettingsOp::available(&probed);
+        assert!(ops.contains(&SettingsOp::FlushFakeIP));
+        assert!(!ops.contains(&SettingsOp::TunEnable));
+        assert!(!ops.contains(&SettingsOp::DnsQuery));
     }
 
     #[test]
@@ -1,9 +1,12 @@
 use super::dev::*;
 use crate::config::CoreType;
+use crate::functions::restful::capability::{self, Capabilities, Capability};
+use crate::functions::restful::control::Upgrade;
 #[cfg(unix)]
 use libc;
 use ratatui::style::Color;
 use ratatui::widgets::ListItem;
+use std::sync::{Arc, Mutex};
 
 newtype_tab!(CoreSrvCtlTab(Tab<SrvCtlContent>));
 
@@ -69,6 +72,9 @@ enum SrvCtlOp {
     Restart,
     SwitchCore,
     StopAll,
+    UpgradeCore,
+    UpgradeUi,
+    UpgradeGeo,
     #[cfg(windows)]
     Install,
     #[cfg(windows)]
@@ -84,6 +90,9 @@ impl SrvCtlOp {
             Self::Restart => "Start Service",
             Self::SwitchCore => "Switch Core",
             Self::StopAll => "Stop All Services",
+            Self::UpgradeCore => "Upgrade Core",
+            Self::UpgradeUi => "Upgrade UI",
+            Self::UpgradeGeo => "Upgrade GEO",
             #[cfg(windows)]
             Self::Install => "Install Srv",
             #[cfg(windows)]
@@ -93,7 +102,15 @@ impl SrvCtlOp {
         }
     }
     fn all() -> Vec<Self> {
-        let mut ops = vec![Self::Stop, Self::Restart, Self::SwitchCore, Self::StopAll];
+        let mut ops = vec![
+            Self::Stop,
+            Self::Restart,
+            Self::SwitchCore,
+            Self::StopAll,
+            Self::UpgradeCore,
+            Self::UpgradeUi,
+            Self::UpgradeGeo,
+        ];
         #[cfg(windows)]
         {
             ops.push(Self::Install);
@@ -102,6 +119,111 @@ impl SrvCtlOp {
         }
         ops
     }
+    /// [all](Self::all) without the upgrades that have no way to run,
+    /// `local` if clashtui manages the core
+    fn available(caps: &Capabilities, local: bool) -> Vec<Self> {
+        Self::all()
+            .into_iter()
+            .filter(|op| op.requires().is_none() || op.via(caps, local).is_some())
+            .collect()
+    }
+    /// The controller feature the op needs, `None` for service ops
+    fn requires(&self) -> Option<Capability> {
+        match self {
+            Self::UpgradeCore => Some(Capability::UpgradeCore),
+            Self::UpgradeUi => Some(Capability::UpgradeUi),
+            Self::UpgradeGeo => Some(Capability::UpgradeGeo),
+            _ => None,
+        }
+    }
+    /// How the upgrade op runs, `None` if it cannot
+    ///
+    /// Only a local mihomo can be replaced by the GitHub release.
+    fn via(&self, caps: &Capabilities, local: bool) -> Option<UpgradeVia> {
+        let cap = self.requires()?;
+        let release = *self == Self::UpgradeCore && local && caps.core == CoreType::Mihomo;
+        match (caps.supports(cap), release) {
+            (true, true) => Some(UpgradeVia::ControllerOrRelease),
+            (true, false) => Some(UpgradeVia::Controller),
+            (false, true) => Some(UpgradeVia::Release),
+            (false, false) => None,
+        }
+    }
+    /// The controller upgrade this op asks for
+    fn upgrade(&self) -> Option<Upgrade> {
+        match self {
+            Self::UpgradeCore => Some(Upgrade::Core { alpha: false }),
+            Self::UpgradeUi => Some(Upgrade::Ui),
+            Self::UpgradeGeo => Some(Upgrade::Geo),
+            _ => None,
+        }
+    }
+}
+
+/// Where an upgrade comes from, see [SrvCtlOp::via]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpgradeVia {
+    /// The controller's `/upgrade` routes
+    Controller,
+    /// The controller, then the GitHub release if that fails
+    ControllerOrRelease,
+    /// The GitHub release, the controller has no `/upgrade`
+    Release,
+}
+
+/// `progress` gets the steps as they happen, the returned message is the outcome
+fn run_upgrade(
+    target: Upgrade,
+    via: UpgradeVia,
+    mut progress: impl FnMut(&str),
+) -> anyhow::Result<String> {
+    use crate::functions::restful::control;
+    use crate::functions::update;
+
+    let err = match via {
+        UpgradeVia::Release => None,
+        UpgradeVia::Controller | UpgradeVia::ControllerOrRelease => {
+            progress("Waiting for the controller");
+            match control::upgrade(target) {
+                Ok(_) => {
+                    return Ok(match target {
+                        Upgrade::Core { .. } => "mihomo upgraded, it restarts itself",
+                        Upgrade::Ui => "External UI upgraded",
+                        Upgrade::Geo => "GeoIP/GeoSite databases upgraded",
+                    }
+                    .to_owned());
+                }
+                Err(e) if via == UpgradeVia::ControllerOrRelease => {
+                    log::warn!("Controller upgrade failed: {e}, trying the GitHub release");
+                    progress("Controller failed, trying the GitHub release");
+                    Some(e)
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    };
+    let controller = err.map_or_else(String::new, |e| format!("Controller: {e}\n"));
+    progress("Checking the latest release");
+    let current = control::version()
+        .map(|v| update::core_version(&v))
+        .unwrap_or_default();
+    let msg = update::mihomo_from_release(
+        &update::release_api(),
+        false,
+        &current,
+        &update::mihomo_path()?,
+        |s| {
+            log::info!("{s}");
+            progress(s);
+        },
+    )
+    .map_err(|e| anyhow::anyhow!("{controller}GitHub: {e}"))?;
+    let hint = if msg.starts_with("Updated") {
+        "\n\nRestart the service to run the new binary."
+    } else {
+        ""
+    };
+    Ok(format!("{controller}GitHub: {msg}{hint}"))
 }
 
 #[derive(Default)]
@@ -118,6 +240,8 @@ struct SrvCtlContent {
     singbox_service_name: String,
     mihomo_is_user: bool,
     singbox_is_user: bool,
+    /// Upgrades still running with their latest step, drawn with a spinner
+    upgrading: Vec<(SrvCtlOp, Arc<Mutex<String>>)>,
     #[cfg(windows)]
     proxy_enabled: bool,
 }
@@ -182,6 +306,37 @@ impl SrvCtlContent {
         }
         .spawn_at(task_set);
     }
+    fn spawn_upgrade(
+        &mut self,
+        op: SrvCtlOp,
+        target: Upgrade,
+        via: UpgradeVia,
+        task_set: &mut FutureSet<Self>,
+    ) {
+        if self.upgrading.iter().any(|(o, _)| *o == op) {
+            return;
+        }
+        let step = Arc::new(Mutex::new(String::new()));
+        self.upgrading.push((op, step.clone()));
+        async move {
+            let result = tokio::task::spawn_blocking(move || {
+                run_upgrade(target, via, |s| *step.lock().unwrap() = s.to_owned())
+            })
+            .await
+            .unwrap();
+            match result {
+                Ok(msg) => {
+                    crate::tui::widget::popmsg::Confirm::title("Upgraded".to_owned())
+                        .with_prompt(msg)
+                        .build_and_send();
+                    crate::tui::hub::kick();
+                }
+                Err(e) => crate::tui::widget::popmsg::Confirm::err(e),
+            }
+            wrapper(move |c: &mut SrvCtlContent| c.upgrading.retain(|(o, _)| *o != op))
+        }
+        .spawn_at(task_set);
+    }
     fn spawn_current_status_check(&self, task_set: &mut FutureSet<Self>) {
         let service_name = self.service_name.clone();
         let is_user = self.is_user;
@@ -297,7 +452,12 @@ impl BasicTabContent for SrvCtlContent {
         agent::all_shortcuts()
     }
 
-    fn on_enter(&mut self, task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
+    fn on_enter(&mut self, task_set: &mut FutureSet<Self>, state: &mut Self::State) {
+        // The hub may have probed the controller since the last visit
+        self.ops = SrvCtlOp::available(&capability::current(), !crate::config::CONFIG.is_remote());
+        if state.selected().is_some_and(|i| i >= self.ops.len()) {
+            state.select(Some(self.ops.len().saturating_sub(1)));
+        }
         self.spawn_status_check(task_set, CoreType::Mihomo);
         self.spawn_status_check(task_set, CoreType::Singbox);
     }
@@ -305,7 +465,7 @@ impl BasicTabContent for SrvCtlContent {
 
 impl TabContent for SrvCtlContent {
     fn init(&mut self, task_set: &mut FutureSet<Self>, state: &mut Self::State) {
-        self.ops = SrvCtlOp::all();
+        self.ops = SrvCtlOp::available(&capability::current(), !crate::config::CONFIG.is_remote());
         let cfg = &crate::config::CONFIG.cfg_file;
 
         self.mihomo_service_name = cfg.mihomo.core_service.service_name.clone();
@@ -378,6 +538,20 @@ impl TabContent for SrvCtlContent {
                 let Some(op) = self.ops.get(idx) else { return };
                 let op = *op;
 
+                if let Some(target) = op.upgrade() {
+                    let local = !crate::config::CONFIG.is_remote();
+                    // The controller may have changed since the list was built
+                    let Some(via) = op.via(&capability::current(), local) else {
+                        crate::tui::widget::popmsg::Confirm::err(format!(
+                            "The controller cannot {}",
+                            op.as_str().to_lowercase()
+                        ));
+                        return;
+                    };
+                    self.spawn_upgrade(op, target, via, task_set);
+                    return;
+                }
+
                 let _bin_path = self.bin_path.clone();
                 let needs_sudo = !self.is_user;
 
@@ -449,6 +623,9 @@ impl TabContent for SrvCtlContent {
                                 "inactive"
                             )
                         }
+                        SrvCtlOp::UpgradeCore | SrvCtlOp::UpgradeUi | SrvCtlOp::UpgradeGeo => {
+                            unreachable!("upgrades are spawned without sudo")
+                        }
                         SrvCtlOp::SwitchCore => {
                             let old_type = crate::config::CONFIG.core_type();
                             let new_type = match old_type {
@@ -464,8 +641,9 @@ impl TabContent for SrvCtlContent {
                             let stop_result = crate::functions::command::stop_all_services(pw_ref);
 
                             match (|| -> anyhow::Result<()> {
-                                crate::config::CONFIG.data.lock().unwrap().core_type = new_type;
-                                crate::config::CONFIG.save()
+                                let mut pm = crate::config::CONFIG.data_mut();
+                                pm.core_type = new_type;
+                                pm.to_file()
                             })() {
                                 Ok(()) => {
                                     // start the target core
@@ -645,10 +823,26 @@ impl TabContent for SrvCtlContent {
                 ratatui::text::Line::from(spans).right_aligned()
             });
 
+        let spinner_chars = ['/', '-', '\\', '|'];
+        let spinner_idx = (crate::tui::app::SPINNER_FRAME.load(std::sync::atomic::Ordering::Relaxed)
+            as usize
+            / 8)
+            % 4;
         let items: Vec<ListItem> = self
             .ops
             .iter()
-            .map(|op| ListItem::new(format!("  {}", op.as_str())))
+            .map(|op| {
+                if let Some((_, step)) = self.upgrading.iter().find(|(o, _)| o == op) {
+                    ListItem::new(format!(
+                        "{} {} ... {}",
+                        spinner_chars[spinner_idx],
+                        op.as_str(),
+                        step.lock().unwrap()
+                    ))
+                } else {
+                    ListItem::new(format!("  {}", op.as_str()))
+                }
+            })
             .collect();
 
         let highlight_style = section.highlight;
@@ -732,6 +926,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn srvctl_upgrade_ops_are_mihomo_only() {
+        let mihomo = SrvCtlOp::available(&Capabilities::assumed(CoreType::Mihomo), false);
+        assert!(mihomo.contains(&SrvCtlOp::UpgradeCore));
+        assert!(mihomo.contains(&SrvCtlOp::UpgradeGeo));
+        let singbox = SrvCtlOp::available(&Capabilities::assumed(CoreType::Singbox), true);
+        assert!(singbox.iter().all(|op| op.upgrade().is_none()));
+        assert!(singbox.contains(&SrvCtlOp::StopAll));
+        assert_eq!(SrvCtlOp::UpgradeUi.upgrade(), Some(Upgrade::Ui));
+    }
+
+    #[test]
+    fn srvctl_upgrade_ops_follow_probe() {
+        use crate::functions::restful::{capability, client::FixtureClient};
+        use minreq::Method;
+        // A mihomo build without the UI upgrade route
+        let client = FixtureClient::empty()
+            .route(Method::Get, "/version", 200, r#"{"version":"v1.19.2"}"#)
+            .route(Method::Post, "/upgrade", 200, "")
+            .route(Method::Post, "/upgrade/geo", 200, "");
+        let ops = SrvCtlOp::available(&capability::probe(&client).unwrap(), false);
+        assert!(ops.contains(&SrvCtlOp::UpgradeCore));
+        assert!(ops.contains(&SrvCtlOp::UpgradeGeo));
+        assert!(!ops.contains(&SrvCtlOp::UpgradeUi));
+    }
+
+    #[test]
+    fn srvctl_upgrade_core_without_route_uses_release() {
+        use crate::functions::restful::{capability, client::FixtureClient};
+        use minreq::Method;
+        // A mihomo build without `/upgrade`, the probe gets a 404
+        let client = FixtureClient::empty()
+            .route(Method::Get, "/version", 200, r#"{"version":"v1.18.0"}"#)
+            .route(Method::Post, "/upgrade/geo", 200, "");
+        let caps = capability::probe(&client).unwrap();
+        assert!(!caps.supports(Capability::UpgradeCore));
+
+        let local = SrvCtlOp::available(&caps, true);
+        assert!(local.contains(&SrvCtlOp::UpgradeCore));
+        assert_eq!(
+            SrvCtlOp::UpgradeCore.via(&caps, true),
+            Some(UpgradeVia::Release)
+        );
+        assert_eq!(
+            SrvCtlOp::UpgradeGeo.via(&caps, true),
+            Some(UpgradeVia::Controller)
+        );
+        // Nothing to replace on a remote controller
+        assert!(!SrvCtlOp::available(&caps, false).contains(&SrvCtlOp::UpgradeCore));
+
+        let caps = Capabilities::assumed(CoreType::Mihomo);
+        assert_eq!(
+            SrvCtlOp::UpgradeCore.via(&caps, true),
+            Some(UpgradeVia::ControllerOrRelease)
+        );
+        assert_eq!(
+            SrvCtlOp::UpgradeCore.via(&caps, false),
+            Some(UpgradeVia::Controller)
+        );
+    }
+
     #[test]
     fn srvctl_op_as_str_unique() {
         let ops = SrvCtlOp::all();
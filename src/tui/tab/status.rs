@@ -1,4 +1,10 @@
-use ratatui::{text::Text, widgets::Paragraph};
+use ratatui::{
+    text::Text,
+    widgets::{Paragraph, Sparkline},
+};
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
 
 use super::dev::*;
 
@@ -17,37 +23,140 @@ impl TryFrom<&crate::tui::Key> for Key {
 
 use crate::config::CONFIG;
 use crate::config::CoreType;
-use crate::functions::restful::{self, config_struct::*};
-
-macro_rules! tri {
-    ($e:expr) => {
-        match $e {
-            Ok(v) => v,
-            Err(e) => {
-                crate::tui::widget::popmsg::Confirm::err(e);
-                return do_nothing();
-            }
+use crate::functions::restful::config_struct::*;
+use crate::functions::restful::traffic::{Memory, Traffic};
+use crate::tui::hub::{self, Health};
+
+/// Samples kept per graph, one per second
+const HISTORY_LEN: usize = 120;
+
+#[derive(Default)]
+struct Series(VecDeque<u64>);
+
+impl Series {
+    fn push(&mut self, v: u64) {
+        if self.0.len() == HISTORY_LEN {
+            self.0.pop_front();
         }
-    };
-    ($e:expr, or_set) => {
-        match $e {
-            Ok(v) => v,
-            Err(e) => {
-                return wrapper(move |content: &mut Self| {
-                    content.error = Some(e.to_string());
-                });
-            }
+        self.0.push_back(v);
+    }
+
+    fn last(&self) -> u64 {
+        self.0.back().copied().unwrap_or(0)
+    }
+
+    fn peak(&self) -> u64 {
+        self.0.iter().copied().max().unwrap_or(0)
+    }
+
+    fn avg(&self) -> u64 {
+        if self.0.is_empty() {
+            0
+        } else {
+            self.0.iter().sum::<u64>() / self.0.len() as u64
         }
-    };
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Filled by the WS /traffic and /memory threads
+#[derive(Default)]
+struct Graphs {
+    up: Series,
+    down: Series,
+    memory: Series,
+    /// [controller_generation](crate::config::controller_generation) the samples are from
+    generation: u64,
+}
+
+impl Graphs {
+    /// The graphs for samples of controller `generation`, cleared if the
+    /// samples so far came from another one
+    fn of(&mut self, generation: u64) -> &mut Self {
+        if self.generation != generation {
+            *self = Self {
+                generation,
+                ..Default::default()
+            };
+        }
+        self
+    }
 }
 
 #[derive(Default)]
 struct Status {
-    config: Option<ClashConfig>,
+    config: Option<Arc<ClashConfig>>,
     version: Option<String>,
     detected_core_type: Option<CoreType>,
     error: Option<String>,
+    /// Why the latest `/configs` fetch failed, `config` is from before
+    stale: Option<String>,
     paused: bool,
+    graphs: Arc<Mutex<Graphs>>,
+    hub: hub::Follower,
+    interest: Option<hub::Interest>,
+}
+
+fn spawn_ws_graphs(graphs: &Arc<Mutex<Graphs>>) {
+    let g = Arc::clone(graphs);
+    crate::tui::ws::spawn_ws_stream(
+        "/traffic",
+        String::new,
+        Arc::new(AtomicBool::new(false)),
+        move |text| {
+            if let Ok(t) = serde_json::from_str::<Traffic>(text) {
+                let mut g = g.lock().unwrap();
+                let g = g.of(crate::config::controller_generation());
+                g.up.push(t.up);
+                g.down.push(t.down);
+            }
+        },
+    );
+
+    let g = Arc::clone(graphs);
+    crate::tui::ws::spawn_ws_stream(
+        "/memory",
+        String::new,
+        Arc::new(AtomicBool::new(false)),
+        move |text| {
+            if let Ok(m) = serde_json::from_str::<Memory>(text) {
+                g.lock()
+                    .unwrap()
+                    .of(crate::config::controller_generation())
+                    .memory
+                    .push(m.inuse);
+            }
+        },
+    );
+}
+
+/// Bordered sparkline titled with the current, peak and average value
+fn render_series(f: &mut Frame, area: Rect, name: &str, unit: &str, series: &Series) {
+    let theme = Theme::get();
+    let section = theme.section("status");
+    let title = format!(
+        " {name} {}{unit}  peak {}{unit}  avg {}{unit} ",
+        human_bytes(series.last()),
+        human_bytes(series.peak()),
+        human_bytes(series.avg()),
+    );
+    // Show the newest samples that fit
+    let width = area.width.saturating_sub(2) as usize;
+    let skip = series.0.len().saturating_sub(width);
+    let data: Vec<u64> = series.0.iter().skip(skip).copied().collect();
+    let widget = Sparkline::default()
+        .block(
+            Block::bordered()
+                .border_style(section.muted)
+                .title(title)
+                .title_style(section.title),
+        )
+        .style(section.border)
+        .data(&data);
+    f.render_widget(widget, area);
 }
 
 impl BasicTabContent for Status {
@@ -61,42 +170,12 @@ impl BasicTabContent for Status {
         if self.paused {
             return;
         }
-        async {
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
-            let version = tri!(
-                tokio::task::spawn_blocking(restful::control::version)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-            let config = tri!(
-                tokio::task::spawn_blocking(restful::config::fetch)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-            let detected = tri!(
-                tokio::task::spawn_blocking(restful::core_detect::detect_core_type)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-
-            wrapper(move |content: &mut Self| {
-                let configured = CONFIG.core_type();
-                content.detected_core_type = Some(detected);
-                if detected == configured {
-                    content.version = Some(version);
-                    content.config = Some(config);
-                    crate::config::set_core_mismatch(false);
-                } else {
-                    content.error = Some(format!(
-                        "API returned {detected} data, but {configured} is configured"
-                    ));
-                    crate::config::set_core_mismatch(true);
-                }
-            })
+        let Some(next) = self.hub.next() else {
+            return;
+        };
+        async move {
+            let state = next.await;
+            wrapper(move |content: &mut Self| content.apply_hub(state))
         }
         .spawn_at(task_set);
     }
@@ -117,47 +196,43 @@ impl BasicTabContent for Status {
             }
         }
 
-        async {
-            let detected = tri!(
-                tokio::task::spawn_blocking(restful::core_detect::detect_core_type)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-            let version = tri!(
-                tokio::task::spawn_blocking(restful::control::version)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-            let config = tri!(
-                tokio::task::spawn_blocking(restful::config::fetch)
-                    .await
-                    .unwrap(),
-                or_set
-            );
-
-            wrapper(move |content: &mut Self| {
-                let configured = CONFIG.core_type();
-                content.detected_core_type = Some(detected);
-                let mismatch = detected != configured;
-                crate::config::set_core_mismatch(mismatch);
-                if mismatch {
-                    let msg =
-                        format!("API returned {detected} data, but {configured} is configured");
-                    content.error = Some(msg);
-                } else {
-                    content.version = Some(version);
-                    content.config = Some(config);
-                    content.error = None;
-                }
-            })
-        }
-        .spawn_at(task_set);
+        self.interest = Some(hub::interest(hub::Want::Config));
+        self.after_sync(task_set);
     }
 
     fn on_leave(&mut self, _task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
         self.paused = true;
+        self.interest = None;
+    }
+}
+
+impl Status {
+    fn apply_hub(&mut self, state: hub::ControllerState) {
+        self.hub.deliver(&state);
+        match state.health {
+            Health::Up => {
+                if let Some(v) = state.version.data.as_deref() {
+                    self.detected_core_type = Some(v.core);
+                    self.version = Some(v.raw.clone());
+                }
+                if let Some(config) = state.config.data {
+                    self.config = Some(config);
+                }
+                self.stale = state.config.error;
+                self.error = None;
+            }
+            Health::Mismatch { detected, .. } => {
+                self.detected_core_type = Some(detected);
+                self.error = state.error();
+            }
+            Health::Unknown | Health::Down { .. } => {
+                self.detected_core_type = None;
+                self.version = None;
+                self.config = None;
+                self.stale = None;
+                self.error = state.error();
+            }
+        }
     }
 }
 
@@ -165,6 +240,7 @@ impl TabContent for Status {
     fn init(&mut self, _task_set: &mut FutureSet<Self>, _state: &mut Self::State) {
         self.paused = true;
         self.error = Some("Waiting".to_owned());
+        spawn_ws_graphs(&self.graphs);
     }
 
     fn handle_key_event(
@@ -195,6 +271,9 @@ impl TabContent for Status {
             if let Some(ref ver) = self.version {
                 lines.push(format!("version: {ver}"));
             }
+            if let Some(e) = &self.stale {
+                lines.push(format!("stale: {e}"));
+            }
             if let Some(cfg) = self.config.as_ref() {
                 lines.extend(cfg.build());
             }
@@ -207,7 +286,79 @@ impl TabContent for Status {
                     .expect("if there is not content, there should be an error"),
             );
         }
+        if let Some(endpoint) = CONFIG.active_controller() {
+            lines.insert(
+                0,
+                format!("controller: {} ({})", endpoint.name, endpoint.address),
+            );
+        }
         let widget = Paragraph::new(Text::from_iter(lines)).block(block);
-        f.render_widget(widget, area);
+
+        let graphs = self.graphs.lock().unwrap();
+        // Until the new controller sends a frame the samples are of the old one
+        if graphs.down.is_empty() && graphs.memory.is_empty()
+            || graphs.generation != crate::config::controller_generation()
+        {
+            f.render_widget(widget, area);
+            return;
+        }
+        use ratatui::layout::{Constraint, Layout};
+        let [info, down, up, memory] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(5),
+        ])
+        .areas(area);
+        f.render_widget(widget, info);
+        render_series(f, down, "Download", "/s", &graphs.down);
+        render_series(f, up, "Upload", "/s", &graphs.up);
+        render_series(f, memory, "Memory", "", &graphs.memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn series_stats() {
+        let mut s = Series::default();
+        assert_eq!((s.last(), s.peak(), s.avg()), (0, 0, 0));
+        for v in [10, 30, 20] {
+            s.push(v);
+        }
+        assert_eq!(s.last(), 20);
+        assert_eq!(s.peak(), 30);
+        assert_eq!(s.avg(), 20);
+    }
+
+    #[test]
+    fn series_is_bounded() {
+        let mut s = Series::default();
+        for v in 0..(HISTORY_LEN as u64 + 10) {
+            s.push(v);
+        }
+        assert_eq!(s.0.len(), HISTORY_LEN);
+        assert_eq!(s.0.front().copied(), Some(10));
+    }
+
+    #[test]
+    fn graphs_restart_for_another_controller() {
+        let mut g = Graphs::default();
+        g.of(0).down.push(10);
+        g.of(0).memory.push(20);
+        assert_eq!(g.down.last(), 10);
+        g.of(1).down.push(30);
+        assert_eq!(g.down.0, [30]);
+        assert!(g.memory.is_empty());
+    }
+
+    #[test]
+    fn parse_ws_samples() {
+        let t: Traffic = serde_json::from_str(r#"{"up":12,"down":34}"#).unwrap();
+        assert_eq!((t.up, t.down), (12, 34));
+        let m: Memory = serde_json::from_str(r#"{"inuse":1024,"oslimit":0}"#).unwrap();
+        assert_eq!(m.inuse, 1024);
     }
 }
@@ -76,6 +76,7 @@ pub(crate) struct ThemeFile {
     pub settings: Option<SectionPaletteDef>,
     pub srvctl: Option<SectionPaletteDef>,
     pub logs: Option<SectionPaletteDef>,
+    pub rules: Option<SectionPaletteDef>,
     pub status: Option<SectionPaletteDef>,
 }
 
@@ -200,6 +201,14 @@ fn make_default_palette() -> SectionPaletteDef {
             bold: false,
         },
     );
+    extra.insert(
+        "node_history".into(),
+        StyleDef {
+            fg: Some(Color::Rgb(140, 140, 140)),
+            bg: None,
+            bold: false,
+        },
+    );
 
     SectionPaletteDef {
         border: Some(StyleDef {
@@ -233,6 +242,7 @@ const SECTION_NAMES: &[&str] = &[
     "settings",
     "srvctl",
     "logs",
+    "rules",
     "status",
     "file",
 ];
@@ -292,6 +302,7 @@ impl TryFrom<ThemeFile> for Theme {
             ("settings", file.settings.as_ref()),
             ("srvctl", file.srvctl.as_ref()),
             ("logs", file.logs.as_ref()),
+            ("rules", file.rules.as_ref()),
             ("status", file.status.as_ref()),
             ("file", None), // always uses defaults, not user-configurable
         ];
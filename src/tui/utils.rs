@@ -31,3 +31,19 @@ pub mod raw_mode {
         }));
     }
 }
+
+/// `1536` -> `1.5 KB`, binary units
+pub(crate) fn human_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit_idx = 0;
+    while size >= 1024.0 && unit_idx < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit_idx += 1;
+    }
+    if unit_idx == 0 {
+        format!("{size:.0} {unit}", size = size, unit = UNITS[unit_idx])
+    } else {
+        format!("{size:.1} {unit}", size = size, unit = UNITS[unit_idx])
+    }
+}
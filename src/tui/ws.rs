@@ -0,0 +1,272 @@
+//! Long-lived WebSocket streams from the controller (`/logs`, `/traffic`, ...)
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use tungstenite::WebSocket;
+
+use crate::config::{CONFIG, controller_generation};
+use crate::functions::restful::tls;
+
+/// Build `ws(s)://addr/path?token=..&query` from an http(s) controller address
+///
+/// For a `unix://` controller the host is only used in the handshake.
+pub fn ws_url(controller: &str, secret: Option<&str>, path: &str, query: &str) -> String {
+    let ws_scheme = if controller.starts_with("https") {
+        "wss"
+    } else {
+        "ws"
+    };
+    // Strip http(s):// prefix and trailing slash if any
+    let addr = if controller.starts_with("unix://") {
+        "localhost"
+    } else {
+        controller
+            .strip_prefix("http://")
+            .or_else(|| controller.strip_prefix("https://"))
+            .unwrap_or(controller)
+            .trim_end_matches('/')
+    };
+
+    let mut params: Vec<String> = Vec::new();
+    if let Some(s) = secret {
+        params.push(format!("token={s}"));
+    }
+    if !query.is_empty() {
+        params.push(query.to_owned());
+    }
+    if params.is_empty() {
+        format!("{ws_scheme}://{addr}{path}")
+    } else {
+        format!("{ws_scheme}://{addr}{path}?{}", params.join("&"))
+    }
+}
+
+/// Keep a WebSocket to `path` of the active controller connected on a
+/// background thread and hand every text frame to `on_text`.
+///
+/// `query` is evaluated on each (re)connect; set `reconnect` to force a
+/// reconnect, e.g. after the query changed. Selecting another controller
+/// reconnects as well.
+pub fn spawn_ws_stream<Q, F>(
+    path: &'static str,
+    query: Q,
+    reconnect: Arc<AtomicBool>,
+    mut on_text: F,
+) where
+    Q: Fn() -> String + Send + 'static,
+    F: FnMut(&str) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        loop {
+            let generation = controller_generation();
+            let controller = CONFIG.controller_for_core();
+            let url_str = ws_url(controller, CONFIG.secret_for_core(), path, &query());
+            reconnect.store(false, Ordering::Relaxed);
+            let stop =
+                || reconnect.load(Ordering::Relaxed) || controller_generation() != generation;
+
+            if let Some(sock) = controller.strip_prefix("unix://") {
+                match connect_unix(sock, &url_str) {
+                    Ok(mut ws) => read_frames(&mut ws, path, &stop, &mut on_text),
+                    Err(e) => log::warn!("WebSocket {path} connect error: {e}"),
+                }
+            } else if let Some((host, port)) = tls::host_port(controller) {
+                match connect_tls(host, port, &url_str) {
+                    Ok(mut ws) => read_frames(&mut ws, path, &stop, &mut on_text),
+                    Err(e) => log::warn!("WebSocket {path} connect error: {e}"),
+                }
+            } else {
+                match tungstenite::connect(&url_str) {
+                    Ok((mut ws, _)) => {
+                        // Set read timeout on inner TcpStream for periodic reconnect checks
+                        if let tungstenite::stream::MaybeTlsStream::Plain(stream) = ws.get_mut() {
+                            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+                        }
+                        read_frames(&mut ws, path, &stop, &mut on_text);
+                    }
+                    Err(e) => {
+                        log::warn!("WebSocket {path} connect error: {e}");
+                    }
+                }
+            }
+            std::thread::sleep(Duration::from_secs(2));
+        }
+    });
+}
+
+/// `wss://` with the trust from `controller_tls`, see [tls::client_config]
+fn connect_tls(host: &str, port: u16, url: &str) -> std::io::Result<WebSocket<tls::TlsStream>> {
+    let stream = tls::connect(
+        tls::controller_config()?,
+        host,
+        port,
+        Duration::from_secs(5),
+    )?;
+    tungstenite::client(url, stream)
+        .map(|(ws, _)| ws)
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+#[cfg(unix)]
+fn connect_unix(
+    sock: &str,
+    url: &str,
+) -> std::io::Result<WebSocket<std::os::unix::net::UnixStream>> {
+    let stream = std::os::unix::net::UnixStream::connect(sock)?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    tungstenite::client(url, stream)
+        .map(|(ws, _)| ws)
+        .map_err(|e| std::io::Error::other(e.to_string()))
+}
+
+#[cfg(not(unix))]
+fn connect_unix(sock: &str, _url: &str) -> std::io::Result<WebSocket<std::net::TcpStream>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("unix socket controller {sock} is not supported on this platform"),
+    ))
+}
+
+/// Read until the stream closes, fails or `stop` returns true
+fn read_frames<S: Read + Write>(
+    ws: &mut WebSocket<S>,
+    path: &str,
+    stop: &impl Fn() -> bool,
+    on_text: &mut impl FnMut(&str),
+) {
+    loop {
+        match ws.read() {
+            Ok(tungstenite::Message::Text(text)) => on_text(&text),
+            Ok(tungstenite::Message::Close(_)) => break,
+            Err(tungstenite::Error::Io(ref e))
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                if stop() {
+                    break;
+                }
+                continue;
+            }
+            Err(e) => {
+                log::warn!("WebSocket {path} read error: {e}");
+                break;
+            }
+            _ => {}
+        }
+        if stop() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ws_url_with_secret_and_query() {
+        assert_eq!(
+            ws_url("http://127.0.0.1:9090", Some("abc"), "/logs", "level=info"),
+            "ws://127.0.0.1:9090/logs?token=abc&level=info"
+        );
+    }
+
+    #[test]
+    fn ws_url_https_and_trailing_slash() {
+        assert_eq!(
+            ws_url("https://example.com/", None, "/traffic", ""),
+            "wss://example.com/traffic"
+        );
+    }
+
+    #[test]
+    fn ws_url_query_without_secret() {
+        assert_eq!(
+            ws_url("127.0.0.1:9090", None, "/connections", "interval=1000"),
+            "ws://127.0.0.1:9090/connections?interval=1000"
+        );
+    }
+
+    #[test]
+    fn ws_url_unix_socket_uses_localhost() {
+        assert_eq!(
+            ws_url(
+                "unix:///run/mihomo.sock",
+                Some("abc"),
+                "/logs",
+                "level=info"
+            ),
+            "ws://localhost/logs?token=abc&level=info"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stream_frames_over_unix_socket() {
+        use std::os::unix::net::UnixListener;
+
+        let sock = std::env::temp_dir().join(format!("clashtui-ws-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&sock);
+        let listener = UnixListener::bind(&sock).unwrap();
+        let server = std::thread::spawn(move || {
+            let (s, _) = listener.accept().unwrap();
+            let mut ws = tungstenite::accept(s).unwrap();
+            ws.send(tungstenite::Message::text(
+                r#"{"type":"info","payload":"hi"}"#,
+            ))
+            .unwrap();
+            ws.close(None).unwrap();
+            // Drive the close handshake to completion
+            while ws.read().is_ok() {}
+        });
+
+        let url = ws_url("unix:///ignored", None, "/logs", "level=info");
+        let mut ws = connect_unix(sock.to_str().unwrap(), &url).unwrap();
+        let mut frames = Vec::new();
+        read_frames(&mut ws, "/logs", &|| false, &mut |t: &str| {
+            frames.push(t.to_owned())
+        });
+        drop(ws);
+        server.join().unwrap();
+        let _ = std::fs::remove_file(&sock);
+        assert_eq!(frames, [r#"{"type":"info","payload":"hi"}"#]);
+    }
+
+    #[test]
+    fn stream_frames_over_pinned_tls() {
+        use crate::config::ControllerTls;
+        use crate::functions::restful::tls::tests::{CONTROLLER_PIN, serve_once};
+
+        let (port, server) = serve_once(|s| {
+            let mut ws = tungstenite::accept(s).unwrap();
+            ws.send(tungstenite::Message::text("{\"up\":1,\"down\":2}"))
+                .unwrap();
+            ws.close(None).unwrap();
+            while ws.read().is_ok() {}
+        });
+        let config = tls::client_config(&ControllerTls {
+            ca_file: None,
+            pin_sha256: Some(CONTROLLER_PIN.to_owned()),
+        })
+        .unwrap();
+        let stream = tls::connect(config, "127.0.0.1", port, Duration::from_secs(5)).unwrap();
+        let url = ws_url(
+            &format!("https://127.0.0.1:{port}"),
+            Some("s3cret"),
+            "/traffic",
+            "",
+        );
+        assert!(url.starts_with("wss://"));
+        let (mut ws, _) = tungstenite::client(url, stream).unwrap();
+        let mut frames = Vec::new();
+        read_frames(&mut ws, "/traffic", &|| false, &mut |t: &str| {
+            frames.push(t.to_owned())
+        });
+        drop(ws);
+        server.join().unwrap();
+        assert_eq!(frames, [r#"{"up":1,"down":2}"#]);
+    }
+}